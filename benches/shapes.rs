@@ -0,0 +1,47 @@
+//! Compares the binary shape encoding against polylines, run via `cargo bench --bench shapes`
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use valhalla_client::shapes::{
+    decode_polyline, from_bytes, to_bytes, to_polyline5, to_polyline6, ShapePoint,
+};
+
+/// The average time of running `f` repeatedly for about a second
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    let shape: Vec<_> = (0..10_000)
+        .map(|i| ShapePoint {
+            lon: 4.9041 + f64::from(i) * 0.000_13,
+            lat: 52.3676 - f64::from(i % 100) * 0.000_07,
+        })
+        .collect();
+    let bytes = to_bytes(&shape);
+    let polyline5 = to_polyline5(&shape);
+    let polyline6 = to_polyline6(&shape);
+    println!(
+        "size: bytes {} B, polyline6 {} B, polyline5 {} B (rounded to 5 decimal places)",
+        bytes.len(),
+        polyline6.len(),
+        polyline5.len()
+    );
+    println!(
+        "encode: bytes {:?}, polyline6 {:?}, polyline5 {:?}",
+        time(|| drop(black_box(to_bytes(black_box(&shape))))),
+        time(|| drop(black_box(to_polyline6(black_box(&shape))))),
+        time(|| drop(black_box(to_polyline5(black_box(&shape))))),
+    );
+    println!(
+        "decode: bytes {:?}, polyline6 {:?}, polyline5 {:?}",
+        time(|| drop(black_box(from_bytes(black_box(&bytes))))),
+        time(|| drop(black_box(decode_polyline(black_box(&polyline6), 6)))),
+        time(|| drop(black_box(decode_polyline(black_box(&polyline5), 5)))),
+    );
+}
@@ -0,0 +1,283 @@
+//! Banner instructions as displayed by navigation SDKs
+//!
+//! Converts a [`Maneuver`] and its [`crate::route::Sign`] into the primary/secondary banner
+//! structure of the Mapbox and MapLibre navigation SDKs.
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::banner::Banner;
+//! use valhalla_client::route::{Location, Manifest};
+//!
+//! let manifest = Manifest::builder()
+//!   .locations([Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)]);
+//! let trip = Valhalla::default().route(manifest).unwrap();
+//! for maneuver in &trip.legs[0].maneuvers {
+//!   let banner = Banner::from(maneuver);
+//!   println!("{:?} {:?}: {}", banner.primary.type_, banner.primary.modifier, banner.primary.text);
+//! }
+//! ```
+use crate::route::{Maneuver, ManeuverType, SignElement};
+use serde::{Deserialize, Serialize};
+
+/// The instructions displayed while approaching a maneuver
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Banner {
+    /// The main instruction, e.g. the road to turn onto
+    pub primary: BannerInstruction,
+    /// Additional context, e.g. the locations an exit leads toward
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secondary: Option<BannerInstruction>,
+}
+
+/// One line of a [`Banner`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BannerInstruction {
+    /// The plain text of all [`Self::components`]
+    pub text: String,
+    #[serde(rename = "type")]
+    pub type_: BannerType,
+    /// The direction of the maneuver, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modifier: Option<BannerModifier>,
+    /// The parts of the text, e.g. to style exit numbers differently
+    pub components: Vec<BannerComponent>,
+}
+
+/// The kind of maneuver a [`BannerInstruction`] describes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BannerType {
+    Depart,
+    Arrive,
+    Turn,
+    Continue,
+    #[serde(rename = "new name")]
+    NewName,
+    Merge,
+    #[serde(rename = "on ramp")]
+    OnRamp,
+    #[serde(rename = "off ramp")]
+    OffRamp,
+    Fork,
+    Roundabout,
+    #[serde(rename = "exit roundabout")]
+    ExitRoundabout,
+    /// Any other maneuver, e.g. boarding a ferry or transit
+    Notification,
+}
+
+/// The direction of a maneuver
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BannerModifier {
+    Uturn,
+    #[serde(rename = "sharp right")]
+    SharpRight,
+    Right,
+    #[serde(rename = "slight right")]
+    SlightRight,
+    Straight,
+    #[serde(rename = "slight left")]
+    SlightLeft,
+    Left,
+    #[serde(rename = "sharp left")]
+    SharpLeft,
+}
+
+/// A part of the text of a [`BannerInstruction`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BannerComponent {
+    pub text: String,
+    #[serde(rename = "type")]
+    pub type_: BannerComponentType,
+}
+
+/// How a [`BannerComponent`] is displayed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BannerComponentType {
+    /// Regular text, e.g. a road name
+    Text,
+    /// An exit number, usually displayed as a badge
+    ExitNumber,
+    /// Separates alternative names, e.g. `/`
+    Delimiter,
+}
+
+impl From<ManeuverType> for BannerType {
+    fn from(type_: ManeuverType) -> Self {
+        use ManeuverType as M;
+        match type_ {
+            M::Start | M::StartRight | M::StartLeft => Self::Depart,
+            M::Destination | M::DestinationRight | M::DestinationLeft => Self::Arrive,
+            M::Becomes => Self::NewName,
+            M::Continue | M::StayStraight => Self::Continue,
+            M::SlightRight
+            | M::Right
+            | M::SharpRight
+            | M::UturnRight
+            | M::UturnLeft
+            | M::SharpLeft
+            | M::Left
+            | M::SlightLeft => Self::Turn,
+            M::RampStraight | M::RampRight | M::RampLeft => Self::OnRamp,
+            M::ExitRight | M::ExitLeft => Self::OffRamp,
+            M::StayRight | M::StayLeft => Self::Fork,
+            M::Merge | M::MergeRight | M::MergeLeft => Self::Merge,
+            M::RoundaboutEnter => Self::Roundabout,
+            M::RoundaboutExit => Self::ExitRoundabout,
+            _ => Self::Notification,
+        }
+    }
+}
+
+impl BannerModifier {
+    /// The direction of a maneuver, `None` for maneuvers without one, e.g. boarding a ferry
+    pub fn of(type_: ManeuverType) -> Option<Self> {
+        use ManeuverType as M;
+        Some(match type_ {
+            M::UturnRight | M::UturnLeft => Self::Uturn,
+            M::SharpRight => Self::SharpRight,
+            M::Right | M::StartRight | M::DestinationRight | M::MergeRight => Self::Right,
+            M::SlightRight | M::RampRight | M::ExitRight | M::StayRight => Self::SlightRight,
+            M::Continue | M::Becomes | M::StayStraight | M::RampStraight | M::Merge => {
+                Self::Straight
+            }
+            M::SlightLeft | M::RampLeft | M::ExitLeft | M::StayLeft => Self::SlightLeft,
+            M::Left | M::StartLeft | M::DestinationLeft | M::MergeLeft => Self::Left,
+            M::SharpLeft => Self::SharpLeft,
+            _ => return None,
+        })
+    }
+}
+
+impl From<&Maneuver> for Banner {
+    /// The primary instruction names the signed branches, the street names or, lacking both, the
+    /// written instruction. The secondary instruction names the signed toward locations.
+    fn from(maneuver: &Maneuver) -> Self {
+        let type_ = BannerType::from(maneuver.type_);
+        let modifier = BannerModifier::of(maneuver.type_);
+        let sign = maneuver.sign.clone().unwrap_or_default();
+        let names = maneuver
+            .begin_street_names
+            .as_ref()
+            .or(maneuver.street_names.as_ref())
+            .filter(|names| !names.is_empty());
+
+        let mut components: Vec<_> = sign
+            .exit_number_elements
+            .iter()
+            .map(|e| BannerComponent {
+                text: e.text.clone(),
+                type_: BannerComponentType::ExitNumber,
+            })
+            .collect();
+        if !sign.exit_branch_elements.is_empty() {
+            components.extend(text_components(&texts(&sign.exit_branch_elements)));
+        } else if let Some(names) = names {
+            components.extend(text_components(names));
+        } else {
+            components.extend(text_components(std::slice::from_ref(&maneuver.instruction)));
+        }
+        let primary = instruction(components, type_, modifier);
+
+        let secondary = (!sign.exit_toward_elements.is_empty()).then(|| {
+            let components = text_components(&texts(&sign.exit_toward_elements));
+            instruction(components, type_, modifier)
+        });
+        Self { primary, secondary }
+    }
+}
+
+fn texts(elements: &[SignElement]) -> Vec<String> {
+    elements.iter().map(|e| e.text.clone()).collect()
+}
+
+/// Text components separated by delimiters
+fn text_components(texts: &[String]) -> Vec<BannerComponent> {
+    let mut components = Vec::new();
+    for (i, text) in texts.iter().enumerate() {
+        if i > 0 {
+            components.push(BannerComponent {
+                text: "/".to_string(),
+                type_: BannerComponentType::Delimiter,
+            });
+        }
+        components.push(BannerComponent {
+            text: text.clone(),
+            type_: BannerComponentType::Text,
+        });
+    }
+    components
+}
+
+fn instruction(
+    components: Vec<BannerComponent>,
+    type_: BannerType,
+    modifier: Option<BannerModifier>,
+) -> BannerInstruction {
+    let text = components
+        .iter()
+        .map(|c| c.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    BannerInstruction {
+        text,
+        type_,
+        modifier,
+        components,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn maneuver(value: serde_json::Value) -> Maneuver {
+        let mut maneuver = serde_json::json!({
+            "instruction": "Drive north.", "time": 45.0, "length": 0.75,
+            "begin_shape_index": 0, "end_shape_index": 1, "travel_mode": "drive",
+        });
+        maneuver
+            .as_object_mut()
+            .unwrap()
+            .extend(value.as_object().unwrap().clone());
+        serde_json::from_value(maneuver).unwrap()
+    }
+    #[test]
+    fn from_maneuver() {
+        let banner = Banner::from(&maneuver(serde_json::json!({"type": 1})));
+        assert_eq!(banner.primary.text, "Drive north.");
+        assert_eq!(banner.primary.type_, BannerType::Depart);
+        assert_eq!(banner.primary.modifier, None);
+        assert_eq!(banner.secondary, None);
+
+        let banner = Banner::from(&maneuver(serde_json::json!({
+            "type": 15, "street_names": ["Main Street", "US 1"],
+        })));
+        assert_eq!(banner.primary.text, "Main Street / US 1");
+        assert_eq!(banner.primary.modifier, Some(BannerModifier::Left));
+        assert_eq!(banner.primary.components.len(), 3);
+
+        let banner = Banner::from(&maneuver(serde_json::json!({
+            "type": 20, "street_names": ["A 1"],
+            "sign": {
+                "exit_number_elements": [{"text": "22A"}],
+                "exit_branch_elements": [{"text": "I 95 North", "is_route_number": true}],
+                "exit_toward_elements": [{"text": "New York"}, {"text": "Boston", "consecutive_count": 1}],
+            },
+        })));
+        assert_eq!(banner.primary.text, "22A I 95 North");
+        assert_eq!(
+            banner.primary.components[0].type_,
+            BannerComponentType::ExitNumber
+        );
+        assert_eq!(banner.primary.type_, BannerType::OffRamp);
+        assert_eq!(banner.primary.modifier, Some(BannerModifier::SlightRight));
+        assert_eq!(banner.secondary.unwrap().text, "New York / Boston");
+        assert_eq!(
+            serde_json::to_value(BannerType::OffRamp).unwrap(),
+            serde_json::json!("off ramp")
+        );
+    }
+}
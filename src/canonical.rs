@@ -0,0 +1,79 @@
+//! Stable identities of requests, e.g. to key external caches or deduplicate requests
+//!
+//! # Example:
+//! ```rust
+//! use valhalla_client::canonical;
+//! use valhalla_client::route::{Location, Manifest};
+//!
+//! let manifest = Manifest::builder()
+//!   .locations([Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)]);
+//! let key = canonical::hash(&manifest).unwrap();
+//! assert_eq!(key, canonical::hash(&manifest.clone()).unwrap());
+//! ```
+use serde::Serialize;
+use serde_json::Value;
+
+/// Floating point numbers are rounded to this many decimal places (roughly `1 cm` for coordinates)
+const DECIMALS: i32 = 7;
+
+/// Serializes a request to a canonical JSON string
+///
+/// Two requests which valhalla treats identically serialize to the same string:
+/// - object keys are sorted,
+/// - floating point numbers are rounded to 7 decimal places, and
+/// - integral floating point numbers are written as integers, i.e. `1.0` becomes `1`.
+pub fn to_string(request: &impl Serialize) -> Result<String, serde_json::Error> {
+    let mut value = serde_json::to_value(request)?;
+    normalize(&mut value);
+    serde_json::to_string(&value)
+}
+
+/// A hash of the [canonical serialization](to_string) of a request
+///
+/// Uses [FNV-1a](https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function),
+/// so the hash is stable across platforms, processes and versions of Rust.
+pub fn hash(request: &impl Serialize) -> Result<u64, serde_json::Error> {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    let canonical = to_string(request)?;
+    Ok(canonical.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    }))
+}
+
+fn normalize(value: &mut Value) {
+    match value {
+        // keys of `serde_json::Map` are already sorted
+        Value::Object(map) => map.values_mut().for_each(normalize),
+        Value::Array(values) => values.iter_mut().for_each(normalize),
+        Value::Number(number) if number.is_f64() => {
+            let factor = 10_f64.powi(DECIMALS);
+            let rounded = number
+                .as_f64()
+                .map_or(0.0, |n| (n * factor).round() / factor);
+            *value = if rounded.fract() == 0.0 && rounded.abs() < 2_f64.powi(53) {
+                Value::from(rounded as i64)
+            } else {
+                Value::from(rounded)
+            };
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn canonical_serialisation() {
+        let a = serde_json::json!({"b": [1.0, 0.1_f32], "a": -0.0, "c": 52.367_612_345});
+        let b = serde_json::json!({"a": 0, "c": 52.367_612_3, "b": [1, 0.1]});
+        assert_eq!(
+            to_string(&a).unwrap(),
+            r#"{"a":0,"b":[1,0.1],"c":52.3676123}"#
+        );
+        assert_eq!(to_string(&a).unwrap(), to_string(&b).unwrap());
+        assert_eq!(hash(&a).unwrap(), hash(&b).unwrap());
+        assert_ne!(hash(&a).unwrap(), hash(&serde_json::json!({})).unwrap());
+    }
+}
@@ -0,0 +1,185 @@
+//! The least-cost meeting point of several locations, e.g. where friends from different places can meet
+//!
+//! See <https://valhalla.github.io/valhalla/api/centroid/api-reference/> for details
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::centroid::Manifest;
+//! use valhalla_client::costing::Costing;
+//! use valhalla_client::route::Location;
+//!
+//! let manifest = Manifest::builder()
+//!   .locations([
+//!     Location::new(4.9041, 52.3676),
+//!     Location::new(5.1214, 52.0907),
+//!     Location::new(4.4777, 51.9244),
+//!   ])
+//!   .costing(Costing::Auto(Default::default()));
+//! let centroid = Valhalla::default().centroid(manifest).unwrap();
+//! println!("meet at {:?}", centroid.point());
+//! for trip in &centroid.trips {
+//!   println!("{:?} to get there", trip.total_time());
+//! }
+//! ```
+use crate::costing;
+use crate::route::{DateTime, DirectionsType, Location, Trip};
+use crate::shapes::{Shape, ShapeFormat, ShapePoint, ShapeRepresentation};
+use serde::{Deserialize, Serialize};
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Default, Debug, Clone)]
+pub struct Manifest {
+    #[serde(flatten)]
+    costing: Option<costing::Costing>,
+    locations: Vec<Location>,
+    units: Option<crate::Units>,
+    id: Option<String>,
+    language: Option<String>,
+    directions_type: Option<DirectionsType>,
+    date_time: Option<DateTime>,
+}
+
+impl Manifest {
+    #[must_use]
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Configures the costing model used for the paths to the meeting point
+    ///
+    /// Default: [`costing::Costing::Auto`]
+    pub fn costing(mut self, costing: costing::Costing) -> Self {
+        self.costing = Some(costing);
+        self
+    }
+    /// Sets the locations to meet from
+    ///
+    /// Minimum number of locations: 2
+    pub fn locations(mut self, locations: impl IntoIterator<Item = Location>) -> Self {
+        self.locations = locations.into_iter().collect();
+        debug_assert!(self.locations.len() >= 2);
+        self
+    }
+    /// Sets the distance units for output, see [`crate::route::Manifest::units`]
+    ///
+    /// Default: [`crate::Units::Metric`]
+    pub fn units(mut self, units: crate::Units) -> Self {
+        self.units = Some(units);
+        self
+    }
+    /// Name your centroid request.
+    ///
+    /// If id is specified, the naming will be sent through to the response.
+    pub fn id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+    /// The language of the narration instructions, see [`crate::route::Manifest::language`]
+    ///
+    /// Default: `en-US` (United States-based English)
+    pub fn language(mut self, language: impl ToString) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+    /// Sets the directions type, see [`crate::route::Manifest::directions_type`]
+    ///
+    /// Default: [`DirectionsType::Instructions`]
+    pub fn directions_type(mut self, directions_type: DirectionsType) -> Self {
+        self.directions_type = Some(directions_type);
+        self
+    }
+    /// Sets the date and time of departure from all locations, see [`DateTime`]
+    ///
+    /// Default: time independent paths
+    pub fn date_time(mut self, date_time: DateTime) -> Self {
+        self.date_time = Some(date_time);
+        self
+    }
+}
+
+/// The response of the centroid endpoint, using the format of a route with alternates
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(
+    serialize = "S: ShapeRepresentation",
+    deserialize = "S: ShapeRepresentation + Default"
+))]
+pub(crate) struct Response<S = Vec<ShapePoint>> {
+    trip: Trip<S>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    alternates: Vec<crate::route::Response<S>>,
+}
+
+impl Response<Shape> {
+    /// Decodes the shapes of all trips, given the [`ShapeFormat`] they were requested in
+    pub(crate) fn into_centroid(self, format: ShapeFormat) -> Centroid {
+        let response = crate::route::Response::from(self.trip);
+        let trips = std::iter::once(response)
+            .chain(self.alternates)
+            .map(|response| response.into_trip().decode(format))
+            .collect();
+        Centroid { trips }
+    }
+}
+
+/// The meeting point of the locations of a [`Manifest`] together with the paths to it
+#[derive(Debug, Clone)]
+pub struct Centroid {
+    /// One trip from each location to the meeting point, in the order of [`Manifest::locations`]
+    pub trips: Vec<Trip>,
+}
+
+impl Centroid {
+    /// The meeting point, i.e. where the paths of all [`Self::trips`] end
+    pub fn point(&self) -> Option<&ShapePoint> {
+        self.trips.first()?.legs.last()?.shape.last()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn deserialisation() {
+        let trip = |shape: &str| {
+            serde_json::json!({
+                "status": 0, "status_message": "Found route between points",
+                "units": "kilometers", "language": "en-US",
+                "locations": [{"lat": 52.0, "lon": 4.0}, {"lat": 52.5, "lon": 4.5}],
+                "legs": [{
+                    "summary": {
+                        "time": 90.0, "length": 1.5, "has_toll": false, "has_highway": false, "has_ferry": false,
+                        "min_lat": 52.0, "min_lon": 4.0, "max_lat": 53.0, "max_lon": 5.0,
+                    },
+                    "maneuvers": [],
+                    "shape": shape,
+                }],
+                "summary": {
+                    "time": 90.0, "length": 1.5, "has_toll": false, "has_highway": false, "has_ferry": false,
+                    "min_lat": 52.0, "min_lon": 4.0, "max_lat": 53.0, "max_lon": 5.0,
+                },
+            })
+        };
+        let response: Response<Shape> = serde_json::from_value(serde_json::json!({
+            "trip": trip("_izlhA~rlgdF_{geC~ywl@"),
+            "alternates": [{"trip": trip("_izlhA~rlgdF_{geC~ywl@")}],
+        }))
+        .unwrap();
+        let centroid = response.clone().into_centroid(ShapeFormat::Polyline6);
+        assert_eq!(centroid.trips.len(), 2);
+        assert_eq!(
+            centroid.point(),
+            centroid.trips[1].legs[0].shape.last(),
+            "all trips end at the meeting point"
+        );
+        assert_eq!(
+            centroid.trips[1].legs[0].summary.units,
+            crate::Units::Metric
+        );
+
+        // the same polyline is ten times as large with one decimal place less
+        let polyline5 = response.into_centroid(ShapeFormat::Polyline5);
+        let (p6, p5) = (centroid.point().unwrap(), polyline5.point().unwrap());
+        assert!((p5.lon - 10.0 * p6.lon).abs() < 1e-9);
+        assert!((p5.lat - 10.0 * p6.lat).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,136 @@
+//! How closely a driven trip followed a planned one, e.g. for audits of delivery routes
+//!
+//! Compare the planned [`Trip`] with the trip matched to the GPS trace of the drive,
+//! see [`crate::Valhalla::trace_route`].
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::conformance;
+//! use valhalla_client::route::{Location, Manifest};
+//! use valhalla_client::trace::{Location as TraceLocation, Manifest as TraceManifest};
+//!
+//! let valhalla = Valhalla::default();
+//! let planned = valhalla
+//!   .route(Manifest::builder().locations([Location::new(4.9041, 52.3676), Location::new(4.9166, 52.3731)]))
+//!   .unwrap();
+//! let trace = [(4.9041, 52.3676), (4.9102, 52.3712), (4.9166, 52.3731)]
+//!   .map(|(lon, lat)| TraceLocation::new(lon, lat));
+//! let driven = valhalla.trace_route(TraceManifest::builder().shape(trace)).unwrap();
+//! let conformance = conformance::score(&planned, &driven, 30.0);
+//! println!("{:.0}% of the plan driven, {:.0}m of detours", conformance.traversed * 100.0, conformance.detour);
+//! ```
+use crate::route::Trip;
+use crate::shapes::ShapePoint;
+
+/// How closely a driven trip followed a planned one, see [`score`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conformance {
+    /// The share of the planned shape's length which was driven, between `0` and `1`
+    pub traversed: f64,
+    /// The length in meters of the driven shape which is off the planned one
+    pub detour: f64,
+    /// The length in meters of the planned shape
+    pub planned_length: f64,
+    /// The length in meters of the driven shape
+    pub driven_length: f64,
+}
+
+/// Scores how closely the `driven` trip followed the `planned` one
+///
+/// A part of either shape counts as shared if it is within `tolerance` meters of the other shape,
+/// e.g. `30` to allow for the distance between the lanes of a dual carriageway.
+/// Both shapes are sampled at least every `tolerance / 2` meters and each sample is compared with the
+/// samples of the other shape, so the cost grows with the product of both lengths.
+pub fn score(planned: &Trip, driven: &Trip, tolerance: f64) -> Conformance {
+    let spacing = (tolerance / 2.0).max(1.0);
+    let (planned, driven) = (sample(planned, spacing), sample(driven, spacing));
+    let near = |point: &ShapePoint, shape: &[ShapePoint]| {
+        shape
+            .iter()
+            .any(|other| point.distance_to(other) <= tolerance)
+    };
+    // the lengths of the segments of `shape` and of those between points near `other`
+    let lengths = |shape: &[ShapePoint], other: &[ShapePoint]| {
+        let near: Vec<bool> = shape.iter().map(|p| near(p, other)).collect();
+        shape.windows(2).zip(near.windows(2)).fold(
+            (0.0, 0.0),
+            |(total, shared), (segment, near)| {
+                let length = segment[0].distance_to(&segment[1]);
+                let shared_length = if near[0] && near[1] { length } else { 0.0 };
+                (total + length, shared + shared_length)
+            },
+        )
+    };
+    let (planned_length, traversed) = lengths(&planned, &driven);
+    let (driven_length, on_plan) = lengths(&driven, &planned);
+    Conformance {
+        traversed: if planned_length > 0.0 {
+            traversed / planned_length
+        } else {
+            0.0
+        },
+        detour: driven_length - on_plan,
+        planned_length,
+        driven_length,
+    }
+}
+
+/// The points of the shapes of all legs, with points inserted so they are at most `spacing` meters apart
+fn sample(trip: &Trip, spacing: f64) -> Vec<ShapePoint> {
+    let shape: Vec<&ShapePoint> = trip.legs.iter().flat_map(|leg| &leg.shape).collect();
+    let mut points: Vec<ShapePoint> = shape.first().map(|&p| p.clone()).into_iter().collect();
+    for segment in shape.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let parts = (a.distance_to(b) / spacing).ceil().max(1.0);
+        for i in 1..=parts as usize {
+            let t = i as f64 / parts;
+            points.push(ShapePoint {
+                lon: a.lon + t * (b.lon - a.lon),
+                lat: a.lat + t * (b.lat - a.lat),
+            });
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn scoring() {
+        let summary = serde_json::json!({
+            "time": 0.0, "length": 0.0, "has_toll": false, "has_highway": false, "has_ferry": false,
+            "min_lat": 0.0, "min_lon": 0.0, "max_lat": 0.0, "max_lon": 0.0,
+        });
+        let trip = |shape: &[(f64, f64)]| {
+            let mut trip: Trip = serde_json::from_value(serde_json::json!({
+                "status": 0, "status_message": "Found route between points",
+                "units": "kilometers", "language": "en-US", "locations": [],
+                "legs": [{"summary": summary, "maneuvers": [], "shape": ""}],
+                "summary": summary,
+            }))
+            .unwrap();
+            trip.legs[0].shape = shape
+                .iter()
+                .map(|&(lon, lat)| ShapePoint { lon, lat })
+                .collect();
+            trip
+        };
+        // about 1.1km east along the equator
+        let planned = trip(&[(0.0, 0.0), (0.01, 0.0)]);
+        let conformance = score(&planned, &planned, 20.0);
+        assert!((conformance.traversed - 1.0).abs() < 1e-9);
+        assert_eq!(conformance.detour, 0.0);
+        assert!((conformance.planned_length - 1_112.0).abs() < 1.0);
+
+        // drives half of the plan, then turns north for about 1.1km
+        let driven = trip(&[(0.0, 0.0), (0.005, 0.0), (0.005, 0.01)]);
+        let conformance = score(&planned, &driven, 20.0);
+        assert!((conformance.traversed - 0.5).abs() < 0.02);
+        assert!((conformance.detour - 1_112.0).abs() < 25.0);
+        assert!((conformance.driven_length - 1_668.0).abs() < 1.0);
+
+        assert_eq!(score(&trip(&[]), &planned, 20.0).traversed, 0.0);
+    }
+}
@@ -0,0 +1,120 @@
+//! Loading of costing options from configuration files
+//!
+//! Allows tuning routing behaviour without recompiling the application.
+//! Anything deserializable can be loaded, e.g. a single [`super::Costing`] or a set of
+//! [`super::profiles::VehicleProfiles`].
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::costing::{config, Costing};
+//!
+//! // {"costing": "truck", "costing_options": {"truck": {"height": 3.5}}}
+//! let costing: Costing = config::load("costing.json").unwrap();
+//! ```
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// Why a configuration file could not be loaded
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    #[cfg(feature = "toml")]
+    Toml(toml::de::Error),
+    /// The file extension is neither `json` nor (with the `toml` feature) `toml`
+    UnsupportedFormat(Option<String>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Json(e) => write!(f, "json error: {e}"),
+            #[cfg(feature = "toml")]
+            Self::Toml(e) => write!(f, "toml error: {e}"),
+            Self::UnsupportedFormat(Some(extension)) => {
+                write!(f, "unsupported config format: {extension}")
+            }
+            Self::UnsupportedFormat(None) => write!(f, "config file without extension"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Loads a configuration file, choosing the format by the file extension
+///
+/// Supports `.json` and, with the `toml` feature, `.toml` files.
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("json") => from_json(&std::fs::read_to_string(path).map_err(ConfigError::Io)?),
+        #[cfg(feature = "toml")]
+        Some("toml") => from_toml(&std::fs::read_to_string(path).map_err(ConfigError::Io)?),
+        _ => Err(ConfigError::UnsupportedFormat(extension)),
+    }
+}
+
+/// Parses a configuration in JSON
+pub fn from_json<T: DeserializeOwned>(config: &str) -> Result<T, ConfigError> {
+    serde_json::from_str(config).map_err(ConfigError::Json)
+}
+
+/// Parses a configuration in TOML
+#[cfg(feature = "toml")]
+pub fn from_toml<T: DeserializeOwned>(config: &str) -> Result<T, ConfigError> {
+    toml::from_str(config).map_err(ConfigError::Toml)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::costing::Costing;
+    #[test]
+    fn from_json_config() {
+        let costing: Costing =
+            from_json(r#"{"costing": "truck", "costing_options": {"truck": {"height": 3.5}}}"#)
+                .unwrap();
+        assert_eq!(
+            serde_json::to_value(costing).unwrap(),
+            serde_json::json!({"costing": "truck", "costing_options": {"truck": {"height": 3.5}}})
+        );
+        assert!(matches!(
+            load::<Costing>("costing.yaml"),
+            Err(ConfigError::UnsupportedFormat(Some(e))) if e == "yaml"
+        ));
+        assert!(matches!(
+            load::<Costing>("/nonexistent/costing.json"),
+            Err(ConfigError::Io(_))
+        ));
+    }
+    #[cfg(feature = "toml")]
+    #[test]
+    fn from_toml_config() {
+        let profiles: crate::costing::profiles::VehicleProfiles = from_toml(
+            r#"
+            [box-truck]
+            costing = "truck"
+            [box-truck.costing_options.truck]
+            height = 3.5
+            axle_count = 2
+
+            [courier]
+            costing = "bicycle"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            profiles.names().collect::<Vec<_>>(),
+            ["box-truck", "courier"]
+        );
+        assert_eq!(
+            serde_json::to_value(profiles.costing("box-truck").unwrap()).unwrap(),
+            serde_json::json!({"costing": "truck", "costing_options": {"truck": {"height": 3.5, "axle_count": 2}}})
+        );
+    }
+}
@@ -1,16 +1,22 @@
 pub mod auto;
 pub mod bicycle;
+pub mod config;
 pub mod motor_scooter;
 pub mod motorcycle;
 pub mod multimodal;
 pub mod pedestrian;
+pub mod profiles;
 pub mod transit;
 pub mod truck;
 
-use serde::Serialize;
+use serde::de::Error as _;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Serialize, Clone, Debug)]
-#[serde(tag = "costing", content = "costing_options")]
+/// The costing model and its options
+///
+/// (De)serialized as valhalla expects it, e.g. `{"costing": "bus", "costing_options": {"bus": {..}}}`.
+#[derive(Clone, Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum Costing {
     /// Standard costing for driving routes by car, motorcycle, truck, and so on.
@@ -20,25 +26,21 @@ pub enum Costing {
     /// uses intersection costing to minimize turns and maneuvers or road name changes.
     /// Routes also tend to favor highways and higher classification roads,
     /// such as motorways and trunks.
-    #[serde(rename = "auto")]
     Auto(auto::AutoCostingOptions),
 
     /// Standard costing for travel by bicycle.
     ///
     /// Has a slight preference for using cycleways or roads with bicycle lanes.
     /// Bicycle routes follow regular roads when needed, but avoid roads without bicycle access.
-    #[serde(rename = "bicycle")]
     Bicycle(bicycle::BicycleCostingOptions),
 
     /// Standard costing for bus routes.
     ///
     /// Bus costing inherits the [`Costing::Auto`] behaviors, but checks for bus access on the roads.
-    #[serde(rename = "bus")]
     Bus(auto::AutoCostingOptions),
     /// A combination of pedestrian and bicycle.
     ///
     /// Use bike share station (indicated by [`amenity:bicycle_rental`](https://wiki.openstreetmap.org/wiki/Tag:amenity%3Dbicycle_rental)) to change the travel mode
-    #[serde(rename = "bikeshare")]
     Bikeshare(bicycle::BicycleCostingOptions),
     /// Standard costing for trucks.
     ///
@@ -46,42 +48,144 @@ pub enum Costing {
     /// - truck access,
     /// - width/height restrictions and
     /// - weight limits
-    #[serde(rename = "truck")]
     Truck(truck::TruckCostingOptions),
     /// Standard costing for taxi routes.
     ///
     /// Taxi costing inherits the [`Costing::Auto`] behaviors, but checks and favors
     /// taxi lane access on roads.
-    #[serde(rename = "taxi")]
     Taxi(auto::AutoCostingOptions),
     /// Standard costing for travel by motor scooter or moped.
     ///
     /// By default, this will avoid higher class roads unless the country overrides allows motor
     /// scooters on these roads. Motor scooter routes follow regular roads when needed,
     /// but avoid roads without motor_scooter, moped, or mofa access.
-    #[serde(rename = "motor_scooter")]
     MotorScooter(motor_scooter::MotorScooterCostingOptions),
     /// Standard costing for travel by motorcycle.
     ///
     /// This costing model provides options to tune the route to take roadways (road touring) vs.
     /// tracks and trails (adventure motorcycling).
-    #[serde(rename = "motorcycle")]
     Motorcycle(motorcycle::MotorcycleCostingOptions),
     /// Combines different modalities.
     ///
     /// **Currently supports pedestrian and transit.**
     /// In the future, multimodal will support a combination of all of the above.
-    #[serde(rename = "multimodal")]
     Multimodal(multimodal::MultimodalCostingOptions),
     /// Standard walking route that excludes roads without pedestrian access.
     ///
     /// In general, pedestrian routes are the shortest distance with the following exceptions:
     /// - walkways and footpaths are slightly favored and
     /// - steps or stairs and alleys are slightly avoided
-    #[serde(rename = "pedestrian")]
     Pedestrian(pedestrian::PedestrianCostingOptions),
 }
 
+impl Costing {
+    /// The name valhalla uses for this costing model
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Auto(_) => "auto",
+            Self::Bicycle(_) => "bicycle",
+            Self::Bus(_) => "bus",
+            Self::Bikeshare(_) => "bikeshare",
+            Self::Truck(_) => "truck",
+            Self::Taxi(_) => "taxi",
+            Self::MotorScooter(_) => "motor_scooter",
+            Self::Motorcycle(_) => "motorcycle",
+            Self::Multimodal(_) => "multimodal",
+            Self::Pedestrian(_) => "pedestrian",
+        }
+    }
+}
+
+/// Options nested under the name of the costing model they apply to
+struct Keyed<'a, T>(&'static str, &'a T);
+
+impl<T: Serialize> Serialize for Keyed<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(self.0, self.1)?;
+        map.end()
+    }
+}
+
+impl Serialize for Costing {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("costing", self.name())?;
+        match self {
+            Self::Auto(o) | Self::Bus(o) | Self::Taxi(o) => {
+                map.serialize_entry("costing_options", &Keyed(self.name(), o))?
+            }
+            // bike share stations are used with the bicycle options
+            Self::Bicycle(o) | Self::Bikeshare(o) => {
+                map.serialize_entry("costing_options", &Keyed("bicycle", o))?
+            }
+            Self::Truck(o) => map.serialize_entry("costing_options", &Keyed(self.name(), o))?,
+            Self::MotorScooter(o) => {
+                map.serialize_entry("costing_options", &Keyed(self.name(), o))?
+            }
+            Self::Motorcycle(o) => {
+                map.serialize_entry("costing_options", &Keyed(self.name(), o))?
+            }
+            // already keyed by the `pedestrian` and `transit` costing models
+            Self::Multimodal(o) => map.serialize_entry("costing_options", o)?,
+            Self::Pedestrian(o) => {
+                map.serialize_entry("costing_options", &Keyed(self.name(), o))?
+            }
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Costing {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Raw {
+            costing: String,
+            #[serde(default)]
+            costing_options: serde_json::Map<String, serde_json::Value>,
+        }
+        let Raw {
+            costing,
+            mut costing_options,
+        } = Raw::deserialize(deserializer)?;
+        fn options<T: serde::de::DeserializeOwned + Default, E: serde::de::Error>(
+            options: Option<serde_json::Value>,
+        ) -> Result<T, E> {
+            options
+                .map_or(Ok(T::default()), serde_json::from_value)
+                .map_err(E::custom)
+        }
+        let mut keyed = |key: &str| costing_options.remove(key);
+        Ok(match costing.as_str() {
+            "auto" => Self::Auto(options(keyed("auto"))?),
+            "bicycle" => Self::Bicycle(options(keyed("bicycle"))?),
+            "bus" => Self::Bus(options(keyed("bus"))?),
+            "bikeshare" => Self::Bikeshare(options(keyed("bicycle"))?),
+            "truck" => Self::Truck(options(keyed("truck"))?),
+            "taxi" => Self::Taxi(options(keyed("taxi"))?),
+            "motor_scooter" => Self::MotorScooter(options(keyed("motor_scooter"))?),
+            "motorcycle" => Self::Motorcycle(options(keyed("motorcycle"))?),
+            "multimodal" => Self::Multimodal(options(Some(costing_options.into()))?),
+            "pedestrian" => Self::Pedestrian(options(keyed("pedestrian"))?),
+            other => return Err(D::Error::unknown_variant(other, &COSTING_MODELS)),
+        })
+    }
+}
+
+/// The names of all supported costing models, see [`Costing::name`]
+const COSTING_MODELS: [&str; 10] = [
+    "auto",
+    "bicycle",
+    "bus",
+    "bikeshare",
+    "truck",
+    "taxi",
+    "motor_scooter",
+    "motorcycle",
+    "multimodal",
+    "pedestrian",
+];
+
 impl Default for Costing {
     fn default() -> Self {
         Self::Auto(Default::default())
@@ -95,7 +199,41 @@ mod test {
     fn serialisation() {
         assert_eq!(
             serde_json::to_value(Costing::default()).unwrap(),
-            serde_json::json!({"costing": "auto", "costing_options": {}})
+            serde_json::json!({"costing": "auto", "costing_options": {"auto": {}}})
+        );
+    }
+    #[test]
+    fn deserialisation() {
+        let costings = [
+            Costing::Truck(truck::TruckCostingOptions::builder().height(3.5)),
+            Costing::Bikeshare(bicycle::BicycleCostingOptions::builder().use_hills(0.2)),
+            Costing::Multimodal(
+                multimodal::MultimodalCostingOptions::builder()
+                    .pedestrian(pedestrian::PedestrianCostingOptions::builder().walking_speed(4.0)),
+            ),
+        ];
+        for costing in costings {
+            let value = serde_json::to_value(&costing).unwrap();
+            let deserialized: Costing = serde_json::from_value(value.clone()).unwrap();
+            assert_eq!(serde_json::to_value(deserialized).unwrap(), value);
+        }
+        let costing: Costing =
+            serde_json::from_value(serde_json::json!({"costing": "taxi"})).unwrap();
+        assert_eq!(costing.name(), "taxi");
+        assert!(
+            serde_json::from_value::<Costing>(serde_json::json!({"costing": "rocket"})).is_err()
+        );
+    }
+    #[test]
+    fn bus_and_taxi_serialisation() {
+        let options = auto::AutoCostingOptions::builder().include_hov2(true);
+        assert_eq!(
+            serde_json::to_value(Costing::Bus(options.clone())).unwrap(),
+            serde_json::json!({"costing": "bus", "costing_options": {"bus": {"include_hov2": true}}})
+        );
+        assert_eq!(
+            serde_json::to_value(Costing::Taxi(options)).unwrap(),
+            serde_json::json!({"costing": "taxi", "costing_options": {"taxi": {"include_hov2": true}}})
         );
     }
 }
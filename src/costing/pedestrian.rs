@@ -305,4 +305,19 @@ mod test {
             serde_json::json!({})
         );
     }
+    #[test]
+    fn night_walking_serialisation() {
+        let options = PedestrianCostingOptions::builder()
+            .use_lit(1.0)
+            .max_distance(5_000.0)
+            .transit_start_end_max_distance(1_000.0);
+        assert_eq!(
+            serde_json::to_value(options).unwrap(),
+            serde_json::json!({
+                "use_lit": 1.0,
+                "max_distance": 5_000.0,
+                "transit_start_end_max_distance": 1_000.0,
+            })
+        );
+    }
 }
@@ -0,0 +1,76 @@
+//! Named vehicle profiles, defined once and referenced by name when building requests
+//!
+//! A profile is a [`Costing`] model together with its options, including the dimensions of
+//! the vehicle (e.g. [`super::truck::TruckCostingOptions::height`]).
+//! Profiles (de)serialize as a map from the name to the costing, so they can live in config files.
+//!
+//! # Example:
+//! ```rust
+//! use valhalla_client::costing::{Costing, profiles::VehicleProfiles, truck::TruckCostingOptions};
+//! use valhalla_client::route::Manifest;
+//!
+//! let profiles: VehicleProfiles = serde_json::from_str(r#"{
+//!     "box-truck": {"costing": "truck", "costing_options": {"truck": {"height": 3.5, "weight": 7.5}}},
+//!     "courier": {"costing": "bicycle"}
+//! }"#).unwrap();
+//! let manifest = Manifest::builder().costing(profiles.costing("box-truck").unwrap());
+//! ```
+use super::Costing;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A registry of named [`Costing`]s, see the [module documentation](self)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct VehicleProfiles {
+    profiles: BTreeMap<String, Costing>,
+}
+
+impl VehicleProfiles {
+    #[must_use]
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Defines a profile, replacing any profile of the same name
+    pub fn profile(mut self, name: impl ToString, costing: Costing) -> Self {
+        self.profiles.insert(name.to_string(), costing);
+        self
+    }
+    /// The costing of the profile `name`, ready to be passed to a request builder
+    pub fn costing(&self, name: &str) -> Option<Costing> {
+        self.profiles.get(name).cloned()
+    }
+    /// The names of all profiles, in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.profiles.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::costing::truck::TruckCostingOptions;
+    #[test]
+    fn profiles() {
+        let profiles = VehicleProfiles::builder()
+            .profile(
+                "box-truck",
+                Costing::Truck(TruckCostingOptions::builder().height(3.5).width(2.5)),
+            )
+            .profile("courier", Costing::Bicycle(Default::default()));
+        assert_eq!(
+            profiles.names().collect::<Vec<_>>(),
+            ["box-truck", "courier"]
+        );
+        assert_eq!(profiles.costing("courier").unwrap().name(), "bicycle");
+        assert!(profiles.costing("van").is_none());
+
+        let value = serde_json::to_value(&profiles).unwrap();
+        assert_eq!(
+            value["box-truck"],
+            serde_json::json!({"costing": "truck", "costing_options": {"truck": {"height": 3.5, "width": 2.5}}})
+        );
+        let deserialized: VehicleProfiles = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(deserialized).unwrap(), value);
+    }
+}
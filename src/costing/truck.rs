@@ -32,6 +32,8 @@ pub struct TruckCostingOptions {
     ignore_non_vehicular_restrictions: Option<bool>,
     ignore_access: Option<bool>,
     // -- ↓ truck only ↓ --
+    height: Option<f32>,
+    width: Option<f32>,
     length: Option<f32>,
     weight: Option<f32>,
     axle_load: Option<f32>,
@@ -344,6 +346,20 @@ impl TruckCostingOptions {
         self.ignore_access = Some(true);
         self
     }
+    ///The height of the truck (in meters).
+    ///
+    /// Default: `4.11`
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = Some(height);
+        self
+    }
+    ///The width of the truck (in meters).
+    ///
+    /// Default: `2.6`
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = Some(width);
+        self
+    }
     ///The length of the truck (in meters).
     ///
     /// Default: `21.64`
@@ -0,0 +1,389 @@
+//! Elevations along a shape, e.g. to plot the elevation profile of a route
+//!
+//! See <https://valhalla.github.io/valhalla/api/elevation/api-reference/> for details
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::elevation::Manifest;
+//! use valhalla_client::shapes::ShapePoint;
+//!
+//! let manifest = Manifest::builder()
+//!   .shape([
+//!     ShapePoint { lon: 7.6586, lat: 45.9763 },
+//!     ShapePoint { lon: 7.7491, lat: 46.0207 },
+//!   ])
+//!   .range(true)
+//!   .resample_distance(100.0);
+//! let response = Valhalla::default().height(manifest).unwrap();
+//! for sample in response.samples() {
+//!   println!("{:?}m: {:?}m", sample.distance, sample.height);
+//! }
+//! ```
+use crate::shapes::ShapePoint;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+struct Point {
+    lat: f64,
+    lon: f64,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    shape: Option<Vec<Point>>,
+    encoded_polyline: Option<String>,
+    range: Option<bool>,
+    height_precision: Option<u8>,
+    resample_distance: Option<f64>,
+    id: Option<String>,
+}
+
+impl Manifest {
+    #[must_use]
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Sets the points to get the elevations of
+    ///
+    /// Replaces a shape set via [`Self::encoded_polyline`].
+    pub fn shape(mut self, shape: impl IntoIterator<Item = ShapePoint>) -> Self {
+        let shape = shape
+            .into_iter()
+            .map(|p| Point {
+                lat: p.lat,
+                lon: p.lon,
+            })
+            .collect();
+        self.shape = Some(shape);
+        self.encoded_polyline = None;
+        self
+    }
+    /// Sets the points to get the elevations of as polyline with a precision of 6 digits, e.g. the shape of a route
+    ///
+    /// Replaces a shape set via [`Self::shape`].
+    pub fn encoded_polyline(mut self, polyline6: impl ToString) -> Self {
+        self.encoded_polyline = Some(polyline6.to_string());
+        self.shape = None;
+        self
+    }
+    /// Also returns the distance along the shape of each elevation, see [`Sample::distance`]
+    ///
+    /// Default: `false`
+    pub fn range(mut self, range: bool) -> Self {
+        self.range = Some(range);
+        self
+    }
+    /// The number of decimal places of the elevations, between `0` and `2`
+    ///
+    /// Default: `0`, i.e. whole meters
+    pub fn height_precision(mut self, precision: u8) -> Self {
+        self.height_precision = Some(precision.min(2));
+        self
+    }
+    /// Resamples the shape with a point every `meters`, instead of returning the elevations at its points
+    ///
+    /// Default: no resampling
+    pub fn resample_distance(mut self, meters: f64) -> Self {
+        self.resample_distance = Some(meters);
+        self
+    }
+    /// Name your elevation request.
+    ///
+    /// If id is specified, the naming will be sent through to the response.
+    pub fn id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Splits the shape into manifests of at most `max_points` points, see [`crate::Valhalla::height_profile`]
+    ///
+    /// Consecutive chunks overlap by one point, so that [`stitch`] can continue the distances.
+    pub(crate) fn chunks(&self, max_points: usize) -> Vec<Self> {
+        let shape: Vec<Point> = match (&self.shape, &self.encoded_polyline) {
+            (Some(shape), _) => shape.clone(),
+            (None, Some(polyline)) => crate::shapes::Shape::Encoded(polyline.clone())
+                .decode()
+                .into_iter()
+                .map(|p| Point {
+                    lat: p.lat,
+                    lon: p.lon,
+                })
+                .collect(),
+            (None, None) => Vec::new(),
+        };
+        if shape.len() <= max_points {
+            return vec![self.clone()];
+        }
+        let step = max_points.max(2) - 1;
+        (0..shape.len() - 1)
+            .step_by(step)
+            .map(|start| {
+                let end = (start + step + 1).min(shape.len());
+                Self {
+                    shape: Some(shape[start..end].to_vec()),
+                    encoded_polyline: None,
+                    ..self.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Joins the samples of consecutive chunks of a shape, see [`Manifest::chunks`]
+///
+/// The first sample of each chunk after the first one is the last one of the previous chunk, so it is dropped
+/// and the distances of the chunk are continued from there.
+pub(crate) fn stitch(responses: &[Response]) -> Vec<Sample> {
+    let mut samples: Vec<Sample> = Vec::new();
+    for (i, response) in responses.iter().enumerate() {
+        let offset = samples.last().and_then(|s| s.distance).unwrap_or_default();
+        let chunk = response.samples().into_iter().skip(usize::from(i > 0));
+        samples.extend(chunk.map(|sample| Sample {
+            distance: sample.distance.map(|d| d + offset),
+            ..sample
+        }));
+    }
+    samples
+}
+
+/// The elevation at a point of the shape
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// The distance in meters from the start of the shape
+    ///
+    /// Only known if requested via [`Manifest::range`].
+    pub distance: Option<f64>,
+    /// The elevation in meters, or `None` if there is no elevation data for this point
+    pub height: Option<f64>,
+}
+
+/// The elevations along the shape of a [`Manifest`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Response {
+    /// The id set via [`Manifest::id`]
+    pub id: Option<String>,
+    /// The distance along the shape and elevation of each point, if requested via [`Manifest::range`]
+    pub range_height: Option<Vec<(f64, Option<f64>)>>,
+    /// The elevation of each point, if [`Manifest::range`] was not requested
+    pub height: Option<Vec<Option<f64>>>,
+    /// Further attributes, e.g. the requested `shape` or `encoded_polyline`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Response {
+    /// The elevation of each point, in the order of the shape
+    pub fn samples(&self) -> Vec<Sample> {
+        match (&self.range_height, &self.height) {
+            (Some(range_height), _) => range_height
+                .iter()
+                .map(|&(distance, height)| Sample {
+                    distance: Some(distance),
+                    height,
+                })
+                .collect(),
+            (None, Some(heights)) => heights
+                .iter()
+                .map(|&height| Sample {
+                    distance: None,
+                    height,
+                })
+                .collect(),
+            (None, None) => Vec::new(),
+        }
+    }
+}
+
+/// The elevation along a leg of a trip, see [`annotate`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegElevation {
+    /// The total ascent in meters
+    pub climb: f64,
+    /// The total descent in meters
+    pub descent: f64,
+    /// One entry per maneuver of the leg
+    pub maneuvers: Vec<ManeuverElevation>,
+}
+
+/// The elevation along a maneuver of a trip, see [`annotate`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ManeuverElevation {
+    /// The total ascent in meters
+    pub climb: f64,
+    /// The total descent in meters
+    pub descent: f64,
+    /// The average grade in percent, from the first to the last point of the maneuver
+    ///
+    /// `None` if the height of either point is unknown or the maneuver has no length.
+    pub grade: Option<f64>,
+}
+
+/// Attaches elevations to the legs and maneuvers of a trip, see [`crate::Valhalla::trip_elevation`]
+///
+/// The `heights` are in meters and follow the shapes of all legs in order, e.g. the
+/// [`Sample::height`]s of a [`crate::Valhalla::height_profile`] of the trip's shape without resampling.
+/// Points of unknown height are skipped when summing the climb and descent.
+pub fn annotate(trip: &crate::route::Trip, heights: &[Option<f64>]) -> Vec<LegElevation> {
+    let mut offset = 0;
+    trip.legs
+        .iter()
+        .map(|leg| {
+            let heights = heights.get(offset..).unwrap_or_default();
+            offset += leg.shape.len();
+            let height = |index: usize| heights.get(index).copied().flatten();
+            let (climb, descent) = climb_and_descent(heights.iter().take(leg.shape.len()));
+            let maneuvers = leg.maneuvers.iter().map(|maneuver| {
+                let (begin, end) = (maneuver.begin_shape_index, maneuver.end_shape_index);
+                let (climb, descent) =
+                    climb_and_descent(heights.get(begin..=end).into_iter().flatten());
+                let distance = trip.units.to_meters(maneuver.length);
+                let grade = match (height(begin), height(end)) {
+                    (Some(begin), Some(end)) if distance > 0.0 => {
+                        Some((end - begin) / distance * 100.0)
+                    }
+                    _ => None,
+                };
+                ManeuverElevation {
+                    climb,
+                    descent,
+                    grade,
+                }
+            });
+            LegElevation {
+                climb,
+                descent,
+                maneuvers: maneuvers.collect(),
+            }
+        })
+        .collect()
+}
+
+/// The sums of the rises and falls between consecutive known heights
+fn climb_and_descent<'a>(heights: impl IntoIterator<Item = &'a Option<f64>>) -> (f64, f64) {
+    let known: Vec<f64> = heights.into_iter().flatten().copied().collect();
+    known.windows(2).fold((0.0, 0.0), |(climb, descent), pair| {
+        let rise = pair[1] - pair[0];
+        (climb + rise.max(0.0), descent + (-rise).max(0.0))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn serialisation() {
+        let manifest = Manifest::builder()
+            .shape([ShapePoint {
+                lon: 7.6,
+                lat: 45.9,
+            }])
+            .range(true)
+            .height_precision(5);
+        assert_eq!(
+            serde_json::to_value(&manifest).unwrap(),
+            serde_json::json!({"shape": [{"lat": 45.9, "lon": 7.6}], "range": true, "height_precision": 2})
+        );
+        let manifest = manifest.encoded_polyline("_izlhA~rlgdF");
+        assert_eq!(
+            serde_json::to_value(&manifest).unwrap()["encoded_polyline"],
+            "_izlhA~rlgdF"
+        );
+    }
+    #[test]
+    fn annotation() {
+        let maneuver = |length: f64, begin: usize, end: usize| {
+            serde_json::json!({
+                "type": 1, "instruction": "Drive north.", "time": 60.0, "length": length,
+                "begin_shape_index": begin, "end_shape_index": end, "travel_mode": "drive",
+            })
+        };
+        let summary = serde_json::json!({
+            "time": 0.0, "length": 0.0, "has_toll": false, "has_highway": false, "has_ferry": false,
+            "min_lat": 0.0, "min_lon": 0.0, "max_lat": 0.0, "max_lon": 0.0,
+        });
+        let mut trip: crate::route::Trip = serde_json::from_value(serde_json::json!({
+            "status": 0, "status_message": "Found route between points",
+            "units": "kilometers", "language": "en-US", "locations": [],
+            "legs": [
+                {"summary": summary, "maneuvers": [maneuver(1.0, 0, 2), maneuver(0.0, 2, 2)], "shape": ""},
+                {"summary": summary, "maneuvers": [maneuver(0.5, 0, 1)], "shape": ""},
+            ],
+            "summary": summary,
+        }))
+        .unwrap();
+        let point = ShapePoint { lon: 0.0, lat: 0.0 };
+        trip.legs[0].shape = vec![point.clone(); 3];
+        trip.legs[1].shape = vec![point; 2];
+        let heights = [Some(100.0), Some(130.0), Some(120.0), None, Some(80.0)];
+        let legs = annotate(&trip, &heights);
+        assert_eq!(legs.len(), 2);
+        assert_eq!((legs[0].climb, legs[0].descent), (30.0, 10.0));
+        assert_eq!(legs[0].maneuvers[0].grade, Some(2.0));
+        assert_eq!(legs[0].maneuvers[1].grade, None, "no length");
+        assert_eq!((legs[1].climb, legs[1].descent), (0.0, 0.0));
+        assert_eq!(legs[1].maneuvers[0].grade, None, "unknown height");
+        // missing heights
+        assert_eq!(annotate(&trip, &[])[1].maneuvers[0].grade, None);
+    }
+    #[test]
+    fn chunking() {
+        let shape = (0..10).map(|i| ShapePoint {
+            lon: f64::from(i),
+            lat: 0.0,
+        });
+        let manifest = Manifest::builder().shape(shape).range(true);
+        let chunks = manifest.chunks(4);
+        let lengths: Vec<_> = chunks
+            .iter()
+            .map(|c| c.shape.as_ref().unwrap().len())
+            .collect();
+        assert_eq!(lengths, [4, 4, 4]);
+        assert_eq!(chunks[1].shape.as_ref().unwrap()[0].lon, 3.0, "overlaps");
+        assert_eq!(chunks[2].range, Some(true));
+        assert_eq!(manifest.chunks(10).len(), 1);
+
+        let response = |heights: &[(f64, f64)]| Response {
+            id: None,
+            range_height: Some(heights.iter().map(|&(d, h)| (d, Some(h))).collect()),
+            height: None,
+            other: Default::default(),
+        };
+        let samples = stitch(&[
+            response(&[(0.0, 10.0), (100.0, 20.0)]),
+            response(&[(0.0, 20.0), (50.0, 30.0)]),
+        ]);
+        let profile: Vec<_> = samples
+            .iter()
+            .map(|s| (s.distance.unwrap(), s.height.unwrap()))
+            .collect();
+        assert_eq!(profile, [(0.0, 10.0), (100.0, 20.0), (150.0, 30.0)]);
+    }
+    #[test]
+    fn samples() {
+        let response: Response = serde_json::from_value(serde_json::json!({
+            "shape": [{"lat": 45.9, "lon": 7.6}, {"lat": 46.0, "lon": 7.7}],
+            "range_height": [[0, 1620], [13_697, null]],
+        }))
+        .unwrap();
+        assert_eq!(
+            response.samples(),
+            [
+                Sample {
+                    distance: Some(0.0),
+                    height: Some(1620.0)
+                },
+                Sample {
+                    distance: Some(13_697.0),
+                    height: None
+                },
+            ]
+        );
+        let response: Response =
+            serde_json::from_value(serde_json::json!({"height": [1620, 1625]})).unwrap();
+        assert_eq!(response.samples()[1].height, Some(1625.0));
+        assert_eq!(response.samples()[1].distance, None);
+    }
+}
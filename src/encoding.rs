@@ -0,0 +1,67 @@
+//! How request bodies are encoded before they are sent, see [`crate::Valhalla::request_encoder`]
+//!
+//! Bodies are encoded as compact JSON by default. Other encodings, e.g. a protobuf encoding for instances
+//! built with protobuf support, can be plugged in by implementing [`Encoder`].
+//!
+//! # Example:
+//! ```rust
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::encoding::PrettyJson;
+//!
+//! // readable bodies, e.g. when inspecting requests with a proxy
+//! let valhalla = Valhalla::default().request_encoder(PrettyJson);
+//! ```
+use crate::Error;
+
+/// Encodes the JSON body of a request into the bytes sent to valhalla
+pub trait Encoder: Send + Sync {
+    /// The `Content-Type` header sent with the encoded body
+    fn content_type(&self) -> &str;
+    /// Encodes the body, with the [`crate::Valhalla::defaults`] already filled in
+    fn encode(&self, body: &serde_json::Value) -> Result<Vec<u8>, Error>;
+}
+
+/// Encodes bodies as JSON without whitespace
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactJson;
+
+impl Encoder for CompactJson {
+    fn content_type(&self) -> &str {
+        "application/json"
+    }
+    fn encode(&self, body: &serde_json::Value) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(body).map_err(Error::Serde)
+    }
+}
+
+/// Encodes bodies as indented JSON, e.g. for debugging
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrettyJson;
+
+impl Encoder for PrettyJson {
+    fn content_type(&self) -> &str {
+        "application/json"
+    }
+    fn encode(&self, body: &serde_json::Value) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec_pretty(body).map_err(Error::Serde)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn json() {
+        let body = serde_json::json!({"locations": [{"lat": 52.4}]});
+        assert_eq!(
+            CompactJson.encode(&body).unwrap(),
+            br#"{"locations":[{"lat":52.4}]}"#
+        );
+        let pretty = String::from_utf8(PrettyJson.encode(&body).unwrap()).unwrap();
+        assert!(pretty.contains("\n  \"locations\""));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+            body
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Estimation of the energy (or fuel) consumed along a trip
+//!
+//! Users supply a [`ConsumptionModel`], which is evaluated for each [`Segment`] of a trip.
+//! CO2 emissions are estimated from per-vehicle [`EmissionFactors`].
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::energy::{ConsumptionModel, Segment};
+//! use valhalla_client::route::{Location, Manifest};
+//!
+//! /// kWh used by a small electric car, growing with the speed
+//! let model = |segment: &Segment| segment.distance / 1_000.0 * (0.12 + segment.speed / 1_500.0);
+//!
+//! let manifest = Manifest::builder()
+//!   .locations([Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)]);
+//! let trip = Valhalla::default().route(manifest).unwrap();
+//! println!("{:?} kWh per leg", model.estimate(&trip));
+//! ```
+use crate::costing::Costing;
+use crate::route::{TravelMode, Trip};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A part of a trip travelled at a constant average speed, i.e. a maneuver
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    /// The index of the leg the segment belongs to
+    pub leg: usize,
+    /// Length in meters
+    pub distance: f64,
+    /// Estimated time in seconds
+    pub time: f64,
+    /// Average speed in `km/h`, `0` if the segment takes no time
+    pub speed: f64,
+    /// Average grade in percent, positive uphill
+    ///
+    /// Route responses carry no elevations, so this is only known for segments created via
+    /// [`Self::with_heights`], and `0` otherwise.
+    pub grade: f64,
+    /// How the segment is travelled, e.g. to distinguish walking to a transit stop from riding a bus
+    pub travel_mode: TravelMode,
+}
+
+impl Segment {
+    /// The segments of all legs of a trip, one per maneuver, with a grade of `0`
+    pub fn of(trip: &Trip) -> Vec<Self> {
+        trip.legs
+            .iter()
+            .enumerate()
+            .flat_map(|(leg, l)| l.maneuvers.iter().map(move |m| (leg, m)))
+            .map(|(leg, maneuver)| {
+                let distance = trip.units.to_meters(maneuver.length);
+                let speed = if maneuver.time > 0.0 {
+                    distance / maneuver.time * 3.6
+                } else {
+                    0.0
+                };
+                Self {
+                    leg,
+                    distance,
+                    time: maneuver.time,
+                    speed,
+                    grade: 0.0,
+                    travel_mode: maneuver.travel_mode,
+                }
+            })
+            .collect()
+    }
+
+    /// The segments of all legs of a trip, with the grade derived from the height of each shape point
+    ///
+    /// The `heights` are in meters and follow the shapes of all legs in order, e.g. the
+    /// [`crate::elevation::Sample::height`]s of a [`crate::Valhalla::height_profile`] of the trip's shape.
+    /// The grade is the [`crate::elevation::ManeuverElevation::grade`] of each maneuver,
+    /// and is `0` if it is unknown.
+    pub fn with_heights(trip: &Trip, heights: &[Option<f64>]) -> Vec<Self> {
+        let elevations = crate::elevation::annotate(trip, heights);
+        let maneuvers = elevations.iter().flat_map(|leg| &leg.maneuvers);
+        Self::of(trip)
+            .into_iter()
+            .zip(maneuvers)
+            .map(|(segment, maneuver)| Self {
+                grade: maneuver.grade.unwrap_or_default(),
+                ..segment
+            })
+            .collect()
+    }
+}
+
+/// How much energy (or fuel) is consumed on a [`Segment`]
+///
+/// Implemented for closures, see the [module documentation](self).
+/// The unit of the consumption is up to the model, e.g. `kWh` or litres.
+pub trait ConsumptionModel {
+    /// The consumption on one segment
+    fn consumption(&self, segment: &Segment) -> f64;
+
+    /// The consumption on each leg of a trip
+    fn estimate(&self, trip: &Trip) -> Vec<f64> {
+        let mut legs = vec![0.0; trip.legs.len()];
+        for segment in Segment::of(trip) {
+            legs[segment.leg] += self.consumption(&segment);
+        }
+        legs
+    }
+}
+
+impl<F: Fn(&Segment) -> f64> ConsumptionModel for F {
+    fn consumption(&self, segment: &Segment) -> f64 {
+        self(segment)
+    }
+}
+
+/// The key of [`EmissionFactors`] for segments travelled by public transport
+const TRANSIT: &str = "transit";
+
+/// Grams of CO2 emitted per kilometer, per [costing model](Costing::name)
+///
+/// The defaults are rough averages for the vehicle (not per passenger), except for public transport,
+/// whose factor is per passenger. Override them with figures for your fleet, e.g. from a config file.
+/// Walking and cycling segments never emit CO2.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(transparent)]
+pub struct EmissionFactors {
+    grams_per_km: BTreeMap<String, f64>,
+}
+
+impl Default for EmissionFactors {
+    fn default() -> Self {
+        let grams_per_km = [
+            ("auto", 170.0),
+            ("taxi", 170.0),
+            ("bus", 1_300.0),
+            ("truck", 900.0),
+            ("motorcycle", 110.0),
+            ("motor_scooter", 60.0),
+            (TRANSIT, 60.0),
+        ];
+        Self {
+            grams_per_km: grams_per_km
+                .into_iter()
+                .map(|(costing, grams)| (costing.to_string(), grams))
+                .collect(),
+        }
+    }
+}
+
+impl EmissionFactors {
+    /// Sets the grams of CO2 per kilometer for a costing model, e.g. `auto`
+    ///
+    /// Use `transit` for the public transport segments of [`Costing::Multimodal`] trips.
+    pub fn factor(mut self, costing: impl ToString, grams_per_km: f64) -> Self {
+        self.grams_per_km.insert(costing.to_string(), grams_per_km);
+        self
+    }
+
+    /// The CO2 emitted on a trip routed with `costing`, in grams
+    ///
+    /// Returns `None` if there is no factor for a segment's vehicle.
+    pub fn co2(&self, costing: &Costing, trip: &Trip) -> Option<f64> {
+        Segment::of(trip)
+            .iter()
+            .map(|segment| {
+                let grams_per_km = match segment.travel_mode {
+                    TravelMode::Pedestrian | TravelMode::Bicycle => 0.0,
+                    TravelMode::Transit => *self.grams_per_km.get(TRANSIT)?,
+                    TravelMode::Drive => *self.grams_per_km.get(costing.name())?,
+                };
+                Some(segment.distance / 1_000.0 * grams_per_km)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn estimate() {
+        let maneuver = |time: f64, length: f64| {
+            serde_json::json!({
+                "type": 1, "instruction": "Drive north.", "time": time, "length": length,
+                "begin_shape_index": 0, "end_shape_index": 0, "travel_mode": "drive",
+            })
+        };
+        let summary = serde_json::json!({
+            "time": 0.0, "length": 0.0, "has_toll": false, "has_highway": false, "has_ferry": false,
+            "min_lat": 0.0, "min_lon": 0.0, "max_lat": 0.0, "max_lon": 0.0,
+        });
+        let trip: Trip = serde_json::from_value(serde_json::json!({
+            "status": 0, "status_message": "Found route between points",
+            "units": "kilometers", "language": "en-US", "locations": [],
+            "legs": [
+                {"summary": summary, "maneuvers": [maneuver(60.0, 1.5), maneuver(0.0, 0.0)], "shape": ""},
+                {"summary": summary, "maneuvers": [maneuver(360.0, 10.0)], "shape": ""},
+            ],
+            "summary": summary,
+        }))
+        .unwrap();
+        let segments = Segment::of(&trip);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].speed, 90.0);
+        assert_eq!(segments[1].speed, 0.0);
+        assert_eq!(segments[2].leg, 1);
+        assert_eq!(segments[0].grade, 0.0);
+
+        let trip_with_shapes = {
+            let mut trip = trip.clone();
+            for leg in &mut trip.legs {
+                leg.shape = vec![crate::shapes::ShapePoint { lon: 0.0, lat: 0.0 }; 2];
+            }
+            trip.legs[0].maneuvers[0].end_shape_index = 1;
+            trip.legs[1].maneuvers[0].end_shape_index = 1;
+            trip
+        };
+        let graded = Segment::with_heights(
+            &trip_with_shapes,
+            &[Some(0.0), Some(30.0), Some(100.0), None],
+        );
+        assert_eq!(graded[0].grade, 2.0);
+        assert_eq!(graded[1].grade, 0.0, "no length");
+        assert_eq!(graded[2].grade, 0.0, "unknown height");
+
+        let per_kilometer = |segment: &Segment| segment.distance / 1_000.0;
+        assert_eq!(per_kilometer.estimate(&trip), [1.5, 10.0]);
+
+        let factors = EmissionFactors::default();
+        let auto = Costing::Auto(Default::default());
+        assert_eq!(factors.co2(&auto, &trip), Some(11.5 * 170.0));
+        assert_eq!(
+            factors.co2(&Costing::Bicycle(Default::default()), &trip),
+            None
+        );
+        let factors = factors.factor("auto", 100.0);
+        assert_eq!(factors.co2(&auto, &trip), Some(1_150.0));
+    }
+}
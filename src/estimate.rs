@@ -0,0 +1,113 @@
+//! Estimates of how expensive a request is, before it is sent
+//!
+//! Allows job schedulers to budget and order work, e.g. to send small requests first.
+//! See [`crate::matrix::Manifest::estimate`] and [`crate::route::Manifest::estimate`].
+//!
+//! # Example:
+//! ```rust
+//! use valhalla_client::estimate::CostClass;
+//! use valhalla_client::matrix::{Location, Manifest};
+//!
+//! let locations = (0..20).map(|i| Location::new(13.4 + i as f32 * 0.01, 52.5));
+//! let manifest = Manifest::builder().sources_to_targets(locations.clone(), locations);
+//! let estimate = manifest.estimate();
+//! assert_eq!(estimate.cells, 400);
+//! assert_eq!(estimate.class(), CostClass::Medium);
+//! ```
+use crate::shapes::ShapePoint;
+
+/// How expensive a request is, ordered from cheapest to most expensive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CostClass {
+    /// Answered within milliseconds, e.g. a short route
+    Small,
+    /// Answered within about a second, e.g. a route across a country
+    Medium,
+    /// May take many seconds or exceed the limits of the instance, e.g. a continental matrix
+    Large,
+}
+
+/// The predicted size of a request, see the [module documentation](self)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// The name valhalla uses for the endpoint, e.g. `route`
+    pub action: &'static str,
+    /// The number of locations sent
+    pub locations: usize,
+    /// The number of connections valhalla computes
+    ///
+    /// For a matrix, the number of sources times the number of targets,
+    /// for a route the number of legs.
+    pub cells: usize,
+    /// The longest great-circle distance in meters valhalla has to search
+    ///
+    /// For a matrix, the distance between the farthest source and target,
+    /// for a route the sum of the distances between consecutive locations.
+    pub crow_distance: f64,
+}
+
+/// Connections up to which a request is [`CostClass::Small`] resp. at most [`CostClass::Medium`]
+const CELL_LIMITS: [usize; 2] = [100, 2_500];
+/// Distances in meters up to which a request is [`CostClass::Small`] resp. at most [`CostClass::Medium`]
+const DISTANCE_LIMITS: [f64; 2] = [50_000.0, 500_000.0];
+
+impl Estimate {
+    pub(crate) fn matrix(sources: &[ShapePoint], targets: &[ShapePoint]) -> Self {
+        let crow_distance = sources
+            .iter()
+            .flat_map(|source| targets.iter().map(|target| source.distance_to(target)))
+            .fold(0.0, f64::max);
+        Self {
+            action: "sources_to_targets",
+            locations: sources.len() + targets.len(),
+            cells: sources.len() * targets.len(),
+            crow_distance,
+        }
+    }
+    pub(crate) fn route(locations: &[ShapePoint]) -> Self {
+        Self {
+            action: "route",
+            locations: locations.len(),
+            cells: locations.len().saturating_sub(1),
+            crow_distance: locations.windows(2).map(|w| w[0].distance_to(&w[1])).sum(),
+        }
+    }
+
+    /// How expensive the request is, based on both the number of connections and the distance
+    pub fn class(&self) -> CostClass {
+        let class = |small: bool, medium: bool| match (small, medium) {
+            (true, _) => CostClass::Small,
+            (false, true) => CostClass::Medium,
+            (false, false) => CostClass::Large,
+        };
+        let by_cells = class(self.cells <= CELL_LIMITS[0], self.cells <= CELL_LIMITS[1]);
+        let by_distance = class(
+            self.crow_distance <= DISTANCE_LIMITS[0],
+            self.crow_distance <= DISTANCE_LIMITS[1],
+        );
+        by_cells.max(by_distance)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn classes() {
+        let point = |lon| ShapePoint { lon, lat: 0.0 };
+        let route = Estimate::route(&[point(0.0), point(0.1), point(0.3)]);
+        assert_eq!((route.locations, route.cells), (3, 2));
+        assert!((route.crow_distance - 33_358.0).abs() < 10.0);
+        assert_eq!(route.class(), CostClass::Small);
+        assert_eq!(
+            Estimate::route(&[point(0.0), point(1.0)]).class(),
+            CostClass::Medium
+        );
+
+        let points: Vec<_> = (0..60).map(|i| point(f64::from(i) * 0.001)).collect();
+        let matrix = Estimate::matrix(&points, &points[..50]);
+        assert_eq!((matrix.locations, matrix.cells), (110, 3_000));
+        assert_eq!(matrix.class(), CostClass::Large);
+        assert_eq!(Estimate::matrix(&[], &points).cells, 0);
+    }
+}
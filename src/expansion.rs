@@ -0,0 +1,290 @@
+//! The edges of the road network visited while computing a route or isochrone, e.g. to debug a costing model
+//!
+//! See <https://valhalla.github.io/valhalla/api/expansion/api-reference/> for details
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::expansion::{EdgeStatus, Manifest, Property};
+//! use valhalla_client::route::{Location, Manifest as RouteManifest};
+//!
+//! let route = RouteManifest::builder()
+//!   .locations([Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)]);
+//! let manifest = Manifest::route(route)
+//!   .skip_opposites(true)
+//!   .expansion_properties([Property::Cost, Property::EdgeStatus]);
+//! let response = Valhalla::default().expansion(manifest).unwrap();
+//! let settled = response
+//!   .features
+//!   .iter()
+//!   .filter(|f| f.properties.edge_status == Some(EdgeStatus::Settled))
+//!   .count();
+//! println!("settled {settled} of {} edges", response.features.len());
+//! ```
+use crate::{isochrone, route};
+use serde::{Deserialize, Serialize};
+
+/// The computation to record the expansion of
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Route,
+    Isochrone,
+}
+
+/// A property of the visited edges to include in the response, see [`Properties`]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Property {
+    Cost,
+    Duration,
+    Distance,
+    EdgeStatus,
+    EdgeId,
+    PredEdgeId,
+    ExpansionType,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+enum Request {
+    Route(route::Manifest),
+    Isochrone(isochrone::Manifest),
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Debug, Clone)]
+pub struct Manifest {
+    #[serde(flatten)]
+    request: Request,
+    action: Action,
+    skip_opposites: Option<bool>,
+    dedupe: Option<bool>,
+    expansion_properties: Option<Vec<Property>>,
+}
+
+impl Manifest {
+    fn new(request: Request, action: Action) -> Self {
+        Self {
+            request,
+            action,
+            skip_opposites: None,
+            dedupe: None,
+            expansion_properties: None,
+        }
+    }
+    /// Records the expansion of a route
+    pub fn route(manifest: route::Manifest) -> Self {
+        Self::new(Request::Route(manifest), Action::Route)
+    }
+    /// Records the expansion of an isochrone
+    pub fn isochrone(manifest: isochrone::Manifest) -> Self {
+        Self::new(Request::Isochrone(manifest), Action::Isochrone)
+    }
+    /// Only returns one direction of each edge, i.e. skips an edge if its opposite was already visited
+    ///
+    /// Default: `false`
+    pub fn skip_opposites(mut self, skip_opposites: bool) -> Self {
+        self.skip_opposites = Some(skip_opposites);
+        self
+    }
+    /// Only returns each edge once, with the properties of its most advanced [`EdgeStatus`]
+    ///
+    /// Default: `false`, i.e. an edge is returned once per status it reached
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = Some(dedupe);
+        self
+    }
+    /// Sets the properties returned per visited edge
+    ///
+    /// Default: no properties, only the geometries of the edges
+    pub fn expansion_properties(mut self, properties: impl IntoIterator<Item = Property>) -> Self {
+        self.expansion_properties = Some(properties.into_iter().collect());
+        self
+    }
+}
+
+/// How far the search progressed at an edge
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeStatus {
+    /// The edge was found, but not yet expanded
+    Reached,
+    /// The cheapest path to the edge is known
+    Settled,
+    /// The forward and reverse searches met at the edge
+    Connected,
+}
+
+/// The GeoJSON `FeatureCollection` of the visited edges, in the order they were visited
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Response {
+    /// Always `FeatureCollection`
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub features: Vec<Feature>,
+}
+
+impl Response {
+    /// Splits the visited edges into `n` frames of equal duration, e.g. to animate the search wavefront
+    ///
+    /// The `i`-th frame contains the edges whose [`Properties::duration`] lies in the `i`-th of `n` equal
+    /// intervals up to the largest duration, in the order they were visited. Each frame is a GeoJSON
+    /// `FeatureCollection`; show the frames one after another without clearing the previous ones.
+    /// Edges without a duration are skipped, so request [`Property::Duration`].
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::expansion::{Manifest, Property};
+    /// use valhalla_client::route::{Location, Manifest as RouteManifest};
+    ///
+    /// let route = RouteManifest::builder()
+    ///   .locations([Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)]);
+    /// let manifest = Manifest::route(route).expansion_properties([Property::Duration]);
+    /// let response = Valhalla::default().expansion(manifest).unwrap();
+    /// for frame in response.frames(60) {
+    ///   println!("{}", serde_json::to_string(&frame).unwrap());
+    /// }
+    /// ```
+    pub fn frames(&self, n: usize) -> Vec<Response> {
+        let mut frames = vec![
+            Response {
+                type_: self.type_.clone(),
+                features: Vec::new(),
+            };
+            n
+        ];
+        if n == 0 {
+            return frames;
+        }
+        let durations = self.features.iter().filter_map(|f| f.properties.duration);
+        let max_duration = durations.fold(0.0, f64::max);
+        for feature in &self.features {
+            let Some(duration) = feature.properties.duration else {
+                continue;
+            };
+            // the frame whose interval ends at or after the duration, the first one for zero
+            let end = (duration / max_duration * n as f64).ceil();
+            let frame = if end.is_finite() { end as usize } else { 0 };
+            frames[frame.clamp(1, n) - 1].features.push(feature.clone());
+        }
+        frames
+    }
+}
+
+/// A visited edge
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Feature {
+    /// Always `Feature`
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub properties: Properties,
+    /// The shape of the edge, usually a [`geo_types::LineString`]
+    #[serde(with = "isochrone::geometry")]
+    pub geometry: geo_types::Geometry<f64>,
+}
+
+/// The properties of a [`Feature`], as selected via [`Manifest::expansion_properties`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Properties {
+    /// The cost of the path up to the end of the edge
+    pub cost: Option<f64>,
+    /// The time in seconds of the path up to the end of the edge
+    pub duration: Option<f64>,
+    /// The distance in meters of the path up to the end of the edge
+    pub distance: Option<f64>,
+    pub edge_status: Option<EdgeStatus>,
+    /// The id of the edge in valhalla's graph
+    pub edge_id: Option<crate::graph::GraphId>,
+    /// The id of the edge the edge was reached from
+    pub pred_edge_id: Option<crate::graph::GraphId>,
+    /// Further properties, e.g. `expansion_type` for the direction of the search
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn serialisation() {
+        let route = route::Manifest::builder().locations([
+            route::Location::new(4.9, 52.4),
+            route::Location::new(5.1, 52.1),
+        ]);
+        let manifest = Manifest::route(route)
+            .skip_opposites(true)
+            .expansion_properties([Property::Duration, Property::EdgeStatus]);
+        let value = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(value["action"], "route");
+        assert_eq!(value["skip_opposites"], true);
+        assert_eq!(
+            value["expansion_properties"],
+            serde_json::json!(["duration", "edge_status"])
+        );
+        assert_eq!(value["locations"].as_array().map(Vec::len), Some(2));
+
+        let isochrone = isochrone::Manifest::builder().location(route::Location::new(4.9, 52.4));
+        let value = serde_json::to_value(Manifest::isochrone(isochrone)).unwrap();
+        assert_eq!(value["action"], "isochrone");
+        assert!(value.get("dedupe").is_none());
+    }
+    #[test]
+    fn frames() {
+        let feature = |duration: Option<f64>| Feature {
+            type_: "Feature".to_string(),
+            properties: Properties {
+                cost: None,
+                duration,
+                distance: None,
+                edge_status: None,
+                edge_id: None,
+                pred_edge_id: None,
+                other: Default::default(),
+            },
+            geometry: geo_types::Point::new(0.0, 0.0).into(),
+        };
+        let response = Response {
+            type_: "FeatureCollection".to_string(),
+            features: [Some(0.0), Some(10.0), None, Some(5.0), Some(2.5), Some(7.5)]
+                .map(feature)
+                .to_vec(),
+        };
+        let frames: Vec<Vec<_>> = response
+            .frames(4)
+            .iter()
+            .map(|frame| {
+                frame
+                    .features
+                    .iter()
+                    .map(|f| f.properties.duration.unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(frames, [vec![0.0, 2.5], vec![5.0], vec![7.5], vec![10.0]]);
+        assert!(response.frames(0).is_empty());
+    }
+    #[test]
+    fn deserialisation() {
+        let received = serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "geometry": {"type": "LineString", "coordinates": [[4.9, 52.4], [4.91, 52.41]]},
+                "properties": {"duration": 12.5, "edge_status": "settled", "edge_id": 42, "expansion_type": 0},
+            }],
+        });
+        let response: Response = serde_json::from_value(received.clone()).unwrap();
+        let properties = &response.features[0].properties;
+        assert_eq!(properties.edge_status, Some(EdgeStatus::Settled));
+        assert_eq!(properties.edge_id, Some(crate::graph::GraphId(42)));
+        assert_eq!(properties.edge_id.unwrap().level(), 2);
+        assert!(matches!(
+            response.features[0].geometry,
+            geo_types::Geometry::LineString(_)
+        ));
+        assert_eq!(serde_json::to_value(&response).unwrap(), received);
+    }
+}
@@ -0,0 +1,230 @@
+//! Label points for polygons, e.g. to place a "15 min" label on an isochrone
+//!
+//! Computations are in degrees, i.e. in the equirectangular projection most web maps are close to
+//! at the scale of a city.
+//!
+//! # Example:
+//! ```rust
+//! use geo_types::polygon;
+//! use valhalla_client::geometry;
+//!
+//! // an L-shaped polygon, whose centroid lies outside of it
+//! let polygon = polygon![
+//!     (x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 1.0),
+//!     (x: 1.0, y: 1.0), (x: 1.0, y: 10.0), (x: 0.0, y: 10.0),
+//! ];
+//! let label = geometry::pole_of_inaccessibility(&polygon, 0.01).unwrap();
+//! assert!(label.x() < 1.0 && label.y() < 1.0);
+//! ```
+use geo_types::{Coord, LineString, Point, Polygon};
+use std::collections::BinaryHeap;
+
+/// The centroid (center of mass) of a polygon, taking holes into account
+///
+/// May lie outside of concave polygons, see [`pole_of_inaccessibility`] for a point which does not.
+/// Returns `None` if the polygon has no area.
+pub fn centroid(polygon: &Polygon<f64>) -> Option<Point<f64>> {
+    let (mut area, mut x, mut y) = (0.0, 0.0, 0.0);
+    let rings = std::iter::once((polygon.exterior(), 1.0))
+        .chain(polygon.interiors().iter().map(|ring| (ring, -1.0)));
+    for (ring, sign) in rings {
+        // holes count negatively, regardless of their orientation
+        let ring_sign = sign * signed_area(ring).signum();
+        for line in ring.lines() {
+            let cross = line.start.x * line.end.y - line.end.x * line.start.y;
+            area += ring_sign * cross / 2.0;
+            x += ring_sign * (line.start.x + line.end.x) * cross;
+            y += ring_sign * (line.start.y + line.end.y) * cross;
+        }
+    }
+    (area != 0.0).then(|| Point::new(x / (6.0 * area), y / (6.0 * area)))
+}
+
+/// Whether the point lies inside the polygon, i.e. inside its exterior but not inside a hole
+pub fn contains(polygon: &Polygon<f64>, point: Point<f64>) -> bool {
+    let rings = std::iter::once(polygon.exterior()).chain(polygon.interiors());
+    rings
+        .flat_map(LineString::lines)
+        .filter(|line| crosses(point.into(), line.start, line.end))
+        .count()
+        % 2
+        == 1
+}
+
+/// The point inside a polygon which is farthest from its boundary, i.e. the visual center
+///
+/// Found via the [polylabel](https://github.com/mapbox/polylabel) algorithm to within `precision` degrees.
+/// Returns `None` if the polygon is empty.
+pub fn pole_of_inaccessibility(polygon: &Polygon<f64>, precision: f64) -> Option<Point<f64>> {
+    let coords = || polygon.exterior().coords();
+    let min_x = coords().map(|c| c.x).fold(f64::INFINITY, f64::min);
+    let min_y = coords().map(|c| c.y).fold(f64::INFINITY, f64::min);
+    let max_x = coords().map(|c| c.x).fold(f64::NEG_INFINITY, f64::max);
+    let max_y = coords().map(|c| c.y).fold(f64::NEG_INFINITY, f64::max);
+    let cell_size = (max_x - min_x).min(max_y - min_y);
+    if !cell_size.is_finite() {
+        return None;
+    }
+    if cell_size == 0.0 {
+        return Some(Point::new(min_x, min_y));
+    }
+    let precision = precision.max(cell_size * 1e-9);
+
+    let mut queue = BinaryHeap::new();
+    let half = cell_size / 2.0;
+    let mut x = min_x;
+    while x < max_x {
+        let mut y = min_y;
+        while y < max_y {
+            queue.push(Cell::new(x + half, y + half, half, polygon));
+            y += cell_size;
+        }
+        x += cell_size;
+    }
+    let mut best = centroid(polygon)
+        .map(|c| Cell::new(c.x(), c.y(), 0.0, polygon))
+        .filter(|c| c.distance > 0.0)
+        .unwrap_or_else(|| Cell::new(min_x + half, min_y + half, 0.0, polygon));
+    while let Some(cell) = queue.pop() {
+        if cell.distance > best.distance {
+            best = cell.clone();
+        }
+        if cell.max_distance - best.distance <= precision {
+            continue;
+        }
+        let half = cell.half / 2.0;
+        for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let center = cell.center;
+            queue.push(Cell::new(
+                center.x + dx * half,
+                center.y + dy * half,
+                half,
+                polygon,
+            ));
+        }
+    }
+    Some(best.center.into())
+}
+
+/// A square cell of the polylabel search
+#[derive(Debug, Clone)]
+struct Cell {
+    center: Coord<f64>,
+    half: f64,
+    /// The signed distance from the center to the boundary, negative outside of the polygon
+    distance: f64,
+    /// The largest distance any point in the cell can have
+    max_distance: f64,
+}
+
+impl Cell {
+    fn new(x: f64, y: f64, half: f64, polygon: &Polygon<f64>) -> Self {
+        let center = Coord { x, y };
+        let distance = signed_distance(center, polygon);
+        Self {
+            center,
+            half,
+            distance,
+            max_distance: distance + half * std::f64::consts::SQRT_2,
+        }
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.max_distance == other.max_distance
+    }
+}
+impl Eq for Cell {}
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.max_distance.total_cmp(&other.max_distance)
+    }
+}
+
+fn signed_area(ring: &LineString<f64>) -> f64 {
+    ring.lines()
+        .map(|l| l.start.x * l.end.y - l.end.x * l.start.y)
+        .sum::<f64>()
+        / 2.0
+}
+
+/// The distance from `point` to the closest edge of the polygon, negative outside of it
+fn signed_distance(point: Coord<f64>, polygon: &Polygon<f64>) -> f64 {
+    let rings = std::iter::once(polygon.exterior()).chain(polygon.interiors());
+    let min_distance = rings
+        .flat_map(LineString::lines)
+        .map(|line| segment_distance(point, line.start, line.end))
+        .fold(f64::INFINITY, f64::min);
+    if contains(polygon, point.into()) {
+        min_distance
+    } else {
+        -min_distance
+    }
+}
+
+/// Whether a ray from `point` towards positive x crosses the segment from `a` to `b`
+fn crosses(point: Coord<f64>, a: Coord<f64>, b: Coord<f64>) -> bool {
+    (a.y > point.y) != (b.y > point.y)
+        && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+}
+
+fn segment_distance(p: Coord<f64>, a: Coord<f64>, b: Coord<f64>) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((p.x - a.x) * dx + (p.y - a.y) * dy) / length).clamp(0.0, 1.0)
+    };
+    (p.x - (a.x + t * dx)).hypot(p.y - (a.y + t * dy))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geo_types::polygon;
+    #[test]
+    fn label_points() {
+        let square = polygon![
+            (x: 0.0, y: 0.0), (x: 2.0, y: 0.0), (x: 2.0, y: 2.0), (x: 0.0, y: 2.0),
+        ];
+        assert_eq!(centroid(&square), Some(Point::new(1.0, 1.0)));
+        assert!(contains(&square, Point::new(1.0, 1.0)));
+        assert!(!contains(&square, Point::new(3.0, 1.0)));
+        let pole = pole_of_inaccessibility(&square, 0.001).unwrap();
+        assert!((pole.x() - 1.0).abs() < 0.01 && (pole.y() - 1.0).abs() < 0.01);
+
+        let l_shape = polygon![
+            (x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 1.0),
+            (x: 1.0, y: 1.0), (x: 1.0, y: 10.0), (x: 0.0, y: 10.0),
+        ];
+        let centroid = centroid(&l_shape).unwrap();
+        assert!(signed_distance(centroid.into(), &l_shape) < 0.0, "outside");
+        let pole = pole_of_inaccessibility(&l_shape, 0.001).unwrap();
+        // in the corner, where the arms meet
+        assert!(pole.x() < 1.0 && pole.y() < 1.0);
+        assert!(signed_distance(pole.into(), &l_shape) > 0.5);
+
+        let with_hole = Polygon::new(
+            square.exterior().clone(),
+            vec![LineString::from(vec![
+                (0.5, 0.5),
+                (1.5, 0.5),
+                (1.5, 1.5),
+                (0.5, 1.5),
+            ])],
+        );
+        let pole = pole_of_inaccessibility(&with_hole, 0.001).unwrap();
+        assert!(signed_distance(pole.into(), &with_hole) > 0.2);
+        assert_eq!(
+            pole_of_inaccessibility(&Polygon::new(LineString::new(vec![]), vec![]), 0.1),
+            None
+        );
+    }
+}
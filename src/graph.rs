@@ -0,0 +1,438 @@
+//! Types describing valhalla's routing graph, shared by the responses of several actions
+use serde::{Deserialize, Serialize};
+
+/// What an edge of the routing graph is used for
+///
+/// See [`valhalla/baldr/graphconstants.h`](https://github.com/valhalla/valhalla/blob/master/valhalla/baldr/graphconstants.h)
+/// for the full list of uses.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum EdgeUse {
+    // -- ↓ roads ↓ --
+    Road,
+    Ramp,
+    TurnChannel,
+    Track,
+    Driveway,
+    Alley,
+    ParkingAisle,
+    EmergencyAccess,
+    DriveThrough,
+    Culdesac,
+    LivingStreet,
+    ServiceRoad,
+    // -- ↓ bicycle specific ↓ --
+    Cycleway,
+    MountainBike,
+    // -- ↓ pedestrian specific ↓ --
+    Sidewalk,
+    Footway,
+    Elevator,
+    Steps,
+    Escalator,
+    Path,
+    Pedestrian,
+    Bridleway,
+    PedestrianCrossing,
+    // -- ↓ amenities ↓ --
+    RestArea,
+    ServiceArea,
+    // -- ↓ other ↓ --
+    Ferry,
+    #[serde(rename = "rail-ferry")]
+    RailFerry,
+    Construction,
+    // -- ↓ transit specific ↓ --
+    Rail,
+    Bus,
+    EgressConnection,
+    PlatformConnection,
+    TransitConnection,
+    /// Edges with no specific use, or uses unknown to this crate
+    #[serde(other)]
+    Other,
+}
+
+/// A restriction on accessing an edge, e.g. a weight limit on a bridge
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessRestriction {
+    /// The index of the restricted edge, e.g. in [`crate::trace::Response::edges`]
+    pub edge_index: Option<usize>,
+    /// What is restricted
+    #[serde(rename = "type")]
+    pub type_: AccessRestrictionType,
+    /// The limit of the restriction, as encoded by valhalla
+    ///
+    /// - dimensions ([`AccessRestrictionType::MaxHeight`], [`AccessRestrictionType::MaxWidth`],
+    ///   [`AccessRestrictionType::MaxLength`]) are in centimeters,
+    /// - weights ([`AccessRestrictionType::MaxWeight`], [`AccessRestrictionType::MaxAxleLoad`])
+    ///   are in hundredths of metric tons,
+    /// - [`AccessRestrictionType::MaxAxles`] is a count.
+    pub value: u64,
+    /// The restriction does not apply if the edge is needed to reach the destination
+    #[serde(default)]
+    pub except_destination: bool,
+    /// The travel modes the restriction applies to
+    pub modes: AccessModes,
+}
+
+/// What is restricted by an [`AccessRestriction`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AccessRestrictionType {
+    Hazmat,
+    MaxHeight,
+    MaxWidth,
+    MaxLength,
+    MaxWeight,
+    MaxAxleLoad,
+    TimedAllowed,
+    TimedDenied,
+    DestinationAllowed,
+    MaxAxles,
+    /// Restrictions unknown to this crate
+    #[serde(other)]
+    Other,
+}
+
+/// A set of travel modes, encoded as valhalla's access bitmask
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(transparent)]
+pub struct AccessModes(pub u32);
+
+impl AccessModes {
+    pub const AUTO: Self = Self(1);
+    pub const PEDESTRIAN: Self = Self(1 << 1);
+    pub const BICYCLE: Self = Self(1 << 2);
+    pub const TRUCK: Self = Self(1 << 3);
+    pub const EMERGENCY: Self = Self(1 << 4);
+    pub const TAXI: Self = Self(1 << 5);
+    pub const BUS: Self = Self(1 << 6);
+    pub const HOV: Self = Self(1 << 7);
+    pub const WHEELCHAIR: Self = Self(1 << 8);
+    pub const MOPED: Self = Self(1 << 9);
+    pub const MOTORCYCLE: Self = Self(1 << 10);
+
+    /// Whether all modes of `other` are contained in this set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for AccessModes {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// What a node of the routing graph represents
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum NodeType {
+    StreetIntersection,
+    Gate,
+    Bollard,
+    TollBooth,
+    TransitEgress,
+    TransitStation,
+    MultiUseTransitPlatform,
+    BikeShare,
+    Parking,
+    MotorWayJunction,
+    BorderControl,
+    TollGantry,
+    SumpBuster,
+    BuildingEntrance,
+    Elevator,
+    /// Node types unknown to this crate
+    #[serde(other)]
+    Other,
+}
+
+impl NodeType {
+    /// Whether passing this node incurs a toll
+    pub fn is_toll(self) -> bool {
+        matches!(self, Self::TollBooth | Self::TollGantry)
+    }
+    /// Whether this node is a barrier which may block access, e.g. a gate or bollard
+    pub fn is_barrier(self) -> bool {
+        matches!(self, Self::Gate | Self::Bollard | Self::SumpBuster)
+    }
+}
+
+/// The kind of cycle lane along an edge
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CycleLane {
+    #[default]
+    None,
+    /// Shared use lane, e.g. marked with [sharrows](https://wiki.openstreetmap.org/wiki/Tag:cycleway%3Dshared_lane)
+    Shared,
+    /// Dedicated cycle lane, separated from the road by paint
+    Dedicated,
+    /// Cycle track, physically separated from the road
+    Separated,
+}
+
+/// The cycle networks an edge is part of, encoded as valhalla's bitmask
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(transparent)]
+pub struct BicycleNetwork(pub u8);
+
+impl BicycleNetwork {
+    pub const NATIONAL: Self = Self(1);
+    pub const REGIONAL: Self = Self(1 << 1);
+    pub const LOCAL: Self = Self(1 << 2);
+    pub const MOUNTAIN: Self = Self(1 << 3);
+
+    /// Whether all networks of `other` are contained in this set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+    /// Whether the edge is part of any cycle network
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Where the speed of an edge originates from
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum SpeedType {
+    /// The speed was tagged explicitly, e.g. via [`maxspeed`](https://wiki.openstreetmap.org/wiki/Key:maxspeed)
+    Tagged,
+    /// The speed was derived from the road class and the road density around the edge
+    Classified,
+    /// Speed sources unknown to this crate
+    #[serde(other)]
+    Other,
+}
+
+/// The id of a node or edge in valhalla's tiled routing graph
+///
+/// Consists of the hierarchy level, the tile within the level and the index within the tile,
+/// see [`valhalla/baldr/graphid.h`](https://github.com/valhalla/valhalla/blob/master/valhalla/baldr/graphid.h).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct GraphId(pub u64);
+
+impl GraphId {
+    const LEVEL_BITS: u32 = 3;
+    const TILE_BITS: u32 = 22;
+    const INDEX_BITS: u32 = 21;
+    /// The id valhalla uses for missing nodes or edges
+    pub const INVALID: Self =
+        Self((1 << (Self::LEVEL_BITS + Self::TILE_BITS + Self::INDEX_BITS)) - 1);
+
+    /// Composes an id, returning `None` if a component is out of range
+    pub fn new(level: u8, tile: u32, index: u32) -> Option<Self> {
+        let fits = |value: u64, bits: u32| value < (1_u64 << bits);
+        let all_fit = fits(u64::from(level), Self::LEVEL_BITS)
+            && fits(u64::from(tile), Self::TILE_BITS)
+            && fits(u64::from(index), Self::INDEX_BITS);
+        all_fit.then(|| {
+            Self(
+                u64::from(level)
+                    | u64::from(tile) << Self::LEVEL_BITS
+                    | u64::from(index) << (Self::LEVEL_BITS + Self::TILE_BITS),
+            )
+        })
+    }
+    /// The hierarchy level, `0` (highways) to `2` (local roads), or `3` for transit
+    pub fn level(self) -> u8 {
+        (self.0 & ((1 << Self::LEVEL_BITS) - 1)) as u8
+    }
+    /// The id of the tile within its level
+    pub fn tile(self) -> u32 {
+        ((self.0 >> Self::LEVEL_BITS) & ((1 << Self::TILE_BITS) - 1)) as u32
+    }
+    /// The index of the node or edge within its tile
+    pub fn index(self) -> u32 {
+        ((self.0 >> (Self::LEVEL_BITS + Self::TILE_BITS)) & ((1 << Self::INDEX_BITS) - 1)) as u32
+    }
+    /// The id of the tile, i.e. with an index of `0`
+    pub fn tile_id(self) -> Self {
+        Self(self.0 & ((1 << (Self::LEVEL_BITS + Self::TILE_BITS)) - 1))
+    }
+    /// Whether this is not [`Self::INVALID`]
+    pub fn is_valid(self) -> bool {
+        self != Self::INVALID
+    }
+    /// The path of the tile relative to the tile directory, e.g. `2/000/818/660.gph`
+    pub fn tile_path(self) -> String {
+        // the tiles of levels 0, 1 and 2 (and transit) are 4°, 1° and 0.25° wide
+        let tiles_in_level: u32 = match self.level() {
+            0 => 90 * 45,
+            1 => 360 * 180,
+            _ => 1440 * 720,
+        };
+        let digits = (tiles_in_level - 1).to_string().len().next_multiple_of(3);
+        let tile = format!("{:0digits$}", self.tile());
+        let groups: Vec<_> = tile
+            .as_bytes()
+            .chunks(3)
+            .map(|g| String::from_utf8_lossy(g))
+            .collect();
+        format!("{}/{}.gph", self.level(), groups.join("/"))
+    }
+}
+
+impl std::fmt::Display for GraphId {
+    /// Formats the id like valhalla does, as `level/tile/index`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}/{}", self.level(), self.tile(), self.index())
+    }
+}
+
+/// (De)serialization of an optional [`GraphId`] in the verbose form of the locate action
+///
+/// The id is an object of its components, e.g. `{"id": 1234, "level": 2, "tile_id": 818660, "value": 41..}`.
+/// Plain numbers are accepted as well.
+pub(crate) mod verbose_id {
+    use super::GraphId;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Verbose {
+        id: u32,
+        level: u8,
+        tile_id: u32,
+        value: GraphId,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Plain(GraphId),
+        Verbose { value: GraphId },
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        id: &Option<GraphId>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        id.map(|value| Verbose {
+            id: value.index(),
+            level: value.level(),
+            tile_id: value.tile(),
+            value,
+        })
+        .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<GraphId>, D::Error> {
+        Ok(
+            Option::<Repr>::deserialize(deserializer)?.map(|id| match id {
+                Repr::Plain(value) | Repr::Verbose { value } => value,
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn graph_id() {
+        let id = GraphId::new(2, 818_660, 1_234).unwrap();
+        assert_eq!(
+            serde_json::from_value::<GraphId>(serde_json::json!(id.0)).unwrap(),
+            id
+        );
+        assert_eq!((id.level(), id.tile(), id.index()), (2, 818_660, 1_234));
+        assert_eq!(id.to_string(), "2/818660/1234");
+        assert_eq!(id.tile_id(), GraphId::new(2, 818_660, 0).unwrap());
+        assert_eq!(id.tile_path(), "2/000/818/660.gph");
+        assert_eq!(
+            GraphId::new(0, 3_015, 0).unwrap().tile_path(),
+            "0/003/015.gph"
+        );
+        assert!(id.is_valid());
+        assert!(!GraphId::INVALID.is_valid());
+        assert_eq!(GraphId::new(8, 0, 0), None);
+    }
+    #[test]
+    fn speed_type_deserialisation() {
+        let types: Vec<SpeedType> =
+            serde_json::from_value(serde_json::json!(["tagged", "classified", "predicted"]))
+                .unwrap();
+        assert_eq!(
+            types,
+            [SpeedType::Tagged, SpeedType::Classified, SpeedType::Other]
+        );
+    }
+    #[test]
+    fn bicycle_attributes_deserialisation() {
+        let (lane, network): (CycleLane, BicycleNetwork) =
+            serde_json::from_value(serde_json::json!(["separated", 5])).unwrap();
+        assert_eq!(lane, CycleLane::Separated);
+        assert!(network.contains(BicycleNetwork::NATIONAL));
+        assert!(network.contains(BicycleNetwork::LOCAL));
+        assert!(!network.contains(BicycleNetwork::REGIONAL));
+        assert!(BicycleNetwork::default().is_empty());
+    }
+    #[test]
+    fn node_type_deserialisation() {
+        let types: Vec<NodeType> = serde_json::from_value(serde_json::json!([
+            "toll_booth",
+            "gate",
+            "border_control",
+            "street_intersection",
+        ]))
+        .unwrap();
+        assert_eq!(
+            types.iter().filter(|t| t.is_toll()).count(),
+            1,
+            "only the toll booth is a toll"
+        );
+        assert!(types[1].is_barrier());
+        assert_eq!(types[2], NodeType::BorderControl);
+    }
+    #[test]
+    fn access_restriction_deserialisation() {
+        let restriction: AccessRestriction = serde_json::from_value(serde_json::json!({
+            "type": "max_weight", "value": 750, "modes": 8, "edge_index": 0,
+        }))
+        .unwrap();
+        assert_eq!(restriction.edge_index, Some(0));
+        assert_eq!(restriction.type_, AccessRestrictionType::MaxWeight);
+        assert_eq!(restriction.value, 750);
+        assert!(!restriction.except_destination);
+        assert!(restriction.modes.contains(AccessModes::TRUCK));
+        assert!(!restriction
+            .modes
+            .contains(AccessModes::TRUCK | AccessModes::AUTO));
+    }
+    #[test]
+    fn edge_use_deserialisation() {
+        let uses: Vec<EdgeUse> = serde_json::from_value(serde_json::json!([
+            "road",
+            "turn_channel",
+            "rail-ferry",
+            "transit_connection",
+            "other",
+            "some_future_use",
+        ]))
+        .unwrap();
+        assert_eq!(
+            uses,
+            [
+                EdgeUse::Road,
+                EdgeUse::TurnChannel,
+                EdgeUse::RailFerry,
+                EdgeUse::TransitConnection,
+                EdgeUse::Other,
+                EdgeUse::Other,
+            ]
+        );
+        assert_eq!(serde_json::to_value(EdgeUse::Other).unwrap(), "other");
+    }
+}
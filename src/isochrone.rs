@@ -0,0 +1,820 @@
+//! Isochrones, i.e. the areas reachable from a location within given times or distances
+//!
+//! See <https://valhalla.github.io/valhalla/api/isochrone/api-reference/> for details
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::costing::Costing;
+//! use valhalla_client::isochrone::{Contour, Manifest};
+//! use valhalla_client::route::Location;
+//!
+//! let manifest = Manifest::builder()
+//!   .location(Location::new(4.9041, 52.3676))
+//!   .costing(Costing::Pedestrian(Default::default()))
+//!   .contours([Contour::time(15.0).color("ff0000"), Contour::time(30.0)])
+//!   .polygons(true);
+//! let response = Valhalla::default().isochrone(manifest).unwrap();
+//! for feature in response.contours() {
+//!   println!("{:?} {:?}", feature.properties.contour, feature.properties.metric);
+//! }
+//! ```
+use crate::costing;
+use crate::route::{DateTime, Location};
+use serde::{Deserialize, Serialize};
+
+/// A time or distance limit of an isochrone
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Contour {
+    time: Option<f64>,
+    distance: Option<f64>,
+    color: Option<String>,
+}
+impl Contour {
+    /// The area reachable within `minutes`
+    pub fn time(minutes: f64) -> Self {
+        Self {
+            time: Some(minutes),
+            ..Default::default()
+        }
+    }
+    /// The area reachable within `kilometers`
+    pub fn distance(kilometers: f64) -> Self {
+        Self {
+            distance: Some(kilometers),
+            ..Default::default()
+        }
+    }
+    /// The color of the contour in the response, as hex RGB value without `#`, e.g. `ff0000`
+    ///
+    /// Default: chosen by valhalla
+    pub fn color(mut self, color: impl ToString) -> Self {
+        self.color = Some(color.to_string());
+        self
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Default, Debug, Clone)]
+pub struct Manifest {
+    #[serde(flatten)]
+    costing: Option<costing::Costing>,
+    locations: Vec<Location>,
+    contours: Vec<Contour>,
+    date_time: Option<DateTime>,
+    id: Option<String>,
+    polygons: Option<bool>,
+    denoise: Option<f64>,
+    generalize: Option<f64>,
+    show_locations: Option<bool>,
+}
+impl Manifest {
+    #[must_use]
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Sets the location to compute the isochrone for
+    ///
+    /// **Note:** valhalla currently supports a single location only.
+    pub fn location(mut self, location: Location) -> Self {
+        self.locations = vec![location];
+        self
+    }
+    /// Configures the costing model
+    ///
+    /// **Note:** multimodal costing is not supported for isochrones.
+    ///
+    /// Default: [`costing::Costing::Auto`]
+    pub fn costing(mut self, costing: costing::Costing) -> Self {
+        self.costing = Some(costing);
+        self
+    }
+    /// Sets the time or distance limits, one contour per limit
+    ///
+    /// The limits of all contours must be of the same kind, i.e. either times or distances.
+    pub fn contours(mut self, contours: impl IntoIterator<Item = Contour>) -> Self {
+        self.contours = contours.into_iter().collect();
+        self
+    }
+    /// Sets the date and time of departure or arrival, see [`DateTime`]
+    ///
+    /// With an arrival time, the isochrone contains the locations from which the location is reachable in time.
+    ///
+    /// Default: time independent isochrones
+    pub fn date_time(mut self, date_time: DateTime) -> Self {
+        self.date_time = Some(date_time);
+        self
+    }
+    /// Name your isochrone request.
+    ///
+    /// If id is specified, the naming will be sent through to the response.
+    pub fn id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+    /// Returns the contours as polygons instead of lines
+    ///
+    /// Default: `false`, i.e. [`geo_types::Geometry::LineString`]s
+    pub fn polygons(mut self, polygons: bool) -> Self {
+        self.polygons = Some(polygons);
+        self
+    }
+    /// Removes smaller contours, relative to the largest one
+    ///
+    /// A value of `1.0` keeps only the largest contour, `0.5` those at least half as large.
+    ///
+    /// Default: `1.0`
+    pub fn denoise(mut self, denoise: f64) -> Self {
+        self.denoise = Some(denoise);
+        self
+    }
+    /// Simplifies the contours with the given tolerance in meters
+    ///
+    /// Default: chosen by valhalla
+    pub fn generalize(mut self, meters: f64) -> Self {
+        self.generalize = Some(meters);
+        self
+    }
+    /// Includes the requested and the snapped location as point features in the response
+    ///
+    /// Default: `false`
+    pub fn show_locations(mut self, show_locations: bool) -> Self {
+        self.show_locations = Some(show_locations);
+        self
+    }
+
+    /// One manifest per facility and chunk of at most `max_contours` contours, see [`crate::Valhalla::isochrone_sweep`]
+    ///
+    /// Each manifest is paired with the index of its facility.
+    pub(crate) fn sweep(&self, facilities: &[Location], max_contours: usize) -> Vec<(usize, Self)> {
+        let mut chunks: Vec<&[Contour]> = self.contours.chunks(max_contours.max(1)).collect();
+        if chunks.is_empty() {
+            // still one request per facility, so each gets a response or an error
+            chunks.push(&[]);
+        }
+        facilities
+            .iter()
+            .enumerate()
+            .flat_map(|(i, facility)| {
+                chunks.iter().map(move |contours| {
+                    let manifest = self
+                        .clone()
+                        .location(facility.clone())
+                        .contours(contours.to_vec());
+                    (i, manifest)
+                })
+            })
+            .collect()
+    }
+}
+
+/// The kind of limit a contour is computed for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    Time,
+    Distance,
+}
+
+/// The GeoJSON `FeatureCollection` of an isochrone
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Response {
+    /// The id set via [`Manifest::id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Always `FeatureCollection`
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The contours, largest first, followed by the locations if requested via [`Manifest::show_locations`]
+    pub features: Vec<Feature>,
+}
+
+impl Response {
+    /// The features which are contours, i.e. not locations
+    pub fn contours(&self) -> impl Iterator<Item = &Feature> {
+        self.features
+            .iter()
+            .filter(|f| f.properties.contour.is_some())
+    }
+    /// Fills the holes of the contours which are smaller than `min_area` square meters
+    ///
+    /// Tiny holes, e.g. around a single unreachable building, break downstream union operations and
+    /// are mostly noise. Removing them is applied to [`geo_types::Polygon`]s and [`geo_types::MultiPolygon`]s,
+    /// see [`Manifest::polygons`].
+    /// Areas are approximated on a plane, see [`Self::drop_islands`].
+    pub fn fill_holes(&mut self, min_area: f64) {
+        let fill = |polygon: &mut geo_types::Polygon<f64>| {
+            let empty = geo_types::Polygon::new(geo_types::LineString::new(Vec::new()), Vec::new());
+            let (exterior, mut holes) = std::mem::replace(polygon, empty).into_inner();
+            holes.retain(|hole| ring_area(hole) >= min_area);
+            *polygon = geo_types::Polygon::new(exterior, holes);
+        };
+        for feature in &mut self.features {
+            match &mut feature.geometry {
+                geo_types::Geometry::Polygon(polygon) => fill(polygon),
+                geo_types::Geometry::MultiPolygon(polygons) => polygons.iter_mut().for_each(fill),
+                _ => {}
+            }
+        }
+    }
+    /// Drops the polygons of multi-polygon contours which are smaller than `min_area` square meters
+    ///
+    /// The counterpart to [`Self::fill_holes`] for tiny disconnected islands of reachability,
+    /// e.g. around a single reachable road segment. The holes within the dropped polygons are not considered.
+    /// Contours which would become empty, as well as plain [`geo_types::Polygon`]s, are kept as they are.
+    ///
+    /// The area of a ring is approximated by projecting it onto a plane, scaled at the latitude of
+    /// its first vertex. The relative error is about `tan(latitude)` times the latitudinal extent
+    /// of the ring in radians, i.e. below 1% for rings up to 50 km across at mid-latitudes.
+    pub fn drop_islands(&mut self, min_area: f64) {
+        for feature in &mut self.features {
+            if let geo_types::Geometry::MultiPolygon(polygons) = &mut feature.geometry {
+                if polygons.iter().any(|p| ring_area(p.exterior()) >= min_area) {
+                    polygons.0.retain(|p| ring_area(p.exterior()) >= min_area);
+                }
+            }
+        }
+    }
+    /// The smallest contour limit reaching the center of each cell of the grid, e.g. in minutes for aggregate statistics
+    ///
+    /// Returns one row per latitude of the grid, from south to north, with one value per longitude,
+    /// from west to east. Cells which no contour reaches are `None`.
+    /// Only contours returned as polygons are considered, see [`Manifest::polygons`].
+    pub fn rasterize(&self, grid: &Grid) -> Vec<Vec<Option<f64>>> {
+        let mut contours = self.contour_polygons();
+        contours.sort_by(|a, b| a.0.total_cmp(&b.0));
+        (0..grid.rows)
+            .map(|row| {
+                (0..grid.columns)
+                    .map(|column| {
+                        let center = grid.center(row, column);
+                        contours
+                            .iter()
+                            .find(|(_, _, polygons)| {
+                                polygons
+                                    .iter()
+                                    .any(|p| crate::geometry::contains(p, center))
+                            })
+                            .map(|(contour, _, _)| *contour)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    /// The total weight of the points reached by each contour, e.g. the population within 15 and 30 minutes
+    ///
+    /// Takes the points with their weights, e.g. the centroids of census areas and their population.
+    /// Returns one [`Accessibility`] per contour, in the order of [`Self::contours`]; as the contours
+    /// are nested, a point counts towards every contour reaching it.
+    /// Only contours returned as polygons are considered, see [`Manifest::polygons`].
+    pub fn reachable_weight(
+        &self,
+        points: impl IntoIterator<Item = (crate::Coordinate, f64)>,
+    ) -> Vec<Accessibility> {
+        let contours = self.contour_polygons();
+        let mut weights = vec![0.0; contours.len()];
+        for ((lon, lat), weight) in points {
+            let point = geo_types::Point::new(f64::from(lon), f64::from(lat));
+            for (total, (_, _, polygons)) in weights.iter_mut().zip(&contours) {
+                if polygons.iter().any(|p| crate::geometry::contains(p, point)) {
+                    *total += weight;
+                }
+            }
+        }
+        contours
+            .into_iter()
+            .zip(weights)
+            .map(|((contour, metric, _), weight)| Accessibility {
+                contour,
+                metric,
+                weight,
+            })
+            .collect()
+    }
+    /// The limit, metric and polygons of each contour returned as polygons
+    fn contour_polygons(&self) -> Vec<(f64, Metric, Vec<&geo_types::Polygon<f64>>)> {
+        self.contours()
+            .filter_map(|feature| {
+                let polygons = match &feature.geometry {
+                    geo_types::Geometry::Polygon(polygon) => vec![polygon],
+                    geo_types::Geometry::MultiPolygon(polygons) => polygons.iter().collect(),
+                    _ => return None,
+                };
+                let properties = &feature.properties;
+                Some((properties.contour?, properties.metric?, polygons))
+            })
+            .collect()
+    }
+    /// Joins the responses for chunks of the contours of one location, see [`crate::Valhalla::isochrone_sweep`]
+    ///
+    /// The contours are ordered largest first, followed by the locations of the first response.
+    pub(crate) fn merge(responses: Vec<Self>) -> Option<Self> {
+        let mut responses = responses.into_iter();
+        let mut merged = responses.next()?;
+        for response in responses {
+            let contours = response.features.into_iter();
+            merged
+                .features
+                .extend(contours.filter(|f| f.properties.contour.is_some()));
+        }
+        // locations last, then by descending limit
+        merged.features.sort_by(|a, b| {
+            let (a, b) = (a.properties.contour, b.properties.contour);
+            b.is_some()
+                .cmp(&a.is_some())
+                .then_with(|| b.unwrap_or_default().total_cmp(&a.unwrap_or_default()))
+        });
+        Some(merged)
+    }
+    /// The response as GeoJSON `FeatureCollection`
+    #[cfg(feature = "geojson")]
+    pub fn to_geojson(&self) -> Result<geojson::FeatureCollection, serde_json::Error> {
+        serde_json::from_value(serde_json::to_value(self)?)
+    }
+}
+
+/// The weight reached by a contour, see [`Response::reachable_weight`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accessibility {
+    /// The limit of the contour, see [`Properties::contour`]
+    pub contour: f64,
+    pub metric: Metric,
+    /// The sum of the weights of the points within the contour
+    pub weight: f64,
+}
+
+/// A regular grid of cells in degrees, see [`Response::rasterize`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    /// The longitude of the western edge of the grid
+    pub min_lon: f64,
+    /// The latitude of the southern edge of the grid
+    pub min_lat: f64,
+    /// The width and height of a cell in degrees
+    pub cell_size: f64,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl Grid {
+    /// The center of the cell in the `row`-th row from the south and the `column`-th column from the west
+    pub fn center(&self, row: usize, column: usize) -> geo_types::Point<f64> {
+        geo_types::Point::new(
+            self.min_lon + (column as f64 + 0.5) * self.cell_size,
+            self.min_lat + (row as f64 + 0.5) * self.cell_size,
+        )
+    }
+}
+
+/// The area covered by a contour of several isochrones, see [`union_contours`]
+#[cfg(feature = "geo")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    /// The limit of the contour, see [`Properties::contour`]
+    pub contour: f64,
+    pub metric: Metric,
+    /// The union of the contours of all isochrones with this limit
+    pub area: geo_types::MultiPolygon<f64>,
+}
+
+/// Unions the contours with the same limit of several isochrones, e.g. one per origin of a coverage map
+///
+/// Returns one [`Coverage`] per limit, ordered by limit.
+/// Only contours returned as polygons are included, see [`Manifest::polygons`].
+#[cfg(feature = "geo")]
+pub fn union_contours<'a>(responses: impl IntoIterator<Item = &'a Response>) -> Vec<Coverage> {
+    let mut contours: Vec<(f64, Metric, Vec<geo_types::Polygon<f64>>)> = Vec::new();
+    let features = responses.into_iter().flat_map(|r| &r.features);
+    for feature in features {
+        let (Some(contour), Some(metric)) = (feature.properties.contour, feature.properties.metric)
+        else {
+            continue;
+        };
+        let polygons = match &feature.geometry {
+            geo_types::Geometry::Polygon(polygon) => vec![polygon.clone()],
+            geo_types::Geometry::MultiPolygon(polygons) => polygons.0.clone(),
+            _ => continue,
+        };
+        match contours
+            .iter_mut()
+            .find(|(c, m, _)| *c == contour && *m == metric)
+        {
+            Some((_, _, all)) => all.extend(polygons),
+            None => contours.push((contour, metric, polygons)),
+        }
+    }
+    contours.sort_by(|a, b| a.0.total_cmp(&b.0));
+    contours
+        .into_iter()
+        .map(|(contour, metric, polygons)| Coverage {
+            contour,
+            metric,
+            area: geo::unary_union(&polygons),
+        })
+        .collect()
+}
+
+/// The area covered by both isochrones per contour limit, e.g. the catchment shared by two depots
+///
+/// Returns one [`Coverage`] per limit of both isochrones, ordered by limit.
+/// Only contours returned as polygons are included, see [`Manifest::polygons`].
+#[cfg(feature = "geo")]
+pub fn overlap_contours(a: &Response, b: &Response) -> Vec<Coverage> {
+    let b = union_contours([b]);
+    union_contours([a])
+        .into_iter()
+        .filter_map(|coverage| {
+            let other = b
+                .iter()
+                .find(|o| o.contour == coverage.contour && o.metric == coverage.metric)?;
+            Some(Coverage {
+                area: geo::BooleanOps::intersection(&coverage.area, &other.area),
+                ..coverage
+            })
+        })
+        .collect()
+}
+
+/// The area covered by isochrone `a` but not by `b` per contour limit, e.g. the coverage gained by a new depot
+///
+/// Returns one [`Coverage`] per limit of `a`, ordered by limit. Limits which `b` lacks are returned whole.
+/// Only contours returned as polygons are included, see [`Manifest::polygons`].
+#[cfg(feature = "geo")]
+pub fn difference_contours(a: &Response, b: &Response) -> Vec<Coverage> {
+    let b = union_contours([b]);
+    union_contours([a])
+        .into_iter()
+        .map(|coverage| {
+            match b
+                .iter()
+                .find(|o| o.contour == coverage.contour && o.metric == coverage.metric)
+            {
+                Some(other) => Coverage {
+                    area: geo::BooleanOps::difference(&coverage.area, &other.area),
+                    ..coverage
+                },
+                None => coverage,
+            }
+        })
+        .collect()
+}
+
+/// A contour or location of an isochrone
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Feature {
+    /// Always `Feature`
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub properties: Properties,
+    #[serde(with = "geometry")]
+    pub geometry: geo_types::Geometry<f64>,
+}
+
+/// The properties of a [`Feature`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Properties {
+    /// The limit of the contour, in minutes or kilometers depending on the [`Self::metric`]
+    ///
+    /// `None` for locations.
+    pub contour: Option<f64>,
+    /// `None` for locations.
+    pub metric: Option<Metric>,
+    /// The color of the contour as hex RGB value, e.g. `#ff0000`
+    pub color: Option<String>,
+    /// Further properties, such as the styling properties `fill`, `fill-opacity` and `opacity` of contours
+    /// or the `location_index` and `type` of locations
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The area of a ring in square meters, assuming it is small enough to be projected onto a plane
+///
+/// See [`Response::drop_islands`] for the error of this approximation.
+fn ring_area(ring: &geo_types::LineString<f64>) -> f64 {
+    let Some(first) = ring.0.first() else {
+        return 0.0;
+    };
+    let meters_per_degree = crate::shapes::EARTH_RADIUS.to_radians();
+    let x_scale = meters_per_degree * first.y.to_radians().cos();
+    let twice_area: f64 = ring
+        .lines()
+        .map(|line| {
+            let (start, end) = (line.start - *first, line.end - *first);
+            start.x * end.y - end.x * start.y
+        })
+        .sum();
+    (twice_area / 2.0).abs() * x_scale * meters_per_degree
+}
+
+/// (De)serialization of GeoJSON geometries as [`geo_types::Geometry`]
+pub(crate) mod geometry {
+    use geo_types::{
+        Geometry, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon,
+    };
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    type Ring = Vec<(f64, f64)>;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "type", content = "coordinates")]
+    enum GeoJson {
+        Point((f64, f64)),
+        MultiPoint(Ring),
+        LineString(Ring),
+        MultiLineString(Vec<Ring>),
+        Polygon(Vec<Ring>),
+        MultiPolygon(Vec<Vec<Ring>>),
+    }
+
+    fn ring(line: &LineString<f64>) -> Ring {
+        line.coords().map(|c| (c.x, c.y)).collect()
+    }
+    fn rings(polygon: &Polygon<f64>) -> Vec<Ring> {
+        std::iter::once(polygon.exterior())
+            .chain(polygon.interiors())
+            .map(ring)
+            .collect()
+    }
+    fn polygon(mut rings: Vec<Ring>) -> Polygon<f64> {
+        if rings.is_empty() {
+            return Polygon::new(LineString::new(Vec::new()), Vec::new());
+        }
+        let exterior = rings.remove(0).into();
+        Polygon::new(exterior, rings.into_iter().map(LineString::from).collect())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Geometry<f64>, D::Error> {
+        Ok(match GeoJson::deserialize(deserializer)? {
+            GeoJson::Point(point) => Point::from(point).into(),
+            GeoJson::MultiPoint(points) => MultiPoint::from(points).into(),
+            GeoJson::LineString(line) => LineString::from(line).into(),
+            GeoJson::MultiLineString(lines) => {
+                MultiLineString::new(lines.into_iter().map(LineString::from).collect()).into()
+            }
+            GeoJson::Polygon(rings) => polygon(rings).into(),
+            GeoJson::MultiPolygon(polygons) => {
+                MultiPolygon::new(polygons.into_iter().map(polygon).collect()).into()
+            }
+        })
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        geometry: &Geometry<f64>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let geometry = match geometry {
+            Geometry::Point(point) => GeoJson::Point(point.x_y()),
+            Geometry::MultiPoint(points) => {
+                GeoJson::MultiPoint(points.iter().map(|p| p.x_y()).collect())
+            }
+            Geometry::LineString(line) => GeoJson::LineString(ring(line)),
+            Geometry::MultiLineString(lines) => {
+                GeoJson::MultiLineString(lines.iter().map(ring).collect())
+            }
+            Geometry::Polygon(p) => GeoJson::Polygon(rings(p)),
+            Geometry::MultiPolygon(polygons) => {
+                GeoJson::MultiPolygon(polygons.iter().map(rings).collect())
+            }
+            _ => {
+                return Err(serde::ser::Error::custom(
+                    "unsupported GeoJSON geometry type",
+                ))
+            }
+        };
+        geometry.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    /// A contour in minutes, or a location if `contour` is `None`
+    fn feature(contour: Option<f64>, geometry: impl Into<geo_types::Geometry<f64>>) -> Feature {
+        Feature {
+            type_: "Feature".to_string(),
+            properties: Properties {
+                contour,
+                metric: contour.map(|_| Metric::Time),
+                color: None,
+                other: Default::default(),
+            },
+            geometry: geometry.into(),
+        }
+    }
+    fn response(features: impl IntoIterator<Item = Feature>) -> Response {
+        Response {
+            id: None,
+            type_: "FeatureCollection".to_string(),
+            features: features.into_iter().collect(),
+        }
+    }
+    /// The ring of a square with its lower left corner at `(x, 0)`
+    fn square(x: f64, size: f64) -> geo_types::LineString<f64> {
+        geo_types::LineString::from(vec![
+            (x, 0.0),
+            (x + size, 0.0),
+            (x + size, size),
+            (x, size),
+            (x, 0.0),
+        ])
+    }
+    fn filled_square(x: f64, size: f64) -> geo_types::Polygon<f64> {
+        geo_types::Polygon::new(square(x, size), Vec::new())
+    }
+    #[test]
+    fn serialisation() {
+        let manifest = Manifest::builder()
+            .location(Location::new(4.9, 52.3))
+            .contours([Contour::time(15.0).color("ff0000"), Contour::time(30.0)])
+            .polygons(true)
+            .denoise(0.5);
+        assert_eq!(
+            serde_json::to_value(&manifest).unwrap(),
+            serde_json::json!({
+                "locations": [{"lat": 52.3_f32, "lon": 4.9_f32}],
+                "contours": [{"time": 15.0, "color": "ff0000"}, {"time": 30.0}],
+                "polygons": true,
+                "denoise": 0.5,
+            })
+        );
+    }
+    #[test]
+    fn sweep() {
+        let manifest = Manifest::builder()
+            .contours([5.0, 10.0, 15.0, 20.0, 25.0].map(Contour::time))
+            .polygons(true);
+        let facilities = [Location::new(4.9, 52.3), Location::new(5.1, 52.1)];
+        let manifests = manifest.sweep(&facilities, 2);
+        let indices: Vec<usize> = manifests.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, [0, 0, 0, 1, 1, 1]);
+        assert_eq!(
+            serde_json::to_value(&manifests[5].1).unwrap(),
+            serde_json::json!({
+                "locations": [{"lat": 52.1_f32, "lon": 5.1_f32}],
+                "contours": [{"time": 25.0}],
+                "polygons": true,
+            })
+        );
+
+        let contours = |contours: &[f64]| {
+            let contours = contours.iter().map(|&contour| Some(contour));
+            response(
+                contours
+                    .chain([None])
+                    .map(|contour| feature(contour, geo_types::Point::new(0.0, 0.0))),
+            )
+        };
+        let merged = Response::merge(vec![
+            contours(&[10.0, 5.0]),
+            contours(&[20.0, 15.0]),
+            contours(&[25.0]),
+        ])
+        .unwrap();
+        let contours: Vec<_> = merged
+            .features
+            .iter()
+            .map(|f| f.properties.contour)
+            .collect();
+        assert_eq!(
+            contours,
+            [
+                Some(25.0),
+                Some(20.0),
+                Some(15.0),
+                Some(10.0),
+                Some(5.0),
+                None
+            ]
+        );
+        assert!(Response::merge(Vec::new()).is_none());
+        assert_eq!(Manifest::builder().sweep(&facilities, 2).len(), 2);
+    }
+    #[test]
+    fn deserialisation() {
+        let received = serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "properties": {"fill-opacity": 0.33, "fill": "#ff0000", "contour": 15.0, "metric": "time", "color": "#ff0000"},
+                    "geometry": {"type": "Polygon", "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]]},
+                },
+                {
+                    "type": "Feature",
+                    "properties": {"location_index": 0, "type": "snapped"},
+                    "geometry": {"type": "MultiPoint", "coordinates": [[0.5, 0.5]]},
+                },
+            ],
+        });
+        let response: Response = serde_json::from_value(received.clone()).unwrap();
+        let contours: Vec<_> = response.contours().collect();
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours[0].properties.metric, Some(Metric::Time));
+        assert_eq!(contours[0].properties.other["fill-opacity"], 0.33);
+        let geo_types::Geometry::Polygon(polygon) = &contours[0].geometry else {
+            panic!("expected a polygon, got {:?}", contours[0].geometry);
+        };
+        assert_eq!(polygon.exterior().0.len(), 4);
+        assert_eq!(serde_json::to_value(&response).unwrap(), received);
+    }
+    #[test]
+    #[cfg(feature = "geo")]
+    fn unions_contours() {
+        let isochrone = |x: f64| {
+            response([
+                feature(Some(30.0), filled_square(x, 2.0)),
+                feature(Some(15.0), filled_square(x, 1.0)),
+            ])
+        };
+        // overlapping 30 minute contours, but disjoint 15 minute contours
+        let coverage = union_contours(&[isochrone(0.0), isochrone(1.5)]);
+        let contours: Vec<_> = coverage.iter().map(|c| c.contour).collect();
+        assert_eq!(contours, [15.0, 30.0]);
+        assert_eq!(coverage[0].area.0.len(), 2);
+        assert_eq!(coverage[1].area.0.len(), 1);
+        use geo::Area;
+        assert!((coverage[1].area.unsigned_area() - 7.0).abs() < 1e-9);
+
+        let areas = |coverage: Vec<Coverage>| -> Vec<f64> {
+            coverage.iter().map(|c| c.area.unsigned_area()).collect()
+        };
+        let overlap = areas(overlap_contours(&isochrone(0.0), &isochrone(1.5)));
+        assert!(overlap[0].abs() < 1e-9 && (overlap[1] - 1.0).abs() < 1e-9);
+        let gained = areas(difference_contours(&isochrone(1.5), &isochrone(0.0)));
+        assert!((gained[0] - 1.0).abs() < 1e-9 && (gained[1] - 3.0).abs() < 1e-9);
+        // limits of only one isochrone
+        let mut other_limits = isochrone(1.5);
+        other_limits.features[0].properties.contour = Some(45.0);
+        assert_eq!(overlap_contours(&isochrone(0.0), &other_limits).len(), 1);
+        let difference = difference_contours(&isochrone(0.0), &other_limits);
+        assert_eq!(difference[1], union_contours([&isochrone(0.0)])[1]);
+    }
+    #[test]
+    fn rasterize() {
+        let response = response([
+            feature(Some(30.0), filled_square(0.0, 2.0)),
+            feature(Some(15.0), filled_square(0.0, 1.0)),
+            feature(None, geo_types::Point::new(0.5, 0.5)),
+        ]);
+        let grid = Grid {
+            min_lon: 0.0,
+            min_lat: 0.0,
+            cell_size: 1.0,
+            columns: 3,
+            rows: 2,
+        };
+        assert_eq!(grid.center(1, 2), geo_types::Point::new(2.5, 1.5));
+        let census = [((0.5, 0.5), 100.0), ((1.5, 0.5), 20.0), ((5.0, 5.0), 1.0)];
+        let reached: Vec<_> = response
+            .reachable_weight(census)
+            .iter()
+            .map(|a| (a.contour, a.weight))
+            .collect();
+        assert_eq!(reached, [(30.0, 120.0), (15.0, 100.0)]);
+        assert_eq!(
+            response.rasterize(&grid),
+            [
+                [Some(15.0), Some(30.0), None],
+                [Some(30.0), Some(30.0), None]
+            ]
+        );
+    }
+    #[test]
+    fn removes_noise() {
+        let square = |size: f64| square(0.0, size);
+        // about 111m resp. 11m wide at the equator
+        assert!((ring_area(&square(0.001)) - 12_364.0).abs() < 10.0);
+        let polygon = geo_types::Polygon::new(square(0.01), vec![square(0.001), square(0.0001)]);
+        let mut response = response([feature(Some(15.0), polygon)]);
+        response.fill_holes(1_000.0);
+        let geo_types::Geometry::Polygon(polygon) = &response.features[0].geometry else {
+            unreachable!()
+        };
+        assert_eq!(polygon.interiors(), [square(0.001)]);
+
+        let island = |offset: f64, size: f64| {
+            let ring = square(size)
+                .0
+                .into_iter()
+                .map(|c| (c.x + offset, c.y + offset));
+            geo_types::Polygon::new(ring.collect(), Vec::new())
+        };
+        let islands = geo_types::MultiPolygon::new(vec![island(0.0, 0.01), island(1.0, 0.0001)]);
+        response.features[0].geometry = islands.clone().into();
+        response.drop_islands(1_000.0);
+        assert_eq!(
+            response.features[0].geometry,
+            geo_types::MultiPolygon::new(vec![islands.0[0].clone()]).into()
+        );
+        // contours are not dropped entirely
+        response.drop_islands(f64::MAX);
+        assert_eq!(
+            response.features[0].geometry,
+            geo_types::MultiPolygon::new(vec![islands.0[0].clone()]).into()
+        );
+    }
+}
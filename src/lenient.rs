@@ -0,0 +1,103 @@
+//! Deserializers tolerating minor differences in how valhalla builds encode numbers
+use serde::de::{Error, Unexpected, Visitor};
+use serde::{Deserializer, Serialize, Serializer};
+use serde_with::{DeserializeAs, SerializeAs};
+use std::marker::PhantomData;
+
+/// Deserializes an integer, accepting floating point values as well
+///
+/// Some valhalla builds emit durations or indices as `12.0` instead of `12`.
+/// Floating point values are rounded to the nearest integer.
+pub(crate) struct LenientInteger;
+
+impl<'de, T> DeserializeAs<'de, T> for LenientInteger
+where
+    T: TryFrom<i64> + TryFrom<u64>,
+{
+    fn deserialize_as<D>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(IntegerVisitor(PhantomData))
+    }
+}
+
+/// Integers are serialized as-is
+impl<T: Serialize> SerializeAs<T> for LenientInteger {
+    fn serialize_as<S>(source: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        source.serialize(serializer)
+    }
+}
+
+struct IntegerVisitor<T>(PhantomData<T>);
+
+impl<T> Visitor<'_> for IntegerVisitor<T>
+where
+    T: TryFrom<i64> + TryFrom<u64>,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an integer or a floating point number")
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<T, E> {
+        T::try_from(v).map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<T, E> {
+        T::try_from(v).map_err(|_| E::invalid_value(Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<T, E> {
+        let rounded = v.round();
+        if !rounded.is_finite() || rounded.abs() >= 2_f64.powi(63) {
+            return Err(E::invalid_value(Unexpected::Float(v), &self));
+        }
+        if rounded < 0.0 {
+            self.visit_i64(rounded as i64)
+        } else {
+            self.visit_u64(rounded as u64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[serde_with::serde_as]
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Numbers {
+        #[serde_as(as = "LenientInteger")]
+        unsigned: u32,
+        #[serde_as(as = "LenientInteger")]
+        signed: i32,
+        #[serde_as(as = "Option<LenientInteger>")]
+        optional: Option<i64>,
+        #[serde_as(as = "Vec<Vec<LenientInteger>>")]
+        nested: Vec<Vec<u32>>,
+    }
+
+    #[test]
+    fn deserialisation() {
+        let expected = Numbers {
+            unsigned: 12,
+            signed: -3,
+            optional: None,
+            nested: vec![vec![0, 1], vec![2]],
+        };
+        let integers = r#"{"unsigned":12,"signed":-3,"nested":[[0,1],[2]]}"#;
+        assert_eq!(serde_json::from_str::<Numbers>(integers).unwrap(), expected);
+        let floats =
+            r#"{"unsigned":11.6,"signed":-3.0,"optional":null,"nested":[[0.0,1.2],[2.0]]}"#;
+        assert_eq!(serde_json::from_str::<Numbers>(floats).unwrap(), expected);
+
+        let negative = r#"{"unsigned":-1,"signed":0,"nested":[]}"#;
+        assert!(serde_json::from_str::<Numbers>(negative).is_err());
+    }
+}
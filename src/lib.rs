@@ -1,10 +1,38 @@
 #![forbid(unsafe_code)]
 #![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))]
 
+pub mod banner;
+pub mod canonical;
+pub mod centroid;
+pub mod conformance;
 pub mod costing;
+pub mod elevation;
+pub mod encoding;
+pub mod energy;
+pub mod estimate;
+pub mod expansion;
+pub mod geometry;
+pub mod graph;
+pub mod isochrone;
+mod lenient;
+pub mod locate;
+pub mod logging;
+pub mod matching;
 pub mod matrix;
+pub mod middleware;
+pub mod optimized_route;
+mod parallel;
+pub mod playback;
+pub mod pool;
+mod queue;
+pub mod rate_limit;
 pub mod route;
 pub mod shapes;
+mod single_flight;
+pub mod status;
+mod strict;
+pub mod timing;
+pub mod trace;
 
 use log::debug;
 use serde::{Deserialize, Serialize};
@@ -14,7 +42,29 @@ use serde::{Deserialize, Serialize};
 /// See <https://en.wikipedia.org/wiki/Geographic_coordinate_system> for further context
 pub type Coordinate = (f32, f32);
 
-#[derive(Deserialize, Debug, Clone)]
+/// Extracts the coordinate of a GeoJSON feature with a `Point` geometry
+#[cfg(feature = "geojson")]
+pub(crate) fn coordinate_of(feature: &geojson::Feature) -> Result<Coordinate, geojson::Error> {
+    let Some(geometry) = &feature.geometry else {
+        return Err(geojson::Error::FeatureHasNoGeometry(Box::new(
+            feature.clone(),
+        )));
+    };
+    match &geometry.value {
+        geojson::GeometryValue::Point { coordinates } if coordinates.len() >= 2 => {
+            Ok((coordinates[0] as f32, coordinates[1] as f32))
+        }
+        geojson::GeometryValue::Point { coordinates } => {
+            Err(geojson::Error::PositionTooShort(coordinates.len()))
+        }
+        other => Err(geojson::Error::InvalidGeometryConversion {
+            expected_type: "Point",
+            found_type: other.type_name(),
+        }),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CodedDescription {
     pub code: u64,
     pub description: String,
@@ -23,14 +73,87 @@ pub struct CodedDescription {
 pub struct Valhalla {
     client: reqwest::blocking::Client,
     base_url: url::Url,
+    path_prefix: String,
+    endpoints: std::collections::HashMap<String, String>,
+    logger: Option<logging::Logger>,
+    user_agent: String,
+    rate_limit_observer: Option<rate_limit::Observer>,
+    timing_observer: Option<timing::Observer>,
+    queue: Option<queue::Queue>,
+    single_flight: Option<single_flight::SingleFlight<RawResponse>>,
+    normalize_shapes: bool,
+    defaults: std::collections::HashMap<String, serde_json::Value>,
+    strict_parsing: bool,
+    instruction_hook: Option<InstructionHook>,
+    middlewares: Vec<std::sync::Arc<dyn middleware::Middleware>>,
+    api_key: Option<ApiKey>,
+    encoder: std::sync::Arc<dyn encoding::Encoder>,
+    concurrency: usize,
+    resolved_addrs: Vec<std::net::SocketAddr>,
+    connection_stats: std::sync::Mutex<std::collections::HashMap<std::net::SocketAddr, u64>>,
+}
+
+/// A key authenticating requests to hosted valhalla instances, see [`Valhalla::api_key`]
+///
+/// Never shown in `Debug` output. With the `secrecy` feature, the key is kept in a
+/// [`secrecy::SecretString`], which is zeroized when dropped.
+#[derive(Clone)]
+pub struct ApiKey(
+    #[cfg(feature = "secrecy")] secrecy::SecretString,
+    #[cfg(not(feature = "secrecy"))] String,
+);
+
+impl ApiKey {
+    fn expose(&self) -> &str {
+        #[cfg(feature = "secrecy")]
+        return secrecy::ExposeSecret::expose_secret(&self.0);
+        #[cfg(not(feature = "secrecy"))]
+        return &self.0;
+    }
+}
+
+impl std::fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("ApiKey([REDACTED])")
+    }
 }
 
+impl From<String> for ApiKey {
+    // a conversion into a `SecretString` with the `secrecy` feature
+    #[allow(clippy::useless_conversion)]
+    fn from(key: String) -> Self {
+        Self(key.into())
+    }
+}
+
+impl From<&str> for ApiKey {
+    fn from(key: &str) -> Self {
+        Self(key.into())
+    }
+}
+
+#[cfg(feature = "secrecy")]
+impl From<secrecy::SecretString> for ApiKey {
+    fn from(key: secrecy::SecretString) -> Self {
+        Self(key)
+    }
+}
+
+/// A hook installed via [`Valhalla::instruction_hook`]
+type InstructionHook = std::sync::Arc<dyn Fn(&str) -> String + Send + Sync>;
+
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     Reqwest(reqwest::Error),
     Url(url::ParseError),
     Serde(serde_json::Error),
     RemoteError(RemoteError),
+    /// The instance rejected the request with `429 Too Many Requests`
+    RateLimited(rate_limit::RateLimit),
+    /// The GPX document returned by the instance is invalid
+    #[cfg(feature = "gpx")]
+    Gpx(gpx::errors::GpxError),
 }
 
 /// valhalla needs `date_time` fields to be in the `YYYY-MM-DDTHH:MM` format
@@ -58,7 +181,7 @@ where
     serializer.serialize_str(&value.format("%Y-%m-%dT%H:%M").to_string())
 }
 
-#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Units {
     #[default]
     #[serde(rename = "kilometers")]
@@ -68,6 +191,52 @@ pub enum Units {
     Imperial,
 }
 
+/// The format valhalla encodes a response in
+///
+/// Each method of [`Valhalla`] requests the format its response is parsed from, so a format never
+/// mismatches its parser. Not every action supports every format.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    /// Valhalla's own JSON format, supported by all actions
+    #[default]
+    Json,
+    /// The JSON format of [OSRM](https://project-osrm.org/docs/v5.24.0/api/), supported by `route`, `optimized_route`, `trace_route` and `locate`
+    Osrm,
+    /// A [GPX](https://www.topografix.com/gpx.asp) document, supported by `route`, `optimized_route` and `trace_route`
+    Gpx,
+    /// Protocol buffers, supported by all actions
+    Pbf,
+}
+
+/// Regions which use miles for road distances
+const IMPERIAL_REGIONS: [&str; 4] = ["US", "GB", "LR", "MM"];
+
+impl Units {
+    /// Picks the units customary in the region of a [IETF BCP 47](https://en.wikipedia.org/wiki/IETF_language_tag) locale
+    ///
+    /// Example: `en-US` and `en-GB` use [`Units::Imperial`], while `de-DE` uses [`Units::Metric`].
+    /// Locales without a region (e.g. `en`) default to [`Units::Metric`].
+    pub fn from_locale(locale: &str) -> Self {
+        let is_imperial = locale
+            .split(['-', '_'])
+            .skip(1)
+            .any(|subtag| IMPERIAL_REGIONS.contains(&subtag.to_ascii_uppercase().as_str()));
+        if is_imperial {
+            Self::Imperial
+        } else {
+            Self::Metric
+        }
+    }
+    /// Converts a length given in these units to meters
+    pub fn to_meters(self, length: f64) -> f64 {
+        match self {
+            Self::Metric => length * 1_000.0,
+            Self::Imperial => length * 1_609.344,
+        }
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -75,6 +244,12 @@ impl std::fmt::Display for Error {
             Self::Url(e) => write!(f, "url error: {e}"),
             Self::Serde(e) => write!(f, "serde error: {e}"),
             Self::RemoteError(e) => write!(f, "remote error: {e:?}"),
+            Self::RateLimited(r) => match r.backoff() {
+                Some(backoff) => write!(f, "rate limited, retry in {}s", backoff.as_secs()),
+                None => write!(f, "rate limited"),
+            },
+            #[cfg(feature = "gpx")]
+            Self::Gpx(e) => write!(f, "gpx error: {e}"),
         }
     }
 }
@@ -82,6 +257,8 @@ impl std::fmt::Display for Error {
 impl std::error::Error for Error {}
 
 const VALHALLA_PUBLIC_API_URL: &str = "https://valhalla1.openstreetmap.de/";
+/// The `User-Agent` sent if none is configured via [`Valhalla::user_agent`]
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 impl Default for Valhalla {
     fn default() -> Self {
         Self::new(
@@ -91,10 +268,13 @@ impl Default for Valhalla {
     }
 }
 
+#[serde_with::serde_as]
 #[derive(Debug, Deserialize)]
 pub struct RemoteError {
+    #[serde_as(as = "lenient::LenientInteger")]
     pub error_code: isize,
     pub error: String,
+    #[serde_as(as = "lenient::LenientInteger")]
     pub status_code: isize,
     pub status: String,
 }
@@ -104,35 +284,868 @@ impl Valhalla {
         Self {
             client: reqwest::blocking::Client::new(),
             base_url,
+            path_prefix: String::new(),
+            endpoints: Default::default(),
+            logger: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            rate_limit_observer: None,
+            timing_observer: None,
+            queue: None,
+            single_flight: None,
+            normalize_shapes: false,
+            defaults: Default::default(),
+            strict_parsing: false,
+            instruction_hook: None,
+            middlewares: Vec::new(),
+            api_key: None,
+            encoder: std::sync::Arc::new(encoding::CompactJson),
+            concurrency: 4,
+            resolved_addrs: Vec::new(),
+            connection_stats: Default::default(),
+        }
+    }
+
+    /// Replaces the `User-Agent` header sent with every request
+    ///
+    /// Prefer [`Self::app_identifier`] if you only want to identify your application.
+    ///
+    /// Default: `valhalla-client/{version}`
+    pub fn user_agent(mut self, user_agent: impl ToString) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// Identifies your application to the operator of the valhalla instance
+    ///
+    /// The identifier is appended to the default `User-Agent`, e.g. `valhalla-client/0.3.0 my-app/1.0 (ops@example.com)`.
+    /// Operators of public instances, such as the default one, ask for identifiable clients.
+    pub fn app_identifier(mut self, app: impl std::fmt::Display) -> Self {
+        self.user_agent = format!("{DEFAULT_USER_AGENT} {app}");
+        self
+    }
+
+    /// Authenticates every request with an API key, as required by hosted valhalla instances
+    ///
+    /// The key is sent as the `api_key` query parameter. It is not part of the [`middleware::Request`]
+    /// seen by middlewares, nor of the logged events, and errors are reported without their URL.
+    ///
+    /// Default: no API key
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let key = std::env::var("VALHALLA_API_KEY").unwrap_or_default();
+    /// let valhalla = Valhalla::default().api_key(key);
+    /// ```
+    pub fn api_key(mut self, api_key: impl Into<ApiKey>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Mounts all actions under a path prefix
+    ///
+    /// Useful for deployments which expose valhalla under a sub-path.
+    /// Example: with the prefix `valhalla`, routing requests are sent to `{base_url}/valhalla/route`
+    ///
+    /// Default: no prefix
+    pub fn path_prefix(mut self, path_prefix: impl ToString) -> Self {
+        self.path_prefix = path_prefix.to_string();
+        self
+    }
+
+    /// Overrides the path at which an action is exposed
+    ///
+    /// The `action` is the name valhalla uses for the endpoint, e.g. `route` or `sources_to_targets`.
+    /// The `path` is relative to the [`Self::path_prefix`] and may consist of multiple segments.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let valhalla = Valhalla::default()
+    ///     .path_prefix("valhalla")
+    ///     .endpoint("route", "route/v1");
+    /// ```
+    pub fn endpoint(mut self, action: impl ToString, path: impl ToString) -> Self {
+        self.endpoints.insert(action.to_string(), path.to_string());
+        self
+    }
+
+    /// Installs a hook which is called with a summary of every request and response
+    ///
+    /// Before the hook is called, coordinates are treated according to the [`logging::Redaction`].
+    /// This allows logging of production traffic without storing the precise locations of users.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::logging::{Event, Redaction};
+    ///
+    /// let valhalla = Valhalla::default().logger(Redaction::Round(2), |event| match event {
+    ///     Event::Request { action, body } => log::info!("-> {action}: {body}"),
+    ///     Event::Response { action, status, .. } => log::info!("<- {action}: {status}"),
+    /// });
+    /// ```
+    pub fn logger(
+        mut self,
+        redaction: logging::Redaction,
+        hook: impl Fn(&logging::Event) + Send + Sync + 'static,
+    ) -> Self {
+        self.logger = Some(logging::Logger {
+            redaction,
+            hook: std::sync::Arc::new(hook),
+        });
+        self
+    }
+
+    /// Installs a hook which is called with the rate limit state of every response carrying rate limit headers
+    ///
+    /// Allows batch jobs to throttle themselves before the instance starts rejecting requests.
+    /// Rejected requests are reported as [`Error::RateLimited`].
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let valhalla = Valhalla::default().rate_limit_observer(|rate_limit| {
+    ///     if let Some(backoff) = rate_limit.backoff() {
+    ///         std::thread::sleep(backoff);
+    ///     }
+    /// });
+    /// ```
+    pub fn rate_limit_observer(
+        mut self,
+        observer: impl Fn(&rate_limit::RateLimit) + Send + Sync + 'static,
+    ) -> Self {
+        self.rate_limit_observer = Some(std::sync::Arc::new(observer));
+        self
+    }
+
+    /// Installs a hook which is called with the [`timing::Timing`] of every request which received a response
+    ///
+    /// Allows SLO dashboards to distinguish the time spent on the network from the time spent on the server.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let valhalla = Valhalla::default().timing_observer(|timing| {
+    ///     log::info!("{} took {:?} ({:?} on the server)", timing.action, timing.latency, timing.server_time);
+    /// });
+    /// ```
+    pub fn timing_observer(
+        mut self,
+        observer: impl Fn(&timing::Timing) + Send + Sync + 'static,
+    ) -> Self {
+        self.timing_observer = Some(std::sync::Arc::new(observer));
+        self
+    }
+
+    /// Replaces how request bodies are encoded, see the [`encoding`] module
+    ///
+    /// Middlewares still see the JSON body, which is encoded after the last layer.
+    ///
+    /// Default: [`encoding::CompactJson`]
+    pub fn request_encoder(mut self, encoder: impl encoding::Encoder + 'static) -> Self {
+        self.encoder = std::sync::Arc::new(encoder);
+        self
+    }
+
+    /// Limits how many requests helpers issuing several requests send at the same time
+    ///
+    /// Applies to [`Self::compare_costings`], [`Self::height_profile`] and the per-leg fallback of
+    /// [`Self::route_partial`], so batch helpers do not overwhelm small instances.
+    /// The limit applies per call; see [`Self::request_queue`] for a limit across calls.
+    ///
+    /// Default: `4`
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Resolves the host of the instance to fixed addresses instead of via DNS
+    ///
+    /// Allows deterministic endpoint selection, e.g. to bypass a service mesh or to pin a replica.
+    /// The host is still used for the `Host` header and TLS.
+    /// Has no effect if the host of the base url is an IP address, as it is never resolved.
+    /// See [`Self::connection_stats`] for which addresses served requests.
+    ///
+    /// Default: resolved via DNS
+    ///
+    /// # Panics
+    ///
+    /// If the HTTP client cannot be initialized, like [`reqwest::blocking::Client::new`].
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let url = url::Url::parse("http://valhalla.internal:8002").unwrap();
+    /// let valhalla = Valhalla::new(url).resolve(["10.0.0.7:8002".parse().unwrap()]);
+    /// ```
+    pub fn resolve(mut self, addrs: impl IntoIterator<Item = std::net::SocketAddr>) -> Self {
+        if !matches!(self.base_url.host(), Some(url::Host::Domain(_))) {
+            return self;
+        }
+        self.resolved_addrs = addrs.into_iter().collect();
+        self.client = self.build_client();
+        self
+    }
+
+    /// How many requests each server address answered since the client was created
+    ///
+    /// Allows checking whether requests are spread across the addresses of [`Self::resolve`] or the
+    /// replicas behind a hostname. Requests answered by a middleware without being sent are not counted.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let valhalla = Valhalla::default();
+    /// valhalla.status().unwrap();
+    /// for (addr, requests) in valhalla.connection_stats() {
+    ///     println!("{addr} answered {requests} requests");
+    /// }
+    /// ```
+    pub fn connection_stats(&self) -> std::collections::HashMap<std::net::SocketAddr, u64> {
+        self.connection_stats.lock().unwrap().clone()
+    }
+
+    /// Builds the HTTP client from all settings of the client, so setters do not discard each other
+    fn build_client(&self) -> reqwest::blocking::Client {
+        let mut builder = reqwest::blocking::Client::builder();
+        if let (Some(host), false) = (self.base_url.host_str(), self.resolved_addrs.is_empty()) {
+            builder = builder.resolve_to_addrs(host, &self.resolved_addrs);
         }
+        builder
+            .build()
+            .expect("the HTTP client cannot be initialized")
+    }
+
+    /// Queues requests, so interactive requests are not stuck behind large background requests
+    ///
+    /// At most `interactive` routing (and other interactive) requests and at most `background`
+    /// matrix requests are in flight at the same time.
+    /// Queued interactive requests are sent before queued background requests,
+    /// but background requests which are already in flight are not interrupted.
+    ///
+    /// Default: no queue, i.e. requests are sent immediately
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    ///
+    /// // answer users immediately, while running one matrix at a time
+    /// let valhalla = Valhalla::default().request_queue(8, 1);
+    /// ```
+    pub fn request_queue(mut self, interactive: usize, background: usize) -> Self {
+        self.queue = Some(queue::Queue::new(interactive, background));
+        self
+    }
+
+    /// Sends only one request if identical requests are in flight at the same time
+    ///
+    /// The response is shared by all callers, e.g. for map UIs issuing the same request
+    /// for every hover event.
+    /// Requests are identical if their [canonical serialization](canonical::to_string) is.
+    /// If the request fails due to a transport or server error, each waiting caller sends its own request.
+    ///
+    /// Default: `false`
+    pub fn coalesce_requests(mut self, coalesce_requests: bool) -> Self {
+        self.single_flight = coalesce_requests.then(Default::default);
+        self
+    }
+
+    /// Normalizes all decoded shapes, see [`shapes::normalize`]
+    ///
+    /// Removes invalid and consecutive duplicate points, which break downstream simplification or
+    /// length computations. Shape indices of maneuvers are updated accordingly.
+    /// Shapes kept as returned by valhalla ([`Self::route_encoded`]) are not affected.
+    ///
+    /// Default: `false`
+    pub fn normalize_shapes(mut self, normalize_shapes: bool) -> Self {
+        self.normalize_shapes = normalize_shapes;
+        self
+    }
+
+    /// Sets defaults which every request to an `action` inherits, unless they are set on the request
+    ///
+    /// The `action` is the name valhalla uses for the endpoint, e.g. `route` or `sources_to_targets`.
+    /// Nested options are merged, e.g. costing options set on the request are combined with
+    /// the default costing options of the same costing model.
+    ///
+    /// **Note:** options which are always part of a request, such as the costing of a
+    /// [`matrix::Manifest`], cannot be defaulted.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::{Units, Valhalla};
+    /// use valhalla_client::route::Manifest;
+    /// use valhalla_client::shapes::ShapeFormat;
+    ///
+    /// let valhalla = Valhalla::default().defaults(
+    ///     "route",
+    ///     &Manifest::builder()
+    ///         .units(Units::Imperial)
+    ///         .language("de-DE")
+    ///         .shape_format(ShapeFormat::Polyline6),
+    /// );
+    /// ```
+    pub fn defaults(mut self, action: impl ToString, defaults: &impl Serialize) -> Self {
+        let defaults = serde_json::to_value(defaults).expect("the defaults are not valid JSON");
+        self.defaults.insert(action.to_string(), defaults);
+        self
+    }
+
+    /// The costing model and costing options a request to `action` is sent with
+    ///
+    /// Includes the [`Self::defaults`] merged with the options set on the request, e.g. to
+    /// check what is actually sent while tuning costing options.
+    /// Options which are neither set nor defaulted are chosen by valhalla and thus not included.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::costing::{Costing, auto::AutoCostingOptions};
+    /// use valhalla_client::route::Manifest;
+    ///
+    /// let valhalla = Valhalla::default().defaults(
+    ///     "route",
+    ///     &Manifest::builder().costing(Costing::Auto(AutoCostingOptions::builder().use_tolls(0.0))),
+    /// );
+    /// let manifest = Manifest::builder()
+    ///     .costing(Costing::Auto(AutoCostingOptions::builder().use_highways(0.5)));
+    /// let costing = valhalla.effective_costing_options("route", &manifest).unwrap();
+    /// println!("{costing:#}");
+    /// # assert_eq!(costing["costing_options"]["auto"]["use_tolls"], 0.0);
+    /// # assert_eq!(costing["costing_options"]["auto"]["use_highways"], 0.5);
+    /// ```
+    pub fn effective_costing_options(
+        &self,
+        action: &str,
+        manifest: &impl Serialize,
+    ) -> Result<serde_json::Value, Error> {
+        let request = self.with_defaults(manifest, action)?;
+        let costing = ["costing", "costing_options"]
+            .into_iter()
+            .filter_map(|key| Some((key.to_string(), request.get(key)?.clone())))
+            .collect();
+        Ok(serde_json::Value::Object(costing))
+    }
+
+    /// Fails on responses containing fields unknown to this crate or `null` values
+    ///
+    /// Intended for testing against a pinned valhalla version, so that schema drift is caught
+    /// early. In production, the default lenient parsing ignores unknown fields and tolerates
+    /// minor differences, such as floating point numbers where integers are expected.
+    /// Violations are reported as [`Error::Serde`].
+    ///
+    /// Default: `false`
+    pub fn strict_parsing(mut self, strict_parsing: bool) -> Self {
+        self.strict_parsing = strict_parsing;
+        self
+    }
+
+    /// Installs a hook which transforms every instruction of a route before it is returned
+    ///
+    /// Applied to the written and verbal instructions of all maneuvers, see [`route::Maneuver::map_instructions`],
+    /// e.g. to expand abbreviations or to filter offensive names consistently.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let valhalla = Valhalla::default().instruction_hook(|instruction| instruction.replace(" St.", " Street"));
+    /// ```
+    pub fn instruction_hook(
+        mut self,
+        hook: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.instruction_hook = Some(std::sync::Arc::new(hook));
+        self
+    }
+
+    /// Adds a layer around every request, see the [`middleware`] module
+    ///
+    /// Allows inserting concerns such as authentication, caching or chaos testing without this crate
+    /// hardcoding each of them. Layers run in the order they are added, i.e. the first one is the outermost.
+    /// Requests are logged before the layers run, responses are logged and observed after them.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::middleware::{Next, Request};
+    ///
+    /// let valhalla = Valhalla::default().middleware(|request: Request, next: Next| {
+    ///     let started = std::time::Instant::now();
+    ///     let response = next.run(request);
+    ///     log::info!("took {:?}", started.elapsed());
+    ///     response
+    /// });
+    /// ```
+    pub fn middleware(mut self, middleware: impl middleware::Middleware + 'static) -> Self {
+        self.middlewares.push(std::sync::Arc::new(middleware));
+        self
+    }
+
+    /// Opens a connection to the instance, so the first request does not pay for DNS resolution and TLS handshakes
+    ///
+    /// Sends a lightweight request to the `status` action and keeps the connection for later requests.
+    /// The request passes through the [middlewares](Self::middleware), the logger and the observers like any other.
+    /// Any HTTP response counts as success, as only establishing the connection matters;
+    /// transport errors, e.g. an unreachable instance, are returned.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let valhalla = Valhalla::default();
+    /// valhalla.warm_up().expect("the instance is unreachable");
+    /// ```
+    pub fn warm_up(&self) -> Result<(), Error> {
+        let manifest =
+            serde_json::to_value(status::Manifest { verbose: None }).map_err(Error::Serde)?;
+        match self.send(&manifest, "status") {
+            Ok(_) => Ok(()),
+            Err(Error::Reqwest(e)) if e.is_status() => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Requests the version of the instance, e.g. to check that it is up
+    ///
+    /// See [`Self::status_verbose`] for the loaded data.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let status = Valhalla::default().status().expect("the instance is down");
+    /// println!("valhalla {}", status.version);
+    /// ```
+    pub fn status(&self) -> Result<status::Status, Error> {
+        let manifest = status::Manifest { verbose: None };
+        self.do_request(&manifest, "status", ResponseFormat::Json)
+    }
+
+    /// Requests the version and loaded data of the instance, e.g. the age of the tiles or whether traffic is loaded
+    ///
+    /// See <https://valhalla.github.io/valhalla/api/status/api-reference/> and the [`status`] module for details
+    pub fn status_verbose(&self) -> Result<status::Status, Error> {
+        let manifest = status::Manifest {
+            verbose: Some(true),
+        };
+        self.do_request(&manifest, "status", ResponseFormat::Json)
     }
 
     /// Make a turn-by-turn routing request
     ///
     /// See <https://valhalla.github.io/valhalla/api/turn-by-turn/api-reference> for details
     pub fn route(&self, manifest: route::Manifest) -> Result<route::Trip, Error> {
-        debug!(
-            "Sending routing request: {}",
-            serde_json::to_string(&manifest).unwrap()
-        );
-        let mut url = self.base_url.clone();
-        url.path_segments_mut()
-            .expect("base_url is not a valid base url")
-            .push("route");
-        let response = self
-            .client
-            .post(url)
-            .json(&manifest)
-            .send()
-            .map_err(Error::Reqwest)?;
-        if response.status().is_client_error() {
-            return Err(Error::RemoteError(response.json().map_err(Error::Reqwest)?));
-        }
-        response.error_for_status_ref().map_err(Error::Reqwest)?;
-        let text = response.text().map_err(Error::Reqwest)?;
-        let response: route::Response = serde_json::from_str(&text).map_err(Error::Serde)?;
-        Ok(response.trip)
+        let format = self.shape_format(&manifest, "route")?;
+        let response: route::Response<shapes::Shape> =
+            self.do_request(&manifest, "route", ResponseFormat::Json)?;
+        let mut trip = response.into_trip().decode(format);
+        if self.normalize_shapes {
+            trip.legs.iter_mut().for_each(route::Leg::normalize_shape);
+        }
+        self.apply_instruction_hook(&mut trip);
+        Ok(trip)
+    }
+
+    /// Make an optimized routing request, visiting the locations in between the first and last one in the fastest order
+    ///
+    /// See <https://valhalla.github.io/valhalla/api/optimized/api-reference/> and the [`optimized_route`]
+    /// module for details
+    pub fn optimized_route(
+        &self,
+        manifest: optimized_route::Manifest,
+    ) -> Result<optimized_route::OptimizedRoute, Error> {
+        let format = self.shape_format(&manifest, "optimized_route")?;
+        let response: route::Response<shapes::Shape> =
+            self.do_request(&manifest, "optimized_route", ResponseFormat::Json)?;
+        let mut trip = response.into_trip().decode(format);
+        if self.normalize_shapes {
+            trip.legs.iter_mut().for_each(route::Leg::normalize_shape);
+        }
+        self.apply_instruction_hook(&mut trip);
+        Ok(trip.into())
+    }
+
+    /// Matches a GPS trace to the road network, returning the matched path as trip with turn-by-turn directions
+    ///
+    /// See <https://valhalla.github.io/valhalla/api/map-matching/api-reference/> for details
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::trace::{Location, Manifest, ShapeMatch};
+    ///
+    /// let manifest = Manifest::builder()
+    ///   .shape([Location::new(4.9041, 52.3676), Location::new(4.9052, 52.3681), Location::new(4.9068, 52.3685)])
+    ///   .shape_match(ShapeMatch::MapSnap);
+    /// let trip = Valhalla::default().trace_route(manifest).unwrap();
+    /// println!("{:?}", trip.total_distance());
+    /// ```
+    pub fn trace_route(&self, manifest: trace::Manifest) -> Result<route::Trip, Error> {
+        let format = self.shape_format(&manifest, "trace_route")?;
+        let response: route::Response<shapes::Shape> =
+            self.do_request(&manifest, "trace_route", ResponseFormat::Json)?;
+        let mut trip = response.into_trip().decode(format);
+        if self.normalize_shapes {
+            trip.legs.iter_mut().for_each(route::Leg::normalize_shape);
+        }
+        self.apply_instruction_hook(&mut trip);
+        Ok(trip)
+    }
+
+    /// Like [`Self::trace_route`], but on [`Error::RemoteError`]s listed in the [`matching::Fallback`], tries its strategies in order
+    ///
+    /// Returns the trip along with the strategy which produced it, or [`None`] if the `manifest` matched as is.
+    /// Strategies matching the same way as the `manifest` would only repeat the failed request, so they are skipped.
+    /// If every strategy fails, the error of the last attempt is returned.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::matching::Fallback;
+    /// use valhalla_client::trace::{Location, Manifest};
+    ///
+    /// let manifest = Manifest::builder()
+    ///   .shape([Location::new(4.9041, 52.3676), Location::new(4.9052, 52.3681), Location::new(4.9068, 52.3685)]);
+    /// let (trip, strategy) = Valhalla::default()
+    ///   .trace_route_with_fallback(manifest, &Fallback::default())
+    ///   .unwrap();
+    /// println!("{:?} via {strategy:?}", trip.total_distance());
+    /// ```
+    pub fn trace_route_with_fallback(
+        &self,
+        manifest: trace::Manifest,
+        fallback: &matching::Fallback,
+    ) -> Result<(route::Trip, Option<matching::Strategy>), Error> {
+        let requested: trace::ShapeMatch = self
+            .with_defaults(&manifest, "trace_route")?
+            .get("shape_match")
+            .and_then(|shape_match| serde_json::from_value(shape_match.clone()).ok())
+            .unwrap_or_default();
+        let mut error = match self.trace_route(manifest.clone()) {
+            Ok(trip) => return Ok((trip, None)),
+            Err(error) => error,
+        };
+        for strategy in fallback.chain() {
+            if !fallback.applies_to(&error) {
+                break;
+            }
+            let attempt = match strategy.shape_match() {
+                Some(shape_match) if shape_match == requested => continue,
+                Some(shape_match) => self.trace_route(manifest.clone().shape_match(shape_match)),
+                None => match manifest.route_between_endpoints() {
+                    Some(route) => self.route(route),
+                    None => continue,
+                },
+            };
+            match attempt {
+                Ok(trip) => return Ok((trip, Some(strategy))),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    /// Matches a GPS trace to the road network, returning the attributes of the matched roads
+    ///
+    /// Returns the attributes of each road segment (e.g. the OpenStreetMap way, speed or surface) and
+    /// where each trace point was matched to. See [`trace::Manifest::include_attributes`] to limit
+    /// the attributes and <https://valhalla.github.io/valhalla/api/map-matching/api-reference/> for details.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::trace::{Location, Manifest, ShapeMatch};
+    ///
+    /// let manifest = Manifest::builder()
+    ///   .shape([Location::new(4.9041, 52.3676), Location::new(4.9052, 52.3681), Location::new(4.9068, 52.3685)])
+    ///   .shape_match(ShapeMatch::MapSnap)
+    ///   .include_attributes(["edge.way_id", "edge.names", "edge.speed"]);
+    /// let response = Valhalla::default().trace_attributes(manifest).unwrap();
+    /// for edge in response.edges {
+    ///   println!("{:?} {:?} {:?}", edge.way_id, edge.names, edge.speed);
+    /// }
+    /// ```
+    pub fn trace_attributes(&self, manifest: trace::Manifest) -> Result<trace::Response, Error> {
+        let format = self.shape_format(&manifest, "trace_attributes")?;
+        let response: trace::Response<shapes::Shape> =
+            self.do_request(&manifest, "trace_attributes", ResponseFormat::Json)?;
+        let mut response = response.decode(format);
+        if let (true, Some(shape)) = (self.normalize_shapes, &mut response.shape) {
+            let new_indices = shapes::normalize(shape);
+            for edge in &mut response.edges {
+                let remap = |index: &mut Option<usize>| {
+                    if let Some(i) = index {
+                        *i = new_indices.get(*i).copied().unwrap_or(*i);
+                    }
+                };
+                remap(&mut edge.begin_shape_index);
+                remap(&mut edge.end_shape_index);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Routes along a sketched line, e.g. drawn freehand on a map, returning a trip with turn-by-turn directions
+    ///
+    /// The sketch is snapped to the roads close to it, see [`trace::Manifest::sketch`].
+    /// Points are placed every 50 meters, so roads up to about 100 meters from the sketch are considered.
+    /// Use [`Self::trace_route`] with [`trace::Manifest::sketch`] to choose another spacing.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::costing::Costing;
+    /// use valhalla_client::shapes::ShapePoint;
+    ///
+    /// let sketch = [
+    ///   ShapePoint { lon: 4.8897, lat: 52.3731 },
+    ///   ShapePoint { lon: 4.8950, lat: 52.3702 },
+    ///   ShapePoint { lon: 4.9041, lat: 52.3676 },
+    /// ];
+    /// let trip = Valhalla::default()
+    ///   .route_along(&sketch, Costing::Bicycle(Default::default()))
+    ///   .unwrap();
+    /// for maneuver in &trip.legs[0].maneuvers {
+    ///   println!("{}", maneuver.instruction);
+    /// }
+    /// ```
+    pub fn route_along(
+        &self,
+        sketch: &[shapes::ShapePoint],
+        costing: costing::Costing,
+    ) -> Result<route::Trip, Error> {
+        let manifest = trace::Manifest::builder()
+            .sketch(sketch, 50.0)
+            .costing(costing);
+        self.trace_route(manifest)
+    }
+
+    /// Make a turn-by-turn routing request, routing each leg on its own if no route is found between all locations
+    ///
+    /// Valhalla fails the whole request if a single leg of a multi-waypoint route cannot be found.
+    /// If the instance rejects the request, the legs between consecutive break locations are requested
+    /// concurrently (see [`Self::concurrency`]), so the [`route::PartialRoute::Partial`] result tells which legs failed and why.
+    /// Fails if the instance is unreachable or rejects a route with a single leg.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::route::{Location, Manifest};
+    ///
+    /// let manifest = Manifest::builder().locations([
+    ///   Location::new(4.9041, 52.3676),
+    ///   Location::new(-0.1276, 51.5072),
+    ///   Location::new(5.1214, 52.0907),
+    /// ]);
+    /// let route = Valhalla::default().route_partial(manifest).unwrap();
+    /// for leg in route.failed_legs() {
+    ///   println!("no route from {} to {}: {}", leg.from, leg.to, leg.error);
+    /// }
+    /// ```
+    pub fn route_partial(&self, manifest: route::Manifest) -> Result<route::PartialRoute, Error> {
+        let error = match self.route(manifest.clone()) {
+            Ok(trip) => return Ok(route::PartialRoute::Complete(trip)),
+            Err(error @ Error::RemoteError(_)) => error,
+            Err(error) => return Err(error),
+        };
+        let legs = manifest.legs();
+        if legs.len() < 2 {
+            return Err(error);
+        }
+        let trips = parallel::map(&legs, self.concurrency, |(_, _, leg)| {
+            self.route(leg.clone())
+        });
+        let legs = legs
+            .into_iter()
+            .zip(trips)
+            .map(|((from, to, _), trip)| trip.map_err(|error| route::FailedLeg { from, to, error }))
+            .collect();
+        Ok(route::PartialRoute::Partial(legs))
+    }
+
+    /// Make a turn-by-turn routing request, keeping the leg shapes as returned by valhalla
+    ///
+    /// Contrary to [`Self::route`], the shapes are not decoded.
+    /// This avoids the decoding overhead if the geometry is only forwarded, for example to a web client.
+    pub fn route_encoded(
+        &self,
+        manifest: route::Manifest,
+    ) -> Result<route::Trip<shapes::Shape>, Error> {
+        let response: route::Response<shapes::Shape> =
+            self.do_request(&manifest, "route", ResponseFormat::Json)?;
+        let mut trip = response.into_trip();
+        self.apply_instruction_hook(&mut trip);
+        Ok(trip)
+    }
+
+    fn apply_instruction_hook<S>(&self, trip: &mut route::Trip<S>) {
+        let Some(hook) = &self.instruction_hook else {
+            return;
+        };
+        trip.legs
+            .iter_mut()
+            .flat_map(|leg| &mut leg.maneuvers)
+            .for_each(|maneuver| maneuver.map_instructions(|i| hook(i)));
+    }
+    /// Make a turn-by-turn routing request, returning the GPX document generated by valhalla
+    ///
+    /// Useful for exporting routes to devices, as the document is passed through without re-encoding.
+    /// See [`Self::route_gpx`] for a parsed document.
+    pub fn route_gpx_document(&self, manifest: route::Manifest) -> Result<String, Error> {
+        self.fetch(&manifest, "route", ResponseFormat::Gpx)
+    }
+
+    /// Make a turn-by-turn routing request, returning the GPX document generated by valhalla
+    ///
+    /// Contrary to converting a [`route::Trip`] into [`gpx::Gpx`], the document contains
+    /// exactly what valhalla generates.
+    #[cfg(feature = "gpx")]
+    pub fn route_gpx(&self, manifest: route::Manifest) -> Result<gpx::Gpx, Error> {
+        let document = self.route_gpx_document(manifest)?;
+        gpx::read(document.as_bytes()).map_err(Error::Gpx)
+    }
+
+    /// Make a turn-by-turn routing request arriving at the last location by the given time
+    ///
+    /// Returns the trip together with the required departure time.
+    /// Arrival times are not supported for [`costing::Costing::Multimodal`], see
+    /// [`Self::route_latest_departure`] for an alternative.
+    pub fn route_arrive_by(
+        &self,
+        manifest: route::Manifest,
+        arrive_by: chrono::NaiveDateTime,
+    ) -> Result<route::ScheduledTrip, Error> {
+        let manifest = manifest.date_time(route::DateTime::from_arrival_time(arrive_by));
+        let trip = self.route(manifest)?;
+        Ok(route::ScheduledTrip::arriving_at(trip, arrive_by))
     }
+
+    /// Searches for the latest departure time arriving at the last location by the given time
+    ///
+    /// Contrary to [`Self::route_arrive_by`], this only issues routing requests with departure times
+    /// and thus also works for costing models not supporting arrival times, such as
+    /// [`costing::Costing::Multimodal`].
+    /// The departure time is determined via a binary search over the `search_window` before `arrive_by`,
+    /// at the minute resolution valhalla supports.
+    /// Each step of the search is a separate routing request.
+    ///
+    /// Returns `None` if departing at the start of the `search_window` does not arrive in time.
+    pub fn route_latest_departure(
+        &self,
+        manifest: route::Manifest,
+        arrive_by: chrono::NaiveDateTime,
+        search_window: chrono::TimeDelta,
+    ) -> Result<Option<route::ScheduledTrip>, Error> {
+        let depart_at = |departure| {
+            let manifest = manifest
+                .clone()
+                .date_time(route::DateTime::from_departure_time(departure));
+            self.route(manifest)
+                .map(|trip| route::ScheduledTrip::departing_at(trip, departure))
+        };
+        let mut earliest = arrive_by - search_window;
+        let mut latest = arrive_by;
+        let mut best = depart_at(earliest)?;
+        if best.arrival > arrive_by {
+            return Ok(None);
+        }
+        while latest - earliest > chrono::TimeDelta::minutes(1) {
+            let middle = earliest + (latest - earliest) / 2;
+            let candidate = depart_at(middle)?;
+            if candidate.arrival <= arrive_by {
+                earliest = middle;
+                best = candidate;
+            } else {
+                latest = middle;
+            }
+        }
+        Ok(Some(best))
+    }
+
+    /// Issues the same routing request for each of the `departures`, e.g. to build an ETA profile over a morning
+    ///
+    /// At most [`Self::concurrency`] requests are in flight at the same time.
+    /// The results are in the same order as the `departures`.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use chrono::{NaiveDate, TimeDelta};
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::route::{Location, Manifest};
+    ///
+    /// let manifest = Manifest::builder()
+    ///   .locations([Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)]);
+    /// let six_am = NaiveDate::from_ymd_opt(2024, 11, 7).unwrap().and_hms_opt(6, 0, 0).unwrap();
+    /// // every 15 minutes from 06:00 to 10:00
+    /// let departures = (0..=16).map(|i| six_am + TimeDelta::minutes(15 * i));
+    ///
+    /// for trip in Valhalla::default().route_departure_sweep(manifest, departures) {
+    ///   let trip = trip.unwrap();
+    ///   println!("{} -> {}", trip.departure, trip.arrival);
+    /// }
+    /// ```
+    pub fn route_departure_sweep(
+        &self,
+        manifest: route::Manifest,
+        departures: impl IntoIterator<Item = chrono::NaiveDateTime>,
+    ) -> Vec<Result<route::ScheduledTrip, Error>> {
+        let departures: Vec<_> = departures.into_iter().collect();
+        parallel::map(&departures, self.concurrency, |&departure| {
+            let manifest = manifest
+                .clone()
+                .date_time(route::DateTime::from_departure_time(departure));
+            self.route(manifest)
+                .map(|trip| route::ScheduledTrip::departing_at(trip, departure))
+        })
+    }
+
+    /// Routes the same locations with each of the `costings` concurrently, e.g. to compare travel modes
+    ///
+    /// At most [`Self::concurrency`] requests are in flight at the same time.
+    /// The costing of the `manifest` is replaced by each of the `costings`.
+    /// The comparisons are in the same order as the `costings`.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::costing::Costing;
+    /// use valhalla_client::route::{Location, Manifest};
+    ///
+    /// let manifest = Manifest::builder()
+    ///   .locations([Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)]);
+    /// let costings = [
+    ///   Costing::Auto(Default::default()),
+    ///   Costing::Bicycle(Default::default()),
+    ///   Costing::Pedestrian(Default::default()),
+    /// ];
+    /// for comparison in Valhalla::default().compare_costings(manifest, costings) {
+    ///   println!("{:?}: {:?}", comparison.total_time(), comparison.total_distance());
+    /// }
+    /// ```
+    pub fn compare_costings(
+        &self,
+        manifest: route::Manifest,
+        costings: impl IntoIterator<Item = costing::Costing>,
+    ) -> Vec<route::CostingComparison> {
+        let costings: Vec<_> = costings.into_iter().collect();
+        let trips = parallel::map(&costings, self.concurrency, |costing| {
+            self.route(manifest.clone().costing(costing.clone()))
+        });
+        costings
+            .into_iter()
+            .zip(trips)
+            .map(|(costing, trip)| route::CostingComparison { costing, trip })
+            .collect()
+    }
+
     /// Make a time-distance matrix routing request
     ///
     /// See <https://valhalla.github.io/valhalla/api/matrix/api-reference> for details
@@ -177,26 +1190,657 @@ impl Valhalla {
             "a matrix route needs at least one source specified"
         );
 
-        debug!(
-            "Sending routing request: {}",
-            serde_json::to_string(&manifest).unwrap()
-        );
+        let format = self.shape_format(&manifest, "sources_to_targets")?;
+        let response: matrix::Response<shapes::Shape> =
+            self.do_request(&manifest, "sources_to_targets", ResponseFormat::Json)?;
+        let mut response = response.decode(format);
+        if let (true, matrix::Response::Verbose(verbose)) = (self.normalize_shapes, &mut response) {
+            verbose
+                .sources_to_targets
+                .iter_mut()
+                .flatten()
+                .filter_map(|connection| connection.shape.as_mut())
+                .for_each(|shape| {
+                    shapes::normalize(shape);
+                });
+        }
+        Ok(response)
+    }
+
+    /// Make a centroid request, computing the least-cost meeting point of the locations and the paths to it
+    ///
+    /// See <https://valhalla.github.io/valhalla/api/centroid/api-reference/> and the [`centroid`] module for details
+    pub fn centroid(&self, manifest: centroid::Manifest) -> Result<centroid::Centroid, Error> {
+        let format = self.shape_format(&manifest, "centroid")?;
+        let response: centroid::Response<shapes::Shape> =
+            self.do_request(&manifest, "centroid", ResponseFormat::Json)?;
+        let mut centroid = response.into_centroid(format);
+        for trip in &mut centroid.trips {
+            if self.normalize_shapes {
+                trip.legs.iter_mut().for_each(route::Leg::normalize_shape);
+            }
+            self.apply_instruction_hook(trip);
+        }
+        Ok(centroid)
+    }
+
+    /// Make an elevation request, returning the elevations along a shape
+    ///
+    /// See <https://valhalla.github.io/valhalla/api/elevation/api-reference/> and the [`elevation`] module for details
+    pub fn height(&self, manifest: elevation::Manifest) -> Result<elevation::Response, Error> {
+        self.do_request(&manifest, "height", ResponseFormat::Json)
+    }
+
+    /// Requests the elevations along a shape of any length, splitting it into chunks of at most `max_points` points
+    ///
+    /// Instances limit the number of points per request, e.g. to get the elevation profile of a long route.
+    /// The chunks are requested concurrently (see [`Self::concurrency`]) and their samples are joined, continuing the distances from
+    /// one chunk to the next (see [`elevation::Manifest::range`]).
+    ///
+    /// **Note:** with [`elevation::Manifest::resample_distance`], each chunk is resampled on its own.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::elevation::Manifest;
+    /// use valhalla_client::route::{Location, Manifest as RouteManifest};
+    ///
+    /// let valhalla = Valhalla::default();
+    /// let trip = valhalla
+    ///   .route(RouteManifest::builder().locations([Location::new(4.9041, 52.3676), Location::new(7.6586, 45.9763)]))
+    ///   .unwrap();
+    /// let shape = trip.legs.iter().flat_map(|leg| leg.shape.iter().cloned());
+    /// let profile = valhalla
+    ///   .height_profile(Manifest::builder().shape(shape).range(true), 2_000)
+    ///   .unwrap();
+    /// println!("{} samples", profile.len());
+    /// ```
+    pub fn height_profile(
+        &self,
+        manifest: elevation::Manifest,
+        max_points: usize,
+    ) -> Result<Vec<elevation::Sample>, Error> {
+        let chunks = manifest.chunks(max_points);
+        let responses = parallel::map(&chunks, self.concurrency, |chunk| {
+            self.height(chunk.clone())
+        });
+        let responses = responses.into_iter().collect::<Result<Vec<_>, _>>()?;
+        Ok(elevation::stitch(&responses))
+    }
+
+    /// Requests the elevation along a trip, attached to its legs and maneuvers, e.g. the climb per leg
+    ///
+    /// The shape of the trip is requested in chunks of at most `max_points` points, see
+    /// [`Self::height_profile`], and the heights are assigned via [`elevation::annotate`].
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::route::{Location, Manifest};
+    ///
+    /// let valhalla = Valhalla::default();
+    /// let trip = valhalla
+    ///   .route(Manifest::builder().locations([Location::new(7.6586, 45.9763), Location::new(7.7491, 46.0207)]))
+    ///   .unwrap();
+    /// for leg in valhalla.trip_elevation(&trip, 2_000).unwrap() {
+    ///   println!("climb {}m, descent {}m", leg.climb, leg.descent);
+    /// }
+    /// ```
+    pub fn trip_elevation(
+        &self,
+        trip: &route::Trip,
+        max_points: usize,
+    ) -> Result<Vec<elevation::LegElevation>, Error> {
+        let shape = trip.legs.iter().flat_map(|leg| leg.shape.iter().cloned());
+        let manifest = elevation::Manifest::builder().shape(shape);
+        let heights: Vec<_> = self
+            .height_profile(manifest, max_points)?
+            .into_iter()
+            .map(|sample| sample.height)
+            .collect();
+        Ok(elevation::annotate(trip, &heights))
+    }
+
+    /// Make an isochrone request, computing the areas reachable within the contours of the manifest
+    ///
+    /// See <https://valhalla.github.io/valhalla/api/isochrone/api-reference/> and the [`isochrone`] module for details
+    pub fn isochrone(&self, manifest: isochrone::Manifest) -> Result<isochrone::Response, Error> {
+        self.do_request(&manifest, "isochrone", ResponseFormat::Json)
+    }
+
+    /// Computes the isochrones of several facilities, e.g. the catchments of all stores of a chain
+    ///
+    /// Each facility gets the contours of the manifest, whose location is replaced. Instances limit
+    /// the contours per request (4 by default), so the contours are requested in chunks of at most
+    /// `max_contours` and joined again.
+    /// All requests are sent concurrently (see [`Self::concurrency`]), and one response is returned
+    /// per facility, in the order of the facilities.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::isochrone::{Contour, Manifest};
+    /// use valhalla_client::route::Location;
+    ///
+    /// let stores = [Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)];
+    /// let manifest = Manifest::builder()
+    ///   .contours([5.0, 10.0, 15.0, 20.0, 30.0, 45.0].map(Contour::time))
+    ///   .polygons(true);
+    /// let catchments = Valhalla::default().isochrone_sweep(manifest, stores, 4).unwrap();
+    /// assert_eq!(catchments.len(), 2);
+    /// ```
+    pub fn isochrone_sweep(
+        &self,
+        manifest: isochrone::Manifest,
+        facilities: impl IntoIterator<Item = route::Location>,
+        max_contours: usize,
+    ) -> Result<Vec<isochrone::Response>, Error> {
+        let facilities: Vec<_> = facilities.into_iter().collect();
+        let manifests = manifest.sweep(&facilities, max_contours);
+        let responses = parallel::map(&manifests, self.concurrency, |(_, manifest)| {
+            self.isochrone(manifest.clone())
+        });
+        let mut catchments: Vec<Vec<isochrone::Response>> = vec![Vec::new(); facilities.len()];
+        for ((facility, _), response) in manifests.iter().zip(responses) {
+            catchments[*facility].push(response?);
+        }
+        Ok(catchments
+            .into_iter()
+            .filter_map(isochrone::Response::merge)
+            .collect())
+    }
+
+    /// Make an expansion request, returning the edges visited while computing a route or isochrone
+    ///
+    /// See <https://valhalla.github.io/valhalla/api/expansion/api-reference/> and the [`expansion`] module for details
+    pub fn expansion(&self, manifest: expansion::Manifest) -> Result<expansion::Response, Error> {
+        self.do_request(&manifest, "expansion", ResponseFormat::Json)
+    }
+
+    /// Whether a point lies within the tiles of the instance and near a road, e.g. to avoid doomed routing requests
+    ///
+    /// Checks the point against the extent of the tiles ([`status::Status::covers`]) first,
+    /// then probes whether [`Self::locate`] finds an edge within valhalla's search radius.
+    /// If the instance does not report its extent, only the probe is used.
+    /// To check many points, fetch the extent once via [`Self::status_verbose`] instead.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    ///
+    /// let valhalla = Valhalla::default();
+    /// assert!(valhalla.is_covered((4.9041, 52.3676)).unwrap());
+    /// ```
+    pub fn is_covered(&self, point: Coordinate) -> Result<bool, Error> {
+        if self.status_verbose()?.covers(point) == Some(false) {
+            return Ok(false);
+        }
+        let manifest = locate::Manifest::builder().locations([point.into()]);
+        let candidates = self.locate(manifest)?;
+        Ok(candidates.iter().any(|c| !c.edges().is_empty()))
+    }
+
+    /// Make a locate request, returning the edges and nodes nearest to each location
+    ///
+    /// See <https://valhalla.github.io/valhalla/api/locate/api-reference/> and the [`locate`] module for details
+    pub fn locate(&self, manifest: locate::Manifest) -> Result<locate::Response, Error> {
+        self.do_request(&manifest, "locate", ResponseFormat::Json)
+    }
+
+    /// Locates any number of locations, splitting them into requests of at most `max_locations` locations
+    ///
+    /// Instances limit the number of locations per request, e.g. to snap large sets of POIs.
+    /// The chunks are requested concurrently (see [`Self::concurrency`]) and the candidates are
+    /// returned in the order of the locations of the manifest.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::locate::Manifest;
+    /// use valhalla_client::route::Location;
+    ///
+    /// let pois = (0..10_000).map(|i| Location::new(4.9 + i as f32 * 1e-4, 52.37));
+    /// let candidates = Valhalla::default()
+    ///   .locate_many(Manifest::builder().locations(pois), 100)
+    ///   .unwrap();
+    /// assert_eq!(candidates.len(), 10_000);
+    /// ```
+    pub fn locate_many(
+        &self,
+        manifest: locate::Manifest,
+        max_locations: usize,
+    ) -> Result<locate::Response, Error> {
+        let chunks = manifest.chunks(max_locations);
+        let responses = parallel::map(&chunks, self.concurrency, |chunk| {
+            self.locate(chunk.clone())
+        });
+        let responses = responses.into_iter().collect::<Result<Vec<_>, _>>()?;
+        Ok(responses.into_iter().flatten().collect())
+    }
+
+    /// The url with the [`Self::api_key`] added
+    fn authorized(&self, mut url: url::Url) -> url::Url {
+        if let Some(api_key) = &self.api_key {
+            url.query_pairs_mut()
+                .append_pair("api_key", api_key.expose());
+        }
+        url
+    }
+
+    /// Wraps a transport error, without the url if it contains the [`Self::api_key`]
+    fn reqwest_error(&self, error: reqwest::Error) -> Error {
+        match self.api_key {
+            Some(_) => Error::Reqwest(error.without_url()),
+            None => Error::Reqwest(error),
+        }
+    }
+
+    fn url_for(&self, action: &str) -> url::Url {
+        let path = self.endpoints.get(action).map_or(action, String::as_str);
         let mut url = self.base_url.clone();
         url.path_segments_mut()
             .expect("base_url is not a valid base url")
-            .push("sources_to_targets");
-        let response = self
-            .client
-            .post(url)
-            .json(&manifest)
-            .send()
-            .map_err(Error::Reqwest)?;
-        if response.status().is_client_error() {
-            return Err(Error::RemoteError(response.json().map_err(Error::Reqwest)?));
-        }
-        response.error_for_status_ref().map_err(Error::Reqwest)?;
-        let text = response.text().map_err(Error::Reqwest)?;
-        let response: matrix::Response = serde_json::from_str(&text).map_err(Error::Serde)?;
-        Ok(response)
+            .pop_if_empty()
+            .extend(self.path_prefix.split('/').filter(|s| !s.is_empty()))
+            .extend(path.split('/').filter(|s| !s.is_empty()));
+        url
+    }
+
+    fn do_request<Resp: serde::de::DeserializeOwned + Serialize>(
+        &self,
+        manifest: &impl Serialize,
+        action: &'static str,
+        format: ResponseFormat,
+    ) -> Result<Resp, Error> {
+        let text = self.fetch(manifest, action, format)?;
+        if !self.strict_parsing {
+            return serde_json::from_str(&text).map_err(Error::Serde);
+        }
+        let received: serde_json::Value = serde_json::from_str(&text).map_err(Error::Serde)?;
+        let parsed: Resp = serde_json::from_value(received.clone()).map_err(Error::Serde)?;
+        let reserialized = serde_json::to_value(&parsed).map_err(Error::Serde)?;
+        strict::check(&received, &reserialized)
+            .map_err(|e| Error::Serde(serde::de::Error::custom(e)))?;
+        Ok(parsed)
+    }
+
+    /// Sends a request, returning the body of a successful response
+    fn fetch(
+        &self,
+        manifest: &impl Serialize,
+        action: &'static str,
+        format: ResponseFormat,
+    ) -> Result<String, Error> {
+        let mut manifest = self.with_defaults(manifest, action)?;
+        if log::log_enabled!(log::Level::Debug) {
+            // coordinates are dropped, unless the logger of this client allows more
+            let redaction = self
+                .logger
+                .as_ref()
+                .map_or(logging::Redaction::Drop, |logger| logger.redaction);
+            let mut body = manifest.clone();
+            redaction.apply(&mut body);
+            debug!("Sending {action} request: {body}");
+        }
+        if let Some(request) = manifest.as_object_mut() {
+            let format = serde_json::to_value(format).map_err(Error::Serde)?;
+            request.insert("format".to_string(), format);
+        }
+        let manifest = &manifest;
+        let response = match &self.single_flight {
+            Some(flights) => {
+                let request = canonical::to_string(manifest).map_err(Error::Serde)?;
+                flights.run(format!("{action} {request}"), || {
+                    self.send(manifest, action)
+                })?
+            }
+            None => self.send(manifest, action)?,
+        };
+        if response.status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited(response.rate_limit.unwrap_or_default()));
+        }
+        if response.status.is_client_error() {
+            return Err(Error::RemoteError(
+                serde_json::from_str(&response.text).map_err(Error::Serde)?,
+            ));
+        }
+        Ok(response.text)
+    }
+
+    /// The format of the shapes in the response to a request, as requested or defaulted via [`Self::defaults`]
+    fn shape_format(
+        &self,
+        manifest: &impl Serialize,
+        action: &str,
+    ) -> Result<shapes::ShapeFormat, Error> {
+        Ok(shapes::ShapeFormat::of(
+            &self.with_defaults(manifest, action)?,
+        ))
+    }
+
+    /// The request body with the [`Self::defaults`] of the action filled in
+    fn with_defaults(
+        &self,
+        manifest: &impl Serialize,
+        action: &str,
+    ) -> Result<serde_json::Value, Error> {
+        let mut request = serde_json::to_value(manifest).map_err(Error::Serde)?;
+        if let Some(defaults) = self.defaults.get(action) {
+            fill_defaults(&mut request, defaults);
+        }
+        Ok(request)
+    }
+
+    /// Sends a request, failing on transport and server errors
+    fn send(
+        &self,
+        manifest: &serde_json::Value,
+        action: &'static str,
+    ) -> Result<RawResponse, Error> {
+        if let Some(logger) = &self.logger {
+            logger.log_request(action, manifest);
+        }
+        let _slot = self
+            .queue
+            .as_ref()
+            .map(|q| q.acquire(queue::Priority::of(action)));
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Ok(user_agent) = self.user_agent.parse() {
+            headers.insert(reqwest::header::USER_AGENT, user_agent);
+        }
+        let request = middleware::Request {
+            action,
+            url: self.url_for(action),
+            headers,
+            body: manifest.clone(),
+        };
+        // the error of the last response received from valhalla, as layers only pass on the response
+        let status_error = std::cell::Cell::new(None);
+        let remote_addr = std::cell::Cell::new(None);
+        let transport = |request: middleware::Request| {
+            let response = self
+                .client
+                .post(self.authorized(request.url))
+                .header(reqwest::header::CONTENT_TYPE, self.encoder.content_type())
+                .headers(request.headers)
+                .body(self.encoder.encode(&request.body)?)
+                .send()
+                .map_err(|e| self.reqwest_error(e))?;
+            status_error.set(response.error_for_status_ref().err());
+            remote_addr.set(response.remote_addr());
+            if let Some(addr) = response.remote_addr() {
+                *self
+                    .connection_stats
+                    .lock()
+                    .unwrap()
+                    .entry(addr)
+                    .or_default() += 1;
+            }
+            Ok(middleware::Response {
+                status: response.status(),
+                headers: response.headers().clone(),
+                body: response.text().map_err(|e| self.reqwest_error(e))?,
+            })
+        };
+        let started = std::time::Instant::now();
+        let response = middleware::Next::new(&self.middlewares, &transport).run(request)?;
+        let status = response.status;
+        let rate_limit = rate_limit::RateLimit::from_headers(&response.headers);
+        if let (Some(observer), Some(rate_limit)) = (&self.rate_limit_observer, &rate_limit) {
+            observer(rate_limit);
+        }
+        let server_time = timing::server_time(&response.headers);
+        let text = response.body;
+        if let Some(observer) = &self.timing_observer {
+            observer(&timing::Timing {
+                action,
+                latency: started.elapsed(),
+                server_time,
+                remote_addr: remote_addr.get(),
+            });
+        }
+        if let Some(logger) = &self.logger {
+            logger.log_response(action, status.as_u16(), &text);
+        }
+        match status_error.take() {
+            Some(e) if !status.is_client_error() => Err(self.reqwest_error(e)),
+            _ => Ok(RawResponse {
+                status,
+                rate_limit,
+                text,
+            }),
+        }
+    }
+}
+
+/// Recursively adds the keys of `defaults` which are missing in `request`
+fn fill_defaults(request: &mut serde_json::Value, defaults: &serde_json::Value) {
+    let (Some(request), Some(defaults)) = (request.as_object_mut(), defaults.as_object()) else {
+        return;
+    };
+    for (key, default) in defaults {
+        match request.get_mut(key) {
+            Some(value) => fill_defaults(value, default),
+            None => {
+                request.insert(key.clone(), default.clone());
+            }
+        }
+    }
+}
+
+/// A response as received from valhalla, before it is interpreted
+#[derive(Debug, Clone)]
+struct RawResponse {
+    status: reqwest::StatusCode,
+    rate_limit: Option<rate_limit::RateLimit>,
+    text: String,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn url_for() {
+        let valhalla = Valhalla::default();
+        assert_eq!(
+            valhalla.url_for("route").as_str(),
+            "https://valhalla1.openstreetmap.de/route"
+        );
+        let valhalla = Valhalla::new(url::Url::parse("https://example.com/api/").unwrap())
+            .path_prefix("/valhalla/")
+            .endpoint("route", "route/v1");
+        assert_eq!(
+            valhalla.url_for("route").as_str(),
+            "https://example.com/api/valhalla/route/v1"
+        );
+        assert_eq!(
+            valhalla.url_for("sources_to_targets").as_str(),
+            "https://example.com/api/valhalla/sources_to_targets"
+        );
+    }
+    /// Serves a single response on a local port
+    fn serve_once(
+        status: &'static str,
+        body: impl ToString,
+    ) -> (std::net::SocketAddr, std::thread::JoinHandle<()>) {
+        let body = body.to_string();
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let serve = std::thread::spawn(move || {
+            use std::io::{Read, Write};
+            let (mut stream, _) = server.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        });
+        (addr, serve)
+    }
+    #[test]
+    fn resolve() {
+        let (addr, serve) = serve_once("200 OK", r#"{"version":"3.5.1"}"#);
+        let served_by = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let observed = served_by.clone();
+        let url = url::Url::parse(&format!("http://valhalla.invalid:{}", addr.port())).unwrap();
+        let valhalla = Valhalla::new(url)
+            .resolve([addr])
+            .timing_observer(move |timing| *observed.lock().unwrap() = timing.remote_addr);
+        assert_eq!(valhalla.status().unwrap().version, "3.5.1");
+        assert_eq!(*served_by.lock().unwrap(), Some(addr));
+        assert_eq!(valhalla.connection_stats(), [(addr, 1)].into());
+        serve.join().unwrap();
+    }
+    #[test]
+    fn is_covered_outside_tiles() {
+        let status = serde_json::json!({"version": "3.5.1", "bbox": {
+            "type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 1], [0, 0]]],
+        }});
+        let (addr, serve) = serve_once("200 OK", status);
+        let valhalla = Valhalla::new(url::Url::parse(&format!("http://{addr}")).unwrap());
+        assert!(!valhalla.is_covered((4.9041, 52.3676)).unwrap());
+        serve.join().unwrap();
+    }
+    #[test]
+    fn resolve_ip_host() {
+        let url = url::Url::parse("http://127.0.0.1:8002").unwrap();
+        let valhalla = Valhalla::new(url).resolve(["10.0.0.7:8002".parse().unwrap()]);
+        assert!(valhalla.resolved_addrs.is_empty());
+    }
+    #[test]
+    fn polyline5_shapes() {
+        let summary = serde_json::json!({
+            "time": 0.0, "length": 0.0, "has_toll": false, "has_highway": false, "has_ferry": false,
+            "min_lat": 0.0, "min_lon": 0.0, "max_lat": 0.0, "max_lon": 0.0,
+        });
+        let response = serde_json::json!({"trip": {
+            "status": 0, "status_message": "Found route between points",
+            "units": "kilometers", "language": "en-US", "locations": [],
+            "legs": [{"summary": summary, "maneuvers": [], "shape": "_p~iF~ps|U_ulLnnqC"}],
+            "summary": summary,
+        }});
+        let valhalla = |addr: std::net::SocketAddr| {
+            let url = url::Url::parse(&format!("http://{addr}")).unwrap();
+            let polyline5 = serde_json::json!({"shape_format": "polyline5"});
+            Valhalla::new(url).defaults("route", &polyline5)
+        };
+        let assert_polyline5 = |trip: route::Trip| {
+            let shape = &trip.legs[0].shape;
+            assert_eq!((shape[1].lon, shape[1].lat), (-120.95, 40.7));
+        };
+
+        let (addr, serve) = serve_once("200 OK", &response);
+        assert_polyline5(valhalla(addr).route(route::Manifest::builder()).unwrap());
+        serve.join().unwrap();
+
+        let (addr, serve) = serve_once("200 OK", &response);
+        let manifest = trace::Manifest::builder().shape_format(shapes::ShapeFormat::Polyline5);
+        assert_polyline5(valhalla(addr).trace_route(manifest).unwrap());
+        serve.join().unwrap();
+
+        let (addr, serve) = serve_once("200 OK", &response);
+        let manifest =
+            optimized_route::Manifest::builder().shape_format(shapes::ShapeFormat::Polyline5);
+        assert_polyline5(valhalla(addr).optimized_route(manifest).unwrap().trip);
+        serve.join().unwrap();
+    }
+    #[test]
+    fn warm_up() {
+        let (addr, serve) = serve_once("503 Service Unavailable", "");
+        let url = url::Url::parse(&format!("http://{addr}")).unwrap();
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let observed = seen.clone();
+        let valhalla = Valhalla::new(url).middleware(
+            move |request: middleware::Request, next: middleware::Next| {
+                observed.lock().unwrap().push(request.action);
+                next.run(request)
+            },
+        );
+        valhalla.warm_up().unwrap();
+        assert_eq!(*seen.lock().unwrap(), ["status"]);
+        serve.join().unwrap();
+    }
+    #[test]
+    fn api_key() {
+        let valhalla = Valhalla::default().api_key("secret");
+        assert_eq!(
+            valhalla.authorized(valhalla.url_for("route")).as_str(),
+            "https://valhalla1.openstreetmap.de/route?api_key=secret"
+        );
+        assert_eq!(
+            format!("{:?}", valhalla.api_key),
+            "Some(ApiKey([REDACTED]))"
+        );
+    }
+    #[test]
+    fn user_agent() {
+        let version = env!("CARGO_PKG_VERSION");
+        assert_eq!(
+            Valhalla::default().user_agent,
+            format!("valhalla-client/{version}")
+        );
+        assert_eq!(
+            Valhalla::default().app_identifier("my-app/1.0").user_agent,
+            format!("valhalla-client/{version} my-app/1.0")
+        );
+        assert_eq!(
+            Valhalla::default()
+                .app_identifier("my-app/1.0")
+                .user_agent("custom")
+                .user_agent,
+            "custom"
+        );
+    }
+    #[test]
+    fn response_format() {
+        assert_eq!(
+            serde_json::to_value(ResponseFormat::Json).unwrap(),
+            serde_json::json!("json")
+        );
+        assert_eq!(
+            serde_json::to_value(ResponseFormat::Osrm).unwrap(),
+            serde_json::json!("osrm")
+        );
+    }
+    #[test]
+    fn defaults() {
+        let valhalla = Valhalla::default().defaults(
+            "route",
+            &route::Manifest::builder()
+                .units(Units::Imperial)
+                .language("de-DE")
+                .costing(costing::Costing::Auto(
+                    costing::auto::AutoCostingOptions::builder().include_hov2(true),
+                )),
+        );
+        let request = route::Manifest::builder()
+            .language("en-US")
+            .costing(costing::Costing::Auto(
+                costing::auto::AutoCostingOptions::builder().include_hov3(true),
+            ));
+        assert_eq!(
+            valhalla.with_defaults(&request, "route").unwrap(),
+            serde_json::json!({
+                "locations": [], "units": "miles", "language": "en-US", "costing": "auto",
+                "costing_options": {"auto": {"include_hov2": true, "include_hov3": true}},
+            })
+        );
+        assert_eq!(
+            valhalla
+                .with_defaults(&request, "sources_to_targets")
+                .unwrap(),
+            serde_json::to_value(&request).unwrap()
+        );
+    }
+    #[test]
+    fn units_from_locale() {
+        assert_eq!(Units::from_locale("en-US"), Units::Imperial);
+        assert_eq!(Units::from_locale("en_gb"), Units::Imperial);
+        assert_eq!(Units::from_locale("de-DE"), Units::Metric);
+        assert_eq!(Units::from_locale("zh-Hant-TW"), Units::Metric);
+        assert_eq!(Units::from_locale("en"), Units::Metric);
     }
 }
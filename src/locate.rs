@@ -0,0 +1,597 @@
+//! The edges and nodes of the road network nearest to locations, e.g. to check where a location snaps to
+//!
+//! See <https://valhalla.github.io/valhalla/api/locate/api-reference/> for details
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::costing::Costing;
+//! use valhalla_client::locate::{Manifest, SearchFilter};
+//! use valhalla_client::route::Location;
+//!
+//! let location = Location::new(4.9041, 52.3676)
+//!   .search_filter(SearchFilter::builder().exclude_tunnel(true).exclude_ferry(true));
+//! let manifest = Manifest::builder()
+//!   .locations([location])
+//!   .costing(Costing::Auto(Default::default()))
+//!   .verbose(true);
+//! let response = Valhalla::default().locate(manifest).unwrap();
+//! for edge in response[0].edges() {
+//!   println!("way {:?} on the {:?} side", edge.way_id(), edge.side_of_street);
+//! }
+//! ```
+use crate::costing;
+use crate::route::{Location, SideOfStreet};
+use crate::trace::{RoadClass, Surface, Traversability};
+use serde::{Deserialize, Serialize};
+
+/// Restricts which edges a location may be correlated to
+///
+/// Set via [`Location::search_filter`].
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SearchFilter {
+    exclude_tunnel: Option<bool>,
+    exclude_bridge: Option<bool>,
+    exclude_toll: Option<bool>,
+    exclude_ramp: Option<bool>,
+    exclude_ferry: Option<bool>,
+    exclude_closures: Option<bool>,
+    min_road_class: Option<RoadClass>,
+    max_road_class: Option<RoadClass>,
+    level: Option<f32>,
+}
+
+impl SearchFilter {
+    #[must_use]
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Whether to exclude tunnels
+    ///
+    /// Default: `false`
+    pub fn exclude_tunnel(mut self, exclude_tunnel: bool) -> Self {
+        self.exclude_tunnel = Some(exclude_tunnel);
+        self
+    }
+    /// Whether to exclude bridges
+    ///
+    /// Default: `false`
+    pub fn exclude_bridge(mut self, exclude_bridge: bool) -> Self {
+        self.exclude_bridge = Some(exclude_bridge);
+        self
+    }
+    /// Whether to exclude toll roads
+    ///
+    /// Default: `false`
+    pub fn exclude_toll(mut self, exclude_toll: bool) -> Self {
+        self.exclude_toll = Some(exclude_toll);
+        self
+    }
+    /// Whether to exclude ramps, e.g. motorway on- and off-ramps
+    ///
+    /// Default: `false`
+    pub fn exclude_ramp(mut self, exclude_ramp: bool) -> Self {
+        self.exclude_ramp = Some(exclude_ramp);
+        self
+    }
+    /// Whether to exclude ferries
+    ///
+    /// Default: `false`
+    pub fn exclude_ferry(mut self, exclude_ferry: bool) -> Self {
+        self.exclude_ferry = Some(exclude_ferry);
+        self
+    }
+    /// Whether to exclude roads closed due to live traffic
+    ///
+    /// Default: `true`
+    pub fn exclude_closures(mut self, exclude_closures: bool) -> Self {
+        self.exclude_closures = Some(exclude_closures);
+        self
+    }
+    /// The least important road class to correlate to
+    ///
+    /// Default: [`RoadClass::ServiceOther`]
+    pub fn min_road_class(mut self, road_class: RoadClass) -> Self {
+        self.min_road_class = Some(road_class);
+        self
+    }
+    /// The most important road class to correlate to
+    ///
+    /// Default: [`RoadClass::Motorway`]
+    pub fn max_road_class(mut self, road_class: RoadClass) -> Self {
+        self.max_road_class = Some(road_class);
+        self
+    }
+    /// Only correlates to edges on this floor level of a building, e.g. `-1` for the basement
+    ///
+    /// Default: edges on all levels
+    pub fn level(mut self, level: f32) -> Self {
+        self.level = Some(level);
+        self
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Default, Debug, Clone)]
+pub struct Manifest {
+    #[serde(flatten)]
+    costing: Option<costing::Costing>,
+    locations: Vec<Location>,
+    verbose: Option<bool>,
+    id: Option<String>,
+}
+
+impl Manifest {
+    #[must_use]
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Sets the locations to find the nearest edges and nodes of
+    ///
+    /// The search can be configured per location, e.g. via [`Location::radius`] or [`Location::search_filter`].
+    pub fn locations(mut self, locations: impl IntoIterator<Item = Location>) -> Self {
+        self.locations = locations.into_iter().collect();
+        self
+    }
+    /// Only considers edges and nodes accessible with this costing model
+    ///
+    /// Default: [`costing::Costing::Auto`]
+    pub fn costing(mut self, costing: costing::Costing) -> Self {
+        self.costing = Some(costing);
+        self
+    }
+    /// Returns the details of each edge and node, e.g. [`Edge::edge_info`] and [`Edge::edge`]
+    ///
+    /// Default: `false`, i.e. only the OpenStreetMap way ids and the correlated positions
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = Some(verbose);
+        self
+    }
+    /// Name your locate request.
+    ///
+    /// If id is specified, the naming will be sent through to the response.
+    pub fn id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Splits the locations into manifests of at most `max_locations` locations, see [`crate::Valhalla::locate_many`]
+    pub(crate) fn chunks(&self, max_locations: usize) -> Vec<Self> {
+        self.locations
+            .chunks(max_locations.max(1))
+            .map(|locations| Self {
+                locations: locations.to_vec(),
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+/// The result of [`crate::Valhalla::locate`], one entry per location of the [`Manifest`]
+pub type Response = Vec<Candidates>;
+
+/// The edges and nodes nearest to a location
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Candidates {
+    pub input_lat: f64,
+    pub input_lon: f64,
+    /// `None` if no edge was found, e.g. because the location is too far from the road network
+    pub edges: Option<Vec<Edge>>,
+    pub nodes: Option<Vec<Node>>,
+    /// Further attributes, e.g. `warnings`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Candidates {
+    /// The edges the location can be correlated to, nearest first
+    pub fn edges(&self) -> &[Edge] {
+        self.edges.as_deref().unwrap_or_default()
+    }
+
+    /// The country and state the location is in, e.g. to assign a tax or toll jurisdiction
+    ///
+    /// Taken from the nearest node, so only known if the request was [`Manifest::verbose`]
+    /// and a node was found.
+    pub fn admin(&self) -> Option<Admin> {
+        self.nodes
+            .iter()
+            .flatten()
+            .find_map(|node| node.administrative.as_ref())
+            .map(Admin::from)
+    }
+
+    /// The directions in which the way `way_id` can be travelled with the `mode`, e.g. `car` or `pedestrian`
+    ///
+    /// Both directed edges of a way are usually returned, so this combines their [`EdgeAttributes::access`].
+    /// Only known if the request was [`Manifest::verbose`]; `None` if the way cannot be travelled at all.
+    pub fn traversability(&self, way_id: u64, mode: &str) -> Option<Traversability> {
+        let accessible = |forward: bool| {
+            self.edges()
+                .iter()
+                .filter(|edge| edge.way_id() == Some(way_id))
+                .filter_map(|edge| edge.edge.as_ref())
+                .filter(|attributes| attributes.forward == Some(forward))
+                .any(|attributes| attributes.access.get(mode) == Some(&true))
+        };
+        match (accessible(true), accessible(false)) {
+            (true, true) => Some(Traversability::Both),
+            (true, false) => Some(Traversability::Forward),
+            (false, true) => Some(Traversability::Backward),
+            (false, false) => None,
+        }
+    }
+
+    /// Why each edge at the location may not be used with the `mode`, e.g. `car`
+    ///
+    /// Helps debugging why a route does not turn at a junction: locate the junction with
+    /// [`Manifest::verbose`] and inspect the restrictions of its incident edges.
+    /// Edges without restrictions are returned with an empty list.
+    /// Only known if the request was [`Manifest::verbose`].
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::locate::Manifest;
+    /// use valhalla_client::route::Location;
+    ///
+    /// let junction = Location::new(4.8952, 52.3702);
+    /// let manifest = Manifest::builder().locations([junction]).verbose(true);
+    /// let response = Valhalla::default().locate(manifest).unwrap();
+    /// for (edge, restrictions) in response[0].restrictions("car") {
+    ///   println!("way {:?} heading {:?}: {restrictions:?}", edge.way_id(), edge.heading);
+    /// }
+    /// ```
+    pub fn restrictions(&self, mode: &str) -> Vec<(&Edge, Vec<Restriction>)> {
+        self.edges()
+            .iter()
+            .map(|edge| {
+                let Some(attributes) = &edge.edge else {
+                    return (edge, Vec::new());
+                };
+                let restricted = |modes: &std::collections::BTreeMap<String, bool>| {
+                    modes.get(mode) == Some(&true)
+                };
+                let restrictions = [
+                    (
+                        attributes.access.get(mode) == Some(&false),
+                        Restriction::NoAccess,
+                    ),
+                    (
+                        restricted(&attributes.start_restriction),
+                        Restriction::Starts,
+                    ),
+                    (restricted(&attributes.end_restriction), Restriction::Ends),
+                    (
+                        attributes.part_of_complex_restriction == Some(true),
+                        Restriction::Via,
+                    ),
+                ];
+                let restrictions = restrictions
+                    .into_iter()
+                    .filter_map(|(applies, restriction)| applies.then_some(restriction));
+                (edge, restrictions.collect())
+            })
+            .collect()
+    }
+}
+
+/// Why an edge may not be used, see [`Candidates::restrictions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restriction {
+    /// The edge cannot be travelled with the mode, see [`EdgeAttributes::access`]
+    NoAccess,
+    /// A turn restriction over several edges starts on the edge, see [`EdgeAttributes::start_restriction`]
+    Starts,
+    /// A turn restriction over several edges ends on the edge, see [`EdgeAttributes::end_restriction`]
+    Ends,
+    /// The edge is part of a turn restriction over several edges, for any mode
+    Via,
+}
+
+/// A directed edge of the road network a location can be correlated to
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Edge {
+    /// The OpenStreetMap way the edge is part of, see [`Self::way_id`]
+    pub way_id: Option<u64>,
+    /// The position on the edge nearest to the location
+    pub correlated_lat: f64,
+    pub correlated_lon: f64,
+    pub side_of_street: Option<SideOfStreet>,
+    /// How far along the edge the correlated position is, between `0` and `1`
+    pub percent_along: Option<f64>,
+    /// The distance in meters from the location to the correlated position
+    pub distance: Option<f64>,
+    /// The bearing in degrees of the edge at the correlated position, clockwise from north
+    pub heading: Option<f64>,
+    /// The number of nodes reachable from the edge, up to [`Location::minimum_reachability`]
+    pub outbound_reach: Option<u32>,
+    /// The number of nodes from which the edge is reachable, up to [`Location::minimum_reachability`]
+    pub inbound_reach: Option<u32>,
+    /// The attributes shared by both directions of the edge, if [`Manifest::verbose`]
+    pub edge_info: Option<EdgeInfo>,
+    /// The attributes of this direction of the edge, if [`Manifest::verbose`]
+    pub edge: Option<EdgeAttributes>,
+    /// The id of the edge in valhalla's graph, if [`Manifest::verbose`]
+    #[serde(default, with = "crate::graph::verbose_id")]
+    pub edge_id: Option<crate::graph::GraphId>,
+    /// Further attributes, e.g. `linear_reference` or `predicted_speeds`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Edge {
+    /// The OpenStreetMap way the edge is part of, regardless of [`Manifest::verbose`]
+    pub fn way_id(&self) -> Option<u64> {
+        self.way_id
+            .or_else(|| self.edge_info.as_ref().and_then(|info| info.way_id))
+    }
+}
+
+/// The attributes shared by both directions of an [`Edge`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EdgeInfo {
+    pub way_id: Option<u64>,
+    /// The names of the road, e.g. street names and route numbers
+    pub names: Option<Vec<String>>,
+    /// The shape of the edge as polyline with a precision of 6 digits
+    pub shape: Option<String>,
+    /// Further attributes, e.g. `bike_network` or `speed_limit`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The classification of an [`Edge`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub classification: Option<RoadClass>,
+    pub surface: Option<Surface>,
+    /// How the road is used, e.g. a ramp or cycleway
+    #[serde(rename = "use")]
+    pub use_: Option<crate::graph::EdgeUse>,
+    /// Whether the edge is a ramp or turn channel
+    pub link: Option<bool>,
+    /// Whether the edge is part of an intersection
+    pub internal: Option<bool>,
+}
+
+/// The attributes of one direction of an [`Edge`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EdgeAttributes {
+    /// Whether the edge is in the direction of its OpenStreetMap way
+    pub forward: Option<bool>,
+    /// The estimated speed in km/h
+    pub speed: Option<f64>,
+    pub classification: Option<Classification>,
+    /// Whether the edge can be travelled per mode, e.g. `car`, `bicycle` or `pedestrian`
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub access: std::collections::BTreeMap<String, bool>,
+    pub toll: Option<bool>,
+    pub tunnel: Option<bool>,
+    pub bridge: Option<bool>,
+    pub round_about: Option<bool>,
+    /// The kind of cycle lane along the edge
+    pub cycle_lane: Option<crate::graph::CycleLane>,
+    /// Per mode, whether a turn restriction over several edges starts on the edge
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub start_restriction: std::collections::BTreeMap<String, bool>,
+    /// Per mode, whether a turn restriction over several edges ends on the edge
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub end_restriction: std::collections::BTreeMap<String, bool>,
+    /// Whether the edge is part of a turn restriction over several edges
+    pub part_of_complex_restriction: Option<bool>,
+    /// Further attributes, e.g. `end_node`, `lane_count` or `geo_attributes`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A node of the road network, e.g. an intersection, the location is near
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Node {
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    /// The id of the node in valhalla's graph, if [`Manifest::verbose`]
+    #[serde(default, with = "crate::graph::verbose_id")]
+    pub node_id: Option<crate::graph::GraphId>,
+    /// What the node represents, e.g. an intersection or gate, if [`Manifest::verbose`]
+    #[serde(rename = "type")]
+    pub type_: Option<crate::graph::NodeType>,
+    /// The administrative areas the node is in, if [`Manifest::verbose`], see [`Candidates::admin`]
+    pub administrative: Option<Administrative>,
+    /// Further attributes, e.g. `access` or `edge_count` if [`Manifest::verbose`]
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A country and state, as returned by the map matching and locate endpoints
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Admin {
+    /// The ISO 3166-1 code of the country, e.g. `NL`
+    pub country_code: Option<String>,
+    /// The name of the country, e.g. `Netherlands`
+    pub country_text: Option<String>,
+    /// The ISO 3166-2 code of the state without the country prefix, e.g. `NH`
+    pub state_code: Option<String>,
+    /// The name of the state, e.g. `Noord-Holland`
+    pub state_text: Option<String>,
+}
+
+/// The administrative areas of a [`Node`], see [`Admin`] for their meaning
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Administrative {
+    #[serde(rename = "iso_3166-1")]
+    pub country_code: Option<String>,
+    #[serde(rename = "country")]
+    pub country_text: Option<String>,
+    #[serde(rename = "iso_3166-2")]
+    pub state_code: Option<String>,
+    #[serde(rename = "state")]
+    pub state_text: Option<String>,
+    /// Further attributes, e.g. `time_zone_posix`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl From<&Administrative> for Admin {
+    fn from(administrative: &Administrative) -> Self {
+        Self {
+            country_code: administrative.country_code.clone(),
+            country_text: administrative.country_text.clone(),
+            state_code: administrative.state_code.clone(),
+            state_text: administrative.state_text.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn chunks() {
+        let manifest = Manifest::builder()
+            .locations((0..5).map(|i| Location::new(i as f32, 0.0)))
+            .verbose(true);
+        let chunks = manifest.chunks(2);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| chunk.verbose == Some(true)));
+        let locations: Vec<&Location> = chunks.iter().flat_map(|c| &c.locations).collect();
+        assert_eq!(
+            serde_json::to_value(locations).unwrap(),
+            serde_json::to_value(&manifest.locations).unwrap()
+        );
+        assert_eq!(Manifest::builder().chunks(2).len(), 0);
+    }
+    #[test]
+    fn serialisation() {
+        let filter = SearchFilter::builder()
+            .exclude_ramp(true)
+            .min_road_class(RoadClass::Residential);
+        let manifest = Manifest::builder()
+            .locations([Location::new(4.9, 52.4).search_filter(filter)])
+            .verbose(true);
+        assert_eq!(
+            serde_json::to_value(&manifest).unwrap(),
+            serde_json::json!({
+                "locations": [{
+                    "lat": 52.4_f32, "lon": 4.9_f32,
+                    "search_filter": {"exclude_ramp": true, "min_road_class": "residential"},
+                }],
+                "verbose": true,
+            })
+        );
+    }
+    #[test]
+    fn restrictions() {
+        let edge = |attributes: serde_json::Value| serde_json::json!({"correlated_lat": 52.4, "correlated_lon": 4.9, "edge": attributes});
+        let candidates: Candidates = serde_json::from_value(serde_json::json!({
+            "input_lat": 52.4, "input_lon": 4.9,
+            "edges": [
+                edge(serde_json::json!({"access": {"car": true}})),
+                edge(serde_json::json!({
+                    "access": {"car": false, "bicycle": true},
+                    "start_restriction": {"car": true, "bicycle": false},
+                    "part_of_complex_restriction": true,
+                })),
+                {"correlated_lat": 52.4, "correlated_lon": 4.9},
+            ],
+        }))
+        .unwrap();
+        let restrictions = |mode| -> Vec<Vec<Restriction>> {
+            let restrictions = candidates.restrictions(mode).into_iter();
+            restrictions.map(|(_, r)| r).collect()
+        };
+        assert_eq!(
+            restrictions("car"),
+            [
+                vec![],
+                vec![Restriction::NoAccess, Restriction::Starts, Restriction::Via],
+                vec![]
+            ]
+        );
+        assert_eq!(restrictions("bicycle")[1], [Restriction::Via]);
+    }
+    #[test]
+    fn deserialisation() {
+        let edge = |forward: bool, car: bool| {
+            serde_json::json!({
+                "correlated_lat": 52.4, "correlated_lon": 4.9,
+                "side_of_street": "neither", "percent_along": 0.5, "distance": 3.2,
+                "edge_info": {"way_id": 42, "names": ["Damrak"], "shape": "_izlhA~rlgdF"},
+                "edge": {
+                    "forward": forward, "speed": 30,
+                    "classification": {"classification": "residential", "surface": "paved", "use": "road"},
+                    "access": {"car": car, "pedestrian": true}, "cycle_lane": "shared",
+                    "lane_count": 1,
+                },
+                "edge_id": {"id": 1_234, "level": 2, "tile_id": 818_660, "value": 41_412_718_370_u64},
+            })
+        };
+        let response: Response = serde_json::from_value(serde_json::json!([
+            {"input_lat": 52.4, "input_lon": 4.9, "nodes": [], "edges": [edge(true, true), edge(false, false)]},
+            {"input_lat": 52.4, "input_lon": 4.9, "nodes": [{
+                "lat": 52.4, "lon": 4.9, "type": "gate",
+                "node_id": {"id": 7, "level": 2, "tile_id": 818_660, "value": 241_430_306_u64},
+                "administrative": {
+                    "iso_3166-1": "NL", "country": "Netherlands", "iso_3166-2": "NH", "state": "Noord-Holland",
+                    "time_zone_posix": "CET-1CEST,M3.5.0,M10.5.0/3",
+                },
+            }]},
+            {"input_lat": 0.0, "input_lon": 0.0, "nodes": null, "edges": null},
+        ]))
+        .unwrap();
+        let edges = response[0].edges();
+        assert_eq!(edges[0].way_id(), Some(42));
+        assert_eq!(edges[0].side_of_street, Some(SideOfStreet::Neither));
+        assert_eq!(
+            edges[0].edge_id,
+            crate::graph::GraphId::new(2, 818_660, 1_234)
+        );
+        assert_eq!(
+            serde_json::to_value(&edges[0]).unwrap()["edge_id"],
+            edge(true, true)["edge_id"]
+        );
+        assert_eq!(
+            edges[0].edge.as_ref().unwrap().cycle_lane,
+            Some(crate::graph::CycleLane::Shared)
+        );
+        let classification = edges[0].edge.as_ref().unwrap().classification.as_ref();
+        assert_eq!(
+            classification.and_then(|c| c.use_),
+            Some(crate::graph::EdgeUse::Road)
+        );
+        assert!(edges[0]
+            .edge
+            .as_ref()
+            .unwrap()
+            .other
+            .contains_key("lane_count"));
+        assert_eq!(
+            response[0].traversability(42, "car"),
+            Some(Traversability::Forward)
+        );
+        assert_eq!(
+            response[0].traversability(42, "pedestrian"),
+            Some(Traversability::Both)
+        );
+        assert_eq!(response[0].traversability(7, "car"), None);
+        assert_eq!(response[0].admin(), None);
+        let nodes = response[1].nodes.as_deref().unwrap();
+        assert!(nodes[0]
+            .type_
+            .is_some_and(crate::graph::NodeType::is_barrier));
+        assert_eq!(nodes[0].node_id.map(|id| id.index()), Some(7));
+        let admin = response[1].admin().unwrap();
+        assert_eq!(admin.country_code.as_deref(), Some("NL"));
+        assert_eq!(admin.state_text.as_deref(), Some("Noord-Holland"));
+        assert!(response[2].edges().is_empty());
+    }
+}
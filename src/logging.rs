@@ -0,0 +1,179 @@
+//! Hooks for logging request/response summaries without leaking user locations
+//!
+//! See [`crate::Valhalla::logger`] for how to install a hook on a client.
+use serde_json::Value;
+
+/// Keys whose values are coordinates in degrees
+const COORDINATE_KEYS: [&str; 12] = [
+    "lat",
+    "lon",
+    "input_lat",
+    "input_lon",
+    "correlated_lat",
+    "correlated_lon",
+    "display_lat",
+    "display_lon",
+    "min_lat",
+    "min_lon",
+    "max_lat",
+    "max_lon",
+];
+/// Keys whose values encode whole geometries, which cannot be rounded in a meaningful way
+const GEOMETRY_KEYS: [&str; 4] = [
+    "shape",
+    "encoded_polyline",
+    "exclude_polygons",
+    "coordinates",
+];
+
+/// How coordinates are treated before a summary is handed to the logging hook
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+    /// Coordinates are passed through unmodified
+    #[default]
+    None,
+    /// Coordinates are rounded to the given number of decimal places.
+    ///
+    /// Two decimal places correspond to roughly `1 km` of precision.
+    /// Encoded shapes and polygons are dropped, as they cannot be rounded.
+    Round(u8),
+    /// Coordinates, encoded shapes and polygons are replaced by `null`
+    Drop,
+}
+
+impl Redaction {
+    /// Applies the redaction to a JSON document in place
+    pub fn apply(self, value: &mut Value) {
+        if self == Self::None {
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if COORDINATE_KEYS.contains(&key.as_str()) {
+                        self.redact_coordinate(value);
+                    } else if GEOMETRY_KEYS.contains(&key.as_str()) {
+                        *value = Value::Null;
+                    } else {
+                        self.apply(value);
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|v| self.apply(v)),
+            _ => {}
+        }
+    }
+    fn redact_coordinate(self, value: &mut Value) {
+        match (self, value.as_f64()) {
+            (Self::Round(decimals), Some(coordinate)) => {
+                let factor = 10_f64.powi(i32::from(decimals));
+                *value = serde_json::json!((coordinate * factor).round() / factor);
+            }
+            (Self::None, _) => {}
+            _ => *value = Value::Null,
+        }
+    }
+}
+
+/// A summary of a request or response, handed to the logging hook
+#[derive(Debug, Clone)]
+pub enum Event<'a> {
+    /// A request is about to be sent
+    Request {
+        /// The action (endpoint) the request is sent to, e.g. `route`
+        action: &'a str,
+        /// The (redacted) request body
+        body: &'a Value,
+    },
+    /// A response has been received
+    Response {
+        /// The action (endpoint) the request was sent to, e.g. `route`
+        action: &'a str,
+        /// The HTTP status code of the response
+        status: u16,
+        /// The (redacted) response body
+        ///
+        /// [`Value::Null`] if the response was not valid JSON.
+        body: &'a Value,
+    },
+}
+
+/// A logging hook together with the [`Redaction`] applied before it is called
+#[derive(Clone)]
+pub(crate) struct Logger {
+    pub(crate) redaction: Redaction,
+    pub(crate) hook: std::sync::Arc<dyn Fn(&Event) + Send + Sync>,
+}
+
+impl Logger {
+    pub(crate) fn log_request(&self, action: &str, body: &impl serde::Serialize) {
+        let mut body = serde_json::to_value(body).unwrap_or_default();
+        self.redaction.apply(&mut body);
+        (self.hook)(&Event::Request {
+            action,
+            body: &body,
+        });
+    }
+    pub(crate) fn log_response(&self, action: &str, status: u16, text: &str) {
+        let mut body = serde_json::from_str(text).unwrap_or_default();
+        self.redaction.apply(&mut body);
+        (self.hook)(&Event::Response {
+            action,
+            status,
+            body: &body,
+        });
+    }
+}
+
+impl std::fmt::Debug for Logger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Logger")
+            .field("redaction", &self.redaction)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn redaction() {
+        let original = serde_json::json!({
+            "locations": [{"lat": 52.367_612, "lon": 4.904_123, "name": "home"}],
+            "shape": "abc",
+            "encoded_polyline": "abc",
+            "candidates": [{"input_lat": 52.367_612, "input_lon": 4.904_123, "edges": [{"correlated_lat": 52.367_7, "correlated_lon": 4.904_2}]}],
+            "units": "kilometers",
+        });
+
+        let mut unchanged = original.clone();
+        Redaction::None.apply(&mut unchanged);
+        assert_eq!(unchanged, original);
+
+        let mut rounded = original.clone();
+        Redaction::Round(2).apply(&mut rounded);
+        assert_eq!(
+            rounded,
+            serde_json::json!({
+                "locations": [{"lat": 52.37, "lon": 4.9, "name": "home"}],
+                "shape": null,
+                "encoded_polyline": null,
+                "candidates": [{"input_lat": 52.37, "input_lon": 4.9, "edges": [{"correlated_lat": 52.37, "correlated_lon": 4.9}]}],
+                "units": "kilometers",
+            })
+        );
+
+        let mut dropped = original;
+        Redaction::Drop.apply(&mut dropped);
+        assert_eq!(
+            dropped,
+            serde_json::json!({
+                "locations": [{"lat": null, "lon": null, "name": "home"}],
+                "shape": null,
+                "encoded_polyline": null,
+                "candidates": [{"input_lat": null, "input_lon": null, "edges": [{"correlated_lat": null, "correlated_lon": null}]}],
+                "units": "kilometers",
+            })
+        );
+    }
+}
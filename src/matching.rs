@@ -0,0 +1,304 @@
+//! Map matching of traces which are still being recorded, e.g. for live tracking dashboards
+//!
+//! A [`MatchingSession`] collects the points as they arrive and matches them in batches,
+//! extending one continuous matched path.
+//! A [`Fallback`] retries traces which cannot be matched with more lenient strategies,
+//! see [`Valhalla::trace_route_with_fallback`].
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::matching::MatchingSession;
+//! use valhalla_client::trace::{Location, Manifest, ShapeMatch};
+//!
+//! let valhalla = Valhalla::default();
+//! let mut session = MatchingSession::new(&valhalla, Manifest::builder().shape_match(ShapeMatch::MapSnap), 10);
+//! for i in 0..100 {
+//!   let point = Location::new(4.9041 + f64::from(i) * 1e-4, 52.3676);
+//!   if session.push(point).unwrap() {
+//!     println!("matched {} points so far", session.path().len());
+//!   }
+//! }
+//! session.flush().unwrap();
+//! ```
+use crate::shapes::ShapePoint;
+use crate::trace::{Location, Manifest, ShapeMatch};
+use crate::{Error, Valhalla};
+
+/// Matches a trace incrementally, see the [module documentation](self)
+///
+/// Each batch starts with the last point of the previous batch, so the matched path stays continuous.
+pub struct MatchingSession<'a> {
+    valhalla: &'a Valhalla,
+    manifest: Manifest,
+    batch_size: usize,
+    fallback: Option<Fallback>,
+    pending: Vec<Location>,
+    path: Vec<ShapePoint>,
+}
+
+impl<'a> MatchingSession<'a> {
+    /// Starts a session matching batches of `batch_size` points (at least `2`) via [`Valhalla::trace_route`]
+    ///
+    /// The `manifest` configures the matching, e.g. the [`Manifest::costing`]; its shape is replaced by each batch.
+    pub fn new(valhalla: &'a Valhalla, manifest: Manifest, batch_size: usize) -> Self {
+        Self {
+            valhalla,
+            manifest,
+            batch_size: batch_size.max(2),
+            fallback: None,
+            pending: Vec::new(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Retries batches which cannot be matched with the strategies of the `fallback`
+    ///
+    /// Default: batches which cannot be matched are dropped
+    pub fn fallback(mut self, fallback: Fallback) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+    /// Adds the next point of the trace, matching the pending points once a batch is complete
+    ///
+    /// Returns whether the matched path was extended.
+    /// If matching fails, the batch is dropped, except for its last point, so the session continues from there.
+    pub fn push(&mut self, point: Location) -> Result<bool, Error> {
+        self.pending.push(point);
+        if self.pending.len() < self.batch_size {
+            return Ok(false);
+        }
+        self.flush()
+    }
+
+    /// Matches the pending points without waiting for a complete batch, e.g. when the recording ends
+    ///
+    /// Returns whether the matched path was extended, which requires at least two pending points.
+    pub fn flush(&mut self) -> Result<bool, Error> {
+        if self.pending.len() < 2 {
+            return Ok(false);
+        }
+        let last = self.pending.last().cloned();
+        let batch = std::mem::replace(&mut self.pending, last.into_iter().collect());
+        let manifest = self.manifest.clone().shape(batch);
+        let trip = match &self.fallback {
+            Some(fallback) => {
+                self.valhalla
+                    .trace_route_with_fallback(manifest, fallback)?
+                    .0
+            }
+            None => self.valhalla.trace_route(manifest)?,
+        };
+        let shape = trip.legs.into_iter().flat_map(|leg| leg.shape);
+        // the first point is the match of the previous batch's last point
+        let skip = usize::from(!self.path.is_empty());
+        self.path.extend(shape.skip(skip));
+        Ok(true)
+    }
+
+    /// The matched path so far
+    pub fn path(&self) -> &[ShapePoint] {
+        &self.path
+    }
+}
+
+/// A way to match a trace after matching it as requested failed, see [`Fallback`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Matches via [`ShapeMatch::MapSnap`]
+    MapSnap,
+    /// Matches via [`ShapeMatch::WalkOrSnap`]
+    WalkOrSnap,
+    /// Gives up on the trace and routes from its first to its last point via [`Valhalla::route`]
+    RouteEndpoints,
+}
+
+/// The strategies to try, in order, while matching fails with one of the error codes
+///
+/// See [`Valhalla::trace_route_with_fallback`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fallback {
+    strategies: Vec<Strategy>,
+    error_codes: Vec<isize>,
+}
+
+impl Strategy {
+    /// How the trace is matched, `None` for [`Self::RouteEndpoints`]
+    pub(crate) fn shape_match(self) -> Option<ShapeMatch> {
+        match self {
+            Self::MapSnap => Some(ShapeMatch::MapSnap),
+            Self::WalkOrSnap => Some(ShapeMatch::WalkOrSnap),
+            Self::RouteEndpoints => None,
+        }
+    }
+}
+
+impl Default for Fallback {
+    fn default() -> Self {
+        Self {
+            strategies: vec![
+                Strategy::MapSnap,
+                Strategy::WalkOrSnap,
+                Strategy::RouteEndpoints,
+            ],
+            error_codes: vec![171, 442, 443, 444],
+        }
+    }
+}
+
+impl Fallback {
+    #[must_use]
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Sets the strategies to try, in order
+    ///
+    /// Default: [`Strategy::MapSnap`], [`Strategy::WalkOrSnap`], [`Strategy::RouteEndpoints`]
+    pub fn strategies(mut self, strategies: impl IntoIterator<Item = Strategy>) -> Self {
+        self.strategies = strategies.into_iter().collect();
+        self
+    }
+    /// Sets the [`crate::RemoteError::error_code`]s on which the next strategy is tried
+    ///
+    /// Default: `171` (no suitable edges near a location), `442` (no path found),
+    /// `443` (exact route match failed) and `444` (map matching failed)
+    pub fn error_codes(mut self, error_codes: impl IntoIterator<Item = isize>) -> Self {
+        self.error_codes = error_codes.into_iter().collect();
+        self
+    }
+    /// The strategies to try, in order
+    pub(crate) fn chain(&self) -> impl Iterator<Item = Strategy> + '_ {
+        self.strategies.iter().copied()
+    }
+    /// Whether the next strategy is tried after this error
+    pub(crate) fn applies_to(&self, error: &Error) -> bool {
+        matches!(error, Error::RemoteError(e) if self.error_codes.contains(&e.error_code))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::middleware::{Next, Request, Response};
+    /// A response with a trip along the `shape`
+    fn trip(shape: &[ShapePoint]) -> Response {
+        let summary = serde_json::json!({
+            "time": 0.0, "length": 0.0, "has_toll": false, "has_highway": false, "has_ferry": false,
+            "min_lat": 0.0, "min_lon": 0.0, "max_lat": 0.0, "max_lon": 0.0,
+        });
+        let trip = serde_json::json!({"trip": {
+            "status": 0, "status_message": "Found route between points",
+            "units": "kilometers", "language": "en-US", "locations": [],
+            "legs": [{"summary": summary, "maneuvers": [], "shape": crate::shapes::to_polyline5(shape)}],
+            "summary": summary,
+        }});
+        Response {
+            status: reqwest::StatusCode::OK,
+            headers: Default::default(),
+            body: trip.to_string(),
+        }
+    }
+    fn points(points: &serde_json::Value) -> Vec<ShapePoint> {
+        points
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| ShapePoint {
+                lon: p["lon"].as_f64().unwrap(),
+                lat: p["lat"].as_f64().unwrap(),
+            })
+            .collect()
+    }
+    #[test]
+    fn batches() {
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        // matches each point to itself
+        let valhalla = Valhalla::default().middleware(move |request: Request, _: Next| {
+            let shape = points(&request.body["shape"]);
+            recorded.lock().unwrap().push(shape.len());
+            Ok(trip(&shape))
+        });
+        let manifest = Manifest::builder().shape_format(crate::shapes::ShapeFormat::Polyline5);
+        let mut session = MatchingSession::new(&valhalla, manifest, 3);
+        let mut extended = Vec::new();
+        for i in 0..6 {
+            extended.push(session.push(Location::new(f64::from(i), 0.0)).unwrap());
+        }
+        assert_eq!(extended, [false, false, true, false, true, false]);
+        assert!(session.flush().unwrap());
+        assert!(
+            !session.flush().unwrap(),
+            "only the carried over point is pending"
+        );
+        assert_eq!(*requests.lock().unwrap(), [3, 3, 2]);
+        let lons: Vec<f64> = session.path().iter().map(|p| p.lon.round()).collect();
+        assert_eq!(lons, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+    #[test]
+    fn fallback() {
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        // matching always fails, routing always succeeds
+        let valhalla = Valhalla::default().middleware(move |request: Request, _: Next| {
+            let shape_match = request.body["shape_match"].as_str().map(String::from);
+            recorded
+                .lock()
+                .unwrap()
+                .push((request.action, shape_match.clone()));
+            if request.action == "route" {
+                return Ok(trip(&points(&request.body["locations"])));
+            }
+            Ok(Response {
+                status: reqwest::StatusCode::BAD_REQUEST,
+                headers: Default::default(),
+                body: r#"{"error_code":444,"error":"Map Match algorithm failed to find path","status_code":400,"status":"Bad Request"}"#.to_string(),
+            })
+        });
+        let manifest = Manifest::builder()
+            .shape([
+                Location::new(1.0, 0.0),
+                Location::new(2.0, 0.0),
+                Location::new(3.0, 0.0),
+            ])
+            .shape_format(crate::shapes::ShapeFormat::Polyline5);
+
+        let (trip, strategy) = valhalla
+            .trace_route_with_fallback(manifest.clone(), &Fallback::default())
+            .unwrap();
+        assert_eq!(strategy, Some(Strategy::RouteEndpoints));
+        let lons: Vec<f64> = trip.legs[0].shape.iter().map(|p| p.lon.round()).collect();
+        assert_eq!(lons, [1.0, 3.0]);
+        let tried = std::mem::take(&mut *requests.lock().unwrap());
+        // walk_or_snap is the default, so retrying it would repeat the first request
+        assert_eq!(
+            tried,
+            [
+                ("trace_route", None),
+                ("trace_route", Some("map_snap".to_string())),
+                ("route", None),
+            ]
+        );
+
+        let map_snap = manifest.clone().shape_match(ShapeMatch::MapSnap);
+        valhalla
+            .trace_route_with_fallback(map_snap, &Fallback::default())
+            .unwrap();
+        let tried = std::mem::take(&mut *requests.lock().unwrap());
+        assert_eq!(
+            tried,
+            [
+                ("trace_route", Some("map_snap".to_string())),
+                ("trace_route", Some("walk_or_snap".to_string())),
+                ("route", None),
+            ]
+        );
+
+        let unrelated = Fallback::builder().error_codes([171]);
+        let error = valhalla
+            .trace_route_with_fallback(manifest, &unrelated)
+            .unwrap_err();
+        assert!(matches!(error, Error::RemoteError(e) if e.error_code == 444));
+        assert_eq!(requests.lock().unwrap().len(), 1, "no strategy was tried");
+    }
+}
@@ -1,5 +1,5 @@
 use crate::costing;
-use crate::shapes::ShapeFormat;
+use crate::shapes::{Shape, ShapeFormat, ShapePoint, ShapeRepresentation};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -10,6 +10,7 @@ pub struct Manifest {
     pub(crate) sources: Vec<Location>,
     #[serde(flatten)]
     costing: costing::Costing,
+    units: Option<super::Units>,
     id: Option<String>,
     matrix_locations: Option<u32>,
     date_time: Option<DateTime>,
@@ -42,6 +43,17 @@ impl Manifest {
         self.costing = costing;
         self
     }
+    /// Sets the distance units for output.
+    ///
+    /// Possible unit types are
+    /// - miles via [`super::Units::Imperial`] and
+    /// - kilometers via [`super::Units::Metric`].
+    ///
+    /// Default: [`super::Units::Metric`]
+    pub fn units(mut self, units: super::Units) -> Self {
+        self.units = Some(units);
+        self
+    }
     /// Name your route request.
     ///
     /// If id is specified, the naming will be sent through to the response.
@@ -93,10 +105,25 @@ impl Manifest {
         self
     }
     /// Specifies the [`ShapeFormat`] for the path shape of each connection.
+    ///
+    /// The shapes are only returned in `verbose` mode, see [`VerboseSourceToTarget::shape`].
     pub fn shape_format(mut self, shape_format: ShapeFormat) -> Self {
         self.shape_format = Some(shape_format);
         self
     }
+    /// Predicts how expensive the matrix is, see [`crate::estimate`]
+    pub fn estimate(&self) -> crate::estimate::Estimate {
+        let points = |locations: &[Location]| -> Vec<_> {
+            locations
+                .iter()
+                .map(|l| crate::shapes::ShapePoint {
+                    lon: f64::from(l.lon),
+                    lat: f64::from(l.lat),
+                })
+                .collect()
+        };
+        crate::estimate::Estimate::matrix(&points(&self.sources), &points(&self.targets))
+    }
 }
 
 /// The local date and time at the location
@@ -138,23 +165,42 @@ enum MatrixDateTimeType {
     SpecifiedArrival,
 }
 
+#[serde_with::skip_serializing_none]
 #[derive(Serialize, Default, Clone, Copy, PartialEq, Debug)]
 pub struct Location {
     lat: f32,
     lon: f32,
     #[serde(serialize_with = "super::serialize_naive_date_time_opt")]
-    #[serde(skip_serializing_if = "Option::is_none")]
     date_time: Option<chrono::NaiveDateTime>,
+    search_cutoff: Option<f32>,
+    street_side_tolerance: Option<f32>,
+    street_side_max_distance: Option<f32>,
 }
 impl From<super::Coordinate> for Location {
     fn from((longitude, latitude): super::Coordinate) -> Self {
         Self {
             lat: latitude,
             lon: longitude,
-            date_time: None,
+            ..Default::default()
         }
     }
 }
+#[cfg(feature = "geojson")]
+impl Location {
+    /// Creates locations from the `Point` features of a GeoJSON `FeatureCollection`
+    ///
+    /// Fails if a feature is not a `Point`, so that the locations stay aligned with the features.
+    pub fn from_geojson(
+        collection: &geojson::FeatureCollection,
+    ) -> Result<Vec<Self>, geojson::Error> {
+        collection
+            .features
+            .iter()
+            .map(|feature| super::coordinate_of(feature).map(Self::from))
+            .collect()
+    }
+}
+
 impl Location {
     /// Creates a new location from a longitude/latitude
     pub fn new(longitude: f32, latitude: f32) -> Self {
@@ -185,6 +231,32 @@ impl Location {
         self.date_time = Some(date_time);
         self
     }
+    /// Cutoff in meters at which we will assume the input is too far away from civilisation to be
+    /// worth correlating to the nearest graph elements.
+    ///
+    /// Default: `35 km`
+    pub fn search_cutoff(mut self, search_cutoff: f32) -> Self {
+        self.search_cutoff = Some(search_cutoff);
+        self
+    }
+    /// Sets the tolerance in meters for street side changes.
+    ///
+    /// If the input coordinate is less than this tolerance away from the edge centerline,
+    /// the side of street is set to none.
+    ///
+    /// Default: `5 meters`
+    pub fn street_side_tolerance(mut self, street_side_tolerance: f32) -> Self {
+        self.street_side_tolerance = Some(street_side_tolerance);
+        self
+    }
+    /// The max distance in meters that the input coordinates can be from the edge centerline for
+    /// them to be used for determining the side of street.
+    ///
+    /// Default: `1000 meters`
+    pub fn street_side_max_distance(mut self, street_side_max_distance: f32) -> Self {
+        self.street_side_max_distance = Some(street_side_max_distance);
+        self
+    }
 }
 
 /// [`Location`] which was configured in the input
@@ -216,24 +288,33 @@ impl From<VerboseLocation> for Location {
             lat: value.lat,
             lon: value.lon,
             date_time: value.date_time,
+            ..Default::default()
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
-pub enum Response {
+#[serde(bound(
+    serialize = "S: ShapeRepresentation",
+    deserialize = "S: ShapeRepresentation"
+))]
+pub enum Response<S = Vec<ShapePoint>> {
     /// Returned in `verbose` mode.
     ///
     /// Verbosity can be set via [`Manifest::verbose_output`]
-    Verbose(VerboseResponse),
+    Verbose(VerboseResponse<S>),
     /// Returned in non-`verbose` mode.
     ///
     /// Verbosity can be set via [`Manifest::verbose_output`]
     Concise(ConciseResponse),
 }
-#[derive(Deserialize, Debug, Clone)]
-pub struct VerboseResponse {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(
+    serialize = "S: ShapeRepresentation",
+    deserialize = "S: ShapeRepresentation"
+))]
+pub struct VerboseResponse<S = Vec<ShapePoint>> {
     /// Name of the route request.
     ///
     /// If id is specified via [`Manifest::id`] the naming will be sent through to the response.
@@ -259,9 +340,224 @@ pub struct VerboseResponse {
     /// - time and distance from the first source location to all target locations,
     /// - time and distance from the second source location to all target locations,
     /// - etc.
-    pub sources_to_targets: Vec<Vec<VerboseSourceToTarget>>,
+    pub sources_to_targets: Vec<Vec<VerboseSourceToTarget<S>>>,
+}
+/// One connection of a matrix, see [`Response::to_long_format`]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct LongFormatRow {
+    /// Index into the sources
+    pub source_index: usize,
+    /// Index into the targets
+    pub target_index: usize,
+    /// Estimated travel time in seconds
+    pub seconds: u32,
+    /// Distance in meters, regardless of the requested [`super::Units`]
+    pub meters: f64,
+}
+
+impl Response<Shape> {
+    /// Decodes the shapes of all connections, given the [`ShapeFormat`] they were requested in
+    pub fn decode(self, format: ShapeFormat) -> Response {
+        match self {
+            Self::Verbose(r) => Response::Verbose(VerboseResponse {
+                id: r.id,
+                algorithm: r.algorithm,
+                units: r.units,
+                warnings: r.warnings,
+                sources: r.sources,
+                targets: r.targets,
+                sources_to_targets: r
+                    .sources_to_targets
+                    .into_iter()
+                    .map(|row| row.into_iter().map(|c| c.decode(format)).collect())
+                    .collect(),
+            }),
+            Self::Concise(r) => Response::Concise(r),
+        }
+    }
+}
+
+impl<S> Response<S> {
+    /// Flattens the matrix into one row per connection, e.g. for dataframes or database tables
+    ///
+    /// Rows are ordered by source and then by target.
+    /// Connections to unreachable targets are skipped.
+    pub fn to_long_format(&self) -> Box<dyn Iterator<Item = LongFormatRow> + '_> {
+        match self {
+            Self::Verbose(r) => {
+                Box::new(
+                    r.sources_to_targets
+                        .iter()
+                        .flatten()
+                        .map(|c| LongFormatRow {
+                            source_index: c.from_index,
+                            target_index: c.to_index,
+                            seconds: c.time,
+                            meters: r.units.to_meters(f64::from(c.distance)),
+                        }),
+                )
+            }
+            Self::Concise(r) => {
+                let matrix = &r.sources_to_targets;
+                Box::new(
+                    matrix
+                        .durations
+                        .iter()
+                        .zip(&matrix.distances)
+                        .enumerate()
+                        .flat_map(move |(source_index, (durations, distances))| {
+                            durations.iter().zip(distances).enumerate().filter_map(
+                                move |(target_index, (&seconds, &distance))| {
+                                    Some(LongFormatRow {
+                                        source_index,
+                                        target_index,
+                                        seconds: seconds?,
+                                        meters: r.units.to_meters(f64::from(distance?)),
+                                    })
+                                },
+                            )
+                        }),
+                )
+            }
+        }
+    }
+
+    /// Selects `n` sources as facilities, minimizing the total travel time from each target to its nearest facility
+    ///
+    /// The sources are the candidate facilities and the targets the demand points, e.g. for
+    /// territory planning. Uses a greedy selection refined by k-medoids swaps, so the result is a
+    /// good but not necessarily optimal selection.
+    /// Returns the indices of the selected sources in ascending order.
+    ///
+    /// Each round of swaps evaluates every pair of a selected and an unselected source against
+    /// all targets, i.e. takes O(n·s·t) for `s` sources and `t` targets, which is O(n²·k) for `k`
+    /// locations in a square matrix.
+    /// Large matrices should be reduced to the plausible candidates beforehand.
+    pub fn select_facilities(&self, n: usize) -> Vec<usize> {
+        let mut times: Vec<Vec<Option<u64>>> = Vec::new();
+        for row in self.to_long_format() {
+            if times.len() <= row.source_index {
+                times.resize(row.source_index + 1, Vec::new());
+            }
+            let targets = &mut times[row.source_index];
+            if targets.len() <= row.target_index {
+                targets.resize(row.target_index + 1, None);
+            }
+            targets[row.target_index] = Some(u64::from(row.seconds));
+        }
+        let target_count = times.iter().map(Vec::len).max().unwrap_or(0);
+        // unreachable targets are penalized, but still preferred to be reachable from any facility
+        let time = |source: usize, target: usize| {
+            times[source]
+                .get(target)
+                .copied()
+                .flatten()
+                .unwrap_or(u64::from(u32::MAX))
+        };
+        let cost = |selected: &[usize]| -> u64 {
+            (0..target_count)
+                .map(|target| {
+                    selected
+                        .iter()
+                        .map(|&source| time(source, target))
+                        .min()
+                        .unwrap_or(u64::MAX)
+                })
+                .fold(0, u64::saturating_add)
+        };
+
+        let mut selected: Vec<usize> = Vec::new();
+        while selected.len() < n.min(times.len()) {
+            let best = (0..times.len())
+                .filter(|source| !selected.contains(source))
+                .min_by_key(|&source| cost(&[selected.as_slice(), &[source]].concat()));
+            selected.extend(best);
+        }
+        let mut best_cost = cost(&selected);
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 0..selected.len() {
+                for candidate in 0..times.len() {
+                    if selected.contains(&candidate) {
+                        continue;
+                    }
+                    let mut swapped = selected.clone();
+                    swapped[i] = candidate;
+                    let swapped_cost = cost(&swapped);
+                    if swapped_cost < best_cost {
+                        (selected, best_cost, improved) = (swapped, swapped_cost, true);
+                    }
+                }
+            }
+        }
+        selected.sort_unstable();
+        selected
+    }
+
+    /// Writes the [long format](Self::to_long_format) as CSV, including a header row
+    ///
+    /// The columns are `source_index`, `target_index`, `seconds` and `meters`.
+    #[cfg(feature = "csv")]
+    pub fn write_csv(&self, writer: impl std::io::Write) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        for row in self.to_long_format() {
+            writer.serialize(row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
 }
-#[derive(Deserialize, Debug, Clone)]
+
+impl VerboseResponse {
+    /// The sources together with where they were snapped to
+    ///
+    /// The snapped coordinates are the first points of the connection shapes, so they are only
+    /// available if a shape was requested via [`Manifest::shape_format`].
+    pub fn snapped_sources(&self) -> Vec<Option<crate::shapes::SnappedLocation>> {
+        self.sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| {
+                let row = self.sources_to_targets.get(i)?;
+                let snapped = row.iter().find_map(|c| c.shape.as_ref()?.first())?;
+                Some(snapped_location(source, snapped))
+            })
+            .collect()
+    }
+    /// The targets together with where they were snapped to
+    ///
+    /// The snapped coordinates are the last points of the connection shapes, so they are only
+    /// available if a shape was requested via [`Manifest::shape_format`].
+    pub fn snapped_targets(&self) -> Vec<Option<crate::shapes::SnappedLocation>> {
+        self.targets
+            .iter()
+            .enumerate()
+            .map(|(j, target)| {
+                let snapped = self
+                    .sources_to_targets
+                    .iter()
+                    .find_map(|row| row.get(j)?.shape.as_ref()?.last())?;
+                Some(snapped_location(target, snapped))
+            })
+            .collect()
+    }
+}
+
+fn snapped_location(
+    requested: &VerboseLocation,
+    snapped: &crate::shapes::ShapePoint,
+) -> crate::shapes::SnappedLocation {
+    crate::shapes::SnappedLocation {
+        requested: crate::shapes::ShapePoint {
+            lon: f64::from(requested.lon),
+            lat: f64::from(requested.lat),
+        },
+        snapped: snapped.clone(),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConciseResponse {
     /// Name of the route request.
     ///
@@ -287,7 +583,8 @@ pub struct ConciseResponse {
     pub sources_to_targets: ConciseSourceToTargets,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConciseSourceToTargets {
     /// The computed time between each set of points.
     ///
@@ -295,18 +592,28 @@ pub struct ConciseSourceToTargets {
     /// - the first element of the time-distance array for one_to_many,
     /// - the last element in a many_to_one, and
     /// - the first and last elements of a many_to_many
-    pub durations: Vec<Vec<u32>>,
+    ///
+    /// `None` if the target is unreachable from the source.
+    #[serde_as(as = "Vec<Vec<Option<crate::lenient::LenientInteger>>>")]
+    pub durations: Vec<Vec<Option<u32>>>,
     /// The computed distance between each set of points.
     ///
     /// Distance will always be `0.00` for
     /// - the first element of the time-distance array for one_to_many,
     /// - the last element in a many_to_one, and
     /// - the first and last elements of a many_to_many.
-    pub distances: Vec<Vec<f32>>,
+    ///
+    /// `None` if the target is unreachable from the source.
+    pub distances: Vec<Vec<Option<f32>>>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct VerboseSourceToTarget {
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(
+    serialize = "S: ShapeRepresentation",
+    deserialize = "S: ShapeRepresentation"
+))]
+pub struct VerboseSourceToTarget<S = Vec<ShapePoint>> {
     /// The computed distance between each set of points.
     ///
     /// Distance will always be `0.00` for
@@ -320,10 +627,13 @@ pub struct VerboseSourceToTarget {
     /// - the first element of the time-distance array for one_to_many,
     /// - the last element in a many_to_one, and
     /// - the first and last elements of a many_to_many
+    #[serde_as(as = "crate::lenient::LenientInteger")]
     pub time: u32,
     /// The destination index into the locations array
+    #[serde_as(as = "crate::lenient::LenientInteger")]
     pub from_index: usize,
     /// The origin index into the locations array
+    #[serde_as(as = "crate::lenient::LenientInteger")]
     pub to_index: usize,
     /// Timezone of when a user will arrive at this location or has to depart from the start point.
     ///
@@ -357,4 +667,137 @@ pub struct VerboseSourceToTarget {
     ///
     /// Example: `"2024-11-07T15:26"`
     pub date_time: Option<chrono::NaiveDateTime>,
+    /// The path shape of this connection
+    ///
+    /// Only present if a [`ShapeFormat`] other than [`ShapeFormat::NoShape`] was requested via
+    /// [`Manifest::shape_format`].
+    #[serde(
+        default,
+        serialize_with = "crate::shapes::serialize_shape_opt",
+        deserialize_with = "crate::shapes::deserialize_shape_opt"
+    )]
+    pub shape: Option<S>,
+}
+
+impl VerboseSourceToTarget<Shape> {
+    /// Decodes the shape, given the [`ShapeFormat`] it was requested in
+    fn decode(self, format: ShapeFormat) -> VerboseSourceToTarget {
+        VerboseSourceToTarget {
+            distance: self.distance,
+            time: self.time,
+            from_index: self.from_index,
+            to_index: self.to_index,
+            time_zone_name: self.time_zone_name,
+            time_zone_offset: self.time_zone_offset,
+            date_time: self.date_time,
+            shape: self.shape.map(|shape| shape.decode_as(format)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn serialize_units() {
+        let manifest = Manifest::builder().units(crate::Units::Imperial);
+        assert_eq!(serde_json::to_value(&manifest).unwrap()["units"], "miles");
+    }
+    #[test]
+    fn snapped_locations() {
+        let response: Response = serde_json::from_value(serde_json::json!({
+            "algorithm": "costmatrix", "units": "kilometers",
+            "sources": [{"lat": 52.0, "lon": 4.0}],
+            "targets": [{"lat": 52.5, "lon": 4.5}, {"lat": 53.0, "lon": 5.0}],
+            "sources_to_targets": [[
+                {"distance": 1.0, "time": 60, "from_index": 0, "to_index": 0,
+                 "shape": {"type": "LineString", "coordinates": [[4.0, 52.001], [4.5, 52.5]]}},
+                {"distance": 2.0, "time": 120, "from_index": 0, "to_index": 1},
+            ]],
+        }))
+        .unwrap();
+        let Response::Verbose(response) = response else {
+            panic!("expected a verbose response");
+        };
+        let sources = response.snapped_sources();
+        assert!((sources[0].as_ref().unwrap().snap_distance() - 111.2).abs() < 0.1);
+        let targets = response.snapped_targets();
+        assert_eq!(targets[0].as_ref().unwrap().snap_distance(), 0.0);
+        assert_eq!(targets[1], None, "no shape");
+    }
+    #[test]
+    fn polyline5_shapes() {
+        let response: Response<Shape> = serde_json::from_value(serde_json::json!({
+            "algorithm": "costmatrix", "units": "kilometers",
+            "sources": [{"lat": 38.5, "lon": -120.2}],
+            "targets": [{"lat": 40.7, "lon": -120.95}],
+            "sources_to_targets": [[
+                {"distance": 1.0, "time": 60, "from_index": 0, "to_index": 0, "shape": "_p~iF~ps|U_ulLnnqC"},
+            ]],
+        }))
+        .unwrap();
+        let Response::Verbose(response) = response.decode(ShapeFormat::Polyline5) else {
+            panic!("expected a verbose response");
+        };
+        let shape = response.sources_to_targets[0][0].shape.as_ref().unwrap();
+        assert_eq!((shape[1].lon, shape[1].lat), (-120.95, 40.7));
+    }
+    #[test]
+    fn long_format() {
+        let response: Response = serde_json::from_value(serde_json::json!({
+            "algorithm": "costmatrix", "units": "kilometers",
+            "sources_to_targets": {"durations": [[0, 60], [70, 0]], "distances": [[0.0, 1.5], [1.75, 0.0]]},
+        }))
+        .unwrap();
+        let rows: Vec<_> = response.to_long_format().collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(
+            rows[2],
+            LongFormatRow {
+                source_index: 1,
+                target_index: 0,
+                seconds: 70,
+                meters: 1_750.0
+            }
+        );
+        #[cfg(feature = "csv")]
+        {
+            let mut csv = Vec::new();
+            response.write_csv(&mut csv).unwrap();
+            let csv = String::from_utf8(csv).unwrap();
+            assert!(csv.starts_with("source_index,target_index,seconds,meters\n0,0,0,0.0\n"));
+        }
+    }
+
+    #[test]
+    fn select_facilities() {
+        // sources 0 and 2 each serve half of the targets, source 1 serves all of them mediocrely
+        let response: Response = serde_json::from_value(serde_json::json!({
+            "algorithm": "costmatrix", "units": "kilometers",
+            "sources_to_targets": {
+                "durations": [[10, 10, 900, 900], [300, 300, 300, 300], [900, 900, 10, 10]],
+                "distances": vec![[0.0; 4]; 3],
+            },
+        }))
+        .unwrap();
+        assert_eq!(response.select_facilities(1), [1]);
+        assert_eq!(response.select_facilities(2), [0, 2]);
+        assert_eq!(response.select_facilities(5), [0, 1, 2]);
+        assert!(response.select_facilities(0).is_empty());
+    }
+    #[test]
+    fn unreachable_cells() {
+        let response: Response = serde_json::from_value(serde_json::json!({
+            "algorithm": "costmatrix", "units": "kilometers",
+            "sources_to_targets": {"durations": [[0, null], [70, 0]], "distances": [[0.0, null], [1.75, 0.0]]},
+        }))
+        .unwrap();
+        let Response::Concise(concise) = &response else {
+            panic!("expected a concise response");
+        };
+        assert_eq!(concise.sources_to_targets.durations[0], [Some(0), None]);
+        assert_eq!(response.to_long_format().count(), 3);
+        // source 0 cannot reach target 1, so source 1 is the better facility
+        assert_eq!(response.select_facilities(1), [1]);
+    }
 }
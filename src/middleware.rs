@@ -0,0 +1,129 @@
+//! Composable layers around the requests sent to valhalla, e.g. for authentication, caching or chaos testing
+//!
+//! Layers are installed via [`crate::Valhalla::middleware`] and see every request, similar to
+//! tower's `Layer`s, but blocking.
+//! Each layer may modify the [`Request`], answer it itself, or pass it on to the [`Next`] layer.
+//! The first installed layer is the outermost one.
+//!
+//! # Example:
+//! ```rust
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::middleware::{Next, Request};
+//!
+//! let valhalla = Valhalla::default().middleware(|mut request: Request, next: Next| {
+//!     request.headers.insert("authorization", "Bearer secret".parse().unwrap());
+//!     next.run(request)
+//! });
+//! ```
+use crate::Error;
+use reqwest::header::HeaderMap;
+use std::sync::Arc;
+
+/// A request before it is sent to valhalla
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// The name valhalla uses for the endpoint, e.g. `route`
+    pub action: &'static str,
+    pub url: url::Url,
+    pub headers: HeaderMap,
+    /// The JSON body, with the [`crate::Valhalla::defaults`] already filled in
+    pub body: serde_json::Value,
+}
+
+/// A response as received from valhalla (or produced by a layer)
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: reqwest::StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+/// A layer around the requests sent to valhalla, see the [module documentation](self)
+///
+/// Implemented for closures taking the [`Request`] and the [`Next`] layer.
+pub trait Middleware: Send + Sync {
+    /// Handles a request, usually by passing it on via [`Next::run`]
+    fn handle(&self, request: Request, next: Next<'_>) -> Result<Response, Error>;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(Request, Next<'_>) -> Result<Response, Error> + Send + Sync,
+{
+    fn handle(&self, request: Request, next: Next<'_>) -> Result<Response, Error> {
+        self(request, next)
+    }
+}
+
+/// The layers after the current one, ending with the actual HTTP request
+pub struct Next<'a> {
+    layers: &'a [Arc<dyn Middleware>],
+    transport: &'a dyn Fn(Request) -> Result<Response, Error>,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        layers: &'a [Arc<dyn Middleware>],
+        transport: &'a dyn Fn(Request) -> Result<Response, Error>,
+    ) -> Self {
+        Self { layers, transport }
+    }
+
+    /// Passes the request on to the next layer
+    pub fn run(self, request: Request) -> Result<Response, Error> {
+        match self.layers.split_first() {
+            Some((layer, layers)) => layer.handle(request, Next::new(layers, self.transport)),
+            None => (self.transport)(request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+    #[test]
+    fn layers() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let record = |name: &'static str| {
+            let order = order.clone();
+            Arc::new(move |request: Request, next: Next| {
+                order.lock().unwrap().push(name);
+                next.run(request)
+            }) as Arc<dyn Middleware>
+        };
+        let cache: Arc<dyn Middleware> = Arc::new(|request: Request, next: Next| {
+            if request.action == "status" {
+                return Ok(Response {
+                    status: reqwest::StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: "cached".to_string(),
+                });
+            }
+            next.run(request)
+        });
+        let layers = [record("outer"), record("inner"), cache];
+        let transport = |request: Request| {
+            Ok(Response {
+                status: reqwest::StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: request.body.to_string(),
+            })
+        };
+        let request = |action| Request {
+            action,
+            url: url::Url::parse("http://localhost:8002/").unwrap(),
+            headers: HeaderMap::new(),
+            body: serde_json::json!({"action": action}),
+        };
+        let response = Next::new(&layers, &transport)
+            .run(request("route"))
+            .unwrap();
+        assert_eq!(response.body, r#"{"action":"route"}"#);
+        let response = Next::new(&layers, &transport)
+            .run(request("status"))
+            .unwrap();
+        assert_eq!(response.body, "cached");
+        assert_eq!(*order.lock().unwrap(), ["outer", "inner", "outer", "inner"]);
+    }
+}
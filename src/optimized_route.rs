@@ -0,0 +1,158 @@
+//! Routes visiting locations in the fastest order, e.g. to plan the stops of a delivery tour
+//!
+//! The first and last location stay in place, while the locations in between are reordered.
+//! See <https://valhalla.github.io/valhalla/api/optimized/api-reference/> for details
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::optimized_route::Manifest;
+//! use valhalla_client::route::Location;
+//!
+//! let depot = Location::new(4.9041, 52.3676);
+//! let manifest = Manifest::builder().locations([
+//!   depot.clone(),
+//!   Location::new(4.4777, 51.9244).name("Rotterdam"),
+//!   Location::new(5.1214, 52.0907).name("Utrecht"),
+//!   Location::new(4.3007, 52.0705).name("The Hague"),
+//!   depot,
+//! ]);
+//! let route = Valhalla::default().optimized_route(manifest).unwrap();
+//! println!("visit in the order {:?}, taking {:?}", route.order, route.trip.total_time());
+//! ```
+use crate::costing;
+use crate::route::{DirectionsType, Location, Trip};
+use serde::Serialize;
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Default, Debug, Clone)]
+pub struct Manifest {
+    #[serde(flatten)]
+    costing: Option<costing::Costing>,
+    locations: Vec<Location>,
+    units: Option<crate::Units>,
+    id: Option<String>,
+    language: Option<String>,
+    directions_type: Option<DirectionsType>,
+    shape_format: Option<crate::shapes::ShapeFormat>,
+}
+
+impl Manifest {
+    #[must_use]
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Configures the costing model, see [`crate::route::Manifest::costing`]
+    ///
+    /// **Note:** multimodal costing is not supported for optimized routes.
+    ///
+    /// Default: [`costing::Costing::Auto`]
+    pub fn costing(mut self, costing: costing::Costing) -> Self {
+        self.costing = Some(costing);
+        self
+    }
+    /// Sets the locations to visit, starting at the first and ending at the last one
+    ///
+    /// Minimum number of locations: 2
+    ///
+    /// The locations in between are visited in the order minimizing the total time.
+    /// For a round trip, pass the start location again as last location.
+    pub fn locations(mut self, locations: impl IntoIterator<Item = Location>) -> Self {
+        self.locations = locations.into_iter().collect();
+        debug_assert!(self.locations.len() >= 2);
+        self
+    }
+    /// Sets the distance units for output, see [`crate::route::Manifest::units`]
+    ///
+    /// Default: [`crate::Units::Metric`]
+    pub fn units(mut self, units: crate::Units) -> Self {
+        self.units = Some(units);
+        self
+    }
+    /// Name your optimized route request.
+    ///
+    /// If id is specified, the naming will be sent through to the response.
+    pub fn id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+    /// The language of the narration instructions, see [`crate::route::Manifest::language`]
+    ///
+    /// Default: `en-US` (United States-based English)
+    pub fn language(mut self, language: impl ToString) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+    /// Sets the directions type, see [`crate::route::Manifest::directions_type`]
+    ///
+    /// Default: [`DirectionsType::Instructions`]
+    pub fn directions_type(mut self, directions_type: DirectionsType) -> Self {
+        self.directions_type = Some(directions_type);
+        self
+    }
+    /// Specifies the [`crate::shapes::ShapeFormat`] for the path shape of each leg
+    ///
+    /// Default: [`crate::shapes::ShapeFormat::Polyline6`]
+    pub fn shape_format(mut self, shape_format: crate::shapes::ShapeFormat) -> Self {
+        self.shape_format = Some(shape_format);
+        self
+    }
+}
+
+/// A route visiting the locations of a [`Manifest`] in the optimized order
+#[derive(Debug, Clone)]
+pub struct OptimizedRoute {
+    /// The indices into [`Manifest::locations`] in the order they are visited
+    pub order: Vec<usize>,
+    /// The route, with its locations and legs in the order they are visited
+    pub trip: Trip,
+}
+
+impl From<Trip> for OptimizedRoute {
+    fn from(trip: Trip) -> Self {
+        let order = trip
+            .locations
+            .iter()
+            .enumerate()
+            .map(|(i, location)| location.original_index().unwrap_or(i))
+            .collect();
+        Self { order, trip }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn order() {
+        let manifest = Manifest::builder()
+            .locations([Location::new(4.9, 52.4), Location::new(5.1, 52.1)])
+            .units(crate::Units::Imperial);
+        assert_eq!(
+            serde_json::to_value(&manifest).unwrap(),
+            serde_json::json!({
+                "locations": [{"lat": 52.4_f32, "lon": 4.9_f32}, {"lat": 52.1_f32, "lon": 5.1_f32}],
+                "units": "miles",
+            })
+        );
+
+        let summary = serde_json::json!({
+            "time": 90.0, "length": 1.5, "has_toll": false, "has_highway": false, "has_ferry": false,
+            "min_lat": 52.0, "min_lon": 4.0, "max_lat": 53.0, "max_lon": 5.0,
+        });
+        let trip: Trip = serde_json::from_value(serde_json::json!({
+            "status": 0, "status_message": "Found route between points",
+            "units": "kilometers", "language": "en-US",
+            "locations": [
+                {"lat": 52.0, "lon": 4.0, "original_index": 0},
+                {"lat": 52.5, "lon": 4.5, "original_index": 2},
+                {"lat": 52.2, "lon": 4.2, "original_index": 1},
+                {"lat": 53.0, "lon": 5.0, "original_index": 3},
+            ],
+            "legs": [],
+            "summary": summary,
+        }))
+        .unwrap();
+        assert_eq!(OptimizedRoute::from(trip).order, [0, 2, 1, 3]);
+    }
+}
@@ -0,0 +1,49 @@
+//! Bounded concurrency for helpers issuing many requests with the blocking client
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Applies `f` to all `items` on at most `concurrency` threads, keeping the order of `items`
+pub(crate) fn map<T: Sync, R: Send>(
+    items: &[T],
+    concurrency: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let workers = concurrency.clamp(1, items.len().max(1));
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else { break };
+                        done.push((i, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn keeps_order() {
+        let items: Vec<u64> = (0..100).collect();
+        for concurrency in [0, 1, 7, 200] {
+            assert_eq!(
+                map(&items, concurrency, |i| i * 2),
+                (0..200).step_by(2).collect::<Vec<_>>()
+            );
+        }
+        assert!(map(&[] as &[u64], 4, |i| *i).is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+//! Replays a trip in (accelerated) real time, e.g. to demo or test navigation UIs
+//!
+//! The position along each maneuver advances proportionally to the distance travelled,
+//! so that every maneuver takes the time estimated by valhalla.
+//!
+//! # Example:
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::playback::Playback;
+//! use valhalla_client::route::{Location, Manifest};
+//!
+//! let manifest = Manifest::builder()
+//!   .locations([Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)]);
+//! let trip = Valhalla::default().route(manifest).unwrap();
+//! // replay ten times faster than real time, with one update per second
+//! Playback::new(&trip, 10.0).play(Duration::from_secs(1), |update| {
+//!   println!("{:?}: {:?} heading {:?}", update.trip_time, update.position, update.heading);
+//! });
+//! ```
+use crate::route::Trip;
+use crate::shapes::ShapePoint;
+use std::time::Duration;
+
+/// A shape point together with the time the trip passes it
+#[derive(Debug, Clone)]
+struct Keyframe {
+    /// Seconds since the start of the trip
+    time: f64,
+    point: ShapePoint,
+    heading: Option<f64>,
+    leg: usize,
+    shape_index: usize,
+}
+
+/// The simulated state of a trip at one point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackUpdate {
+    /// The time since the start of the playback
+    pub elapsed: Duration,
+    /// The time since the start of the trip, i.e. [`Self::elapsed`] times the speed multiplier
+    pub trip_time: Duration,
+    /// The interpolated position
+    pub position: ShapePoint,
+    /// The bearing in degrees, clockwise from north
+    ///
+    /// `None` if the trip does not move at all.
+    pub heading: Option<f64>,
+    /// The index of the current leg
+    pub leg: usize,
+    /// The index of the last shape point passed in the current leg
+    pub shape_index: usize,
+}
+
+/// A trip prepared for playback, see the [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct Playback {
+    keyframes: Vec<Keyframe>,
+    speed_multiplier: f64,
+}
+
+impl Playback {
+    /// Prepares the playback of a trip
+    ///
+    /// With a `speed_multiplier` of `10.0`, a trip taking ten minutes is replayed within one minute.
+    /// Multipliers below `0.001`, including zero, negative and `NaN` ones, are raised to `0.001`.
+    pub fn new(trip: &Trip, speed_multiplier: f64) -> Self {
+        let mut keyframes = Vec::new();
+        let mut start = 0.0;
+        for (leg_index, leg) in trip.legs.iter().enumerate() {
+            for (m, maneuver) in leg.maneuvers.iter().enumerate() {
+                let end = maneuver
+                    .end_shape_index
+                    .min(leg.shape.len().saturating_sub(1));
+                let Some(points) = leg.shape.get(maneuver.begin_shape_index..=end) else {
+                    continue;
+                };
+                let distances: Vec<f64> = std::iter::once(0.0)
+                    .chain(points.windows(2).map(|w| w[0].distance_to(&w[1])))
+                    .scan(0.0, |total, d| {
+                        *total += d;
+                        Some(*total)
+                    })
+                    .collect();
+                let total = distances.last().copied().unwrap_or_default();
+                // the first point of a maneuver is the last point of the previous one
+                let skip = usize::from(m > 0);
+                for (i, point) in points.iter().enumerate().skip(skip) {
+                    let fraction = match total {
+                        0.0 => i as f64 / (points.len() - 1).max(1) as f64,
+                        total => distances[i] / total,
+                    };
+                    keyframes.push(Keyframe {
+                        time: start + fraction * maneuver.time,
+                        point: point.clone(),
+                        heading: None,
+                        leg: leg_index,
+                        shape_index: maneuver.begin_shape_index + i,
+                    });
+                }
+                start += maneuver.time;
+            }
+        }
+        let points: Vec<_> = keyframes.iter().map(|k| k.point.clone()).collect();
+        for (i, keyframe) in keyframes.iter_mut().enumerate() {
+            keyframe.heading = crate::shapes::bearing_at(&points, i);
+        }
+        Self {
+            keyframes,
+            speed_multiplier: speed_multiplier.max(0.001),
+        }
+    }
+
+    /// How long the playback takes
+    pub fn duration(&self) -> Duration {
+        let trip_time = self.keyframes.last().map_or(0.0, |k| k.time);
+        Duration::try_from_secs_f64(trip_time / self.speed_multiplier).unwrap_or(Duration::MAX)
+    }
+
+    /// The state of the trip after `elapsed` time of playback
+    ///
+    /// Returns `None` if the trip has no shape or the playback is over.
+    pub fn at(&self, elapsed: Duration) -> Option<PlaybackUpdate> {
+        if elapsed > self.duration() {
+            return None;
+        }
+        let time = elapsed.as_secs_f64() * self.speed_multiplier;
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let from = &self.keyframes[next.checked_sub(1)?];
+        let position = match self.keyframes.get(next) {
+            Some(to) if to.time > from.time => {
+                let fraction = (time - from.time) / (to.time - from.time);
+                let delta_lon = crate::shapes::wrap_longitude(to.point.lon - from.point.lon);
+                let lon = from.point.lon + fraction * delta_lon;
+                ShapePoint {
+                    lon: crate::shapes::wrap_longitude(lon),
+                    lat: from.point.lat + fraction * (to.point.lat - from.point.lat),
+                }
+            }
+            _ => from.point.clone(),
+        };
+        Some(PlaybackUpdate {
+            elapsed,
+            trip_time: Duration::from_secs_f64(time),
+            position,
+            heading: from.heading,
+            leg: from.leg,
+            shape_index: from.shape_index,
+        })
+    }
+
+    /// The states of the trip every `interval` of playback, ending with the arrival
+    pub fn updates(&self, interval: Duration) -> impl Iterator<Item = PlaybackUpdate> + '_ {
+        let interval = interval.max(Duration::from_millis(1));
+        let duration = self.duration();
+        let steps = (duration.as_secs_f64() / interval.as_secs_f64()).ceil() as u32;
+        (0..=steps).filter_map(move |step| self.at((interval * step).min(duration)))
+    }
+
+    /// Replays the trip in real time, calling `f` every `interval`
+    ///
+    /// Blocks until the playback is over.
+    pub fn play(&self, interval: Duration, mut f: impl FnMut(&PlaybackUpdate)) {
+        let start = std::time::Instant::now();
+        for update in self.updates(interval) {
+            if let Some(wait) = update.elapsed.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+            f(&update);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn playback() {
+        let summary = serde_json::json!({
+            "time": 60.0, "length": 0.4,
+            "has_toll": false, "has_highway": false, "has_ferry": false,
+            "min_lat": 0.0, "min_lon": 0.0, "max_lat": 0.004, "max_lon": 0.001,
+        });
+        let trip: Trip = serde_json::from_value(serde_json::json!({
+            "status": 0, "status_message": "Found route between points",
+            "units": "kilometers", "language": "en-US", "locations": [],
+            "legs": [{
+                "summary": summary,
+                "maneuvers": [
+                    {"type": 1, "instruction": "Drive north.", "time": 40.0, "length": 0.0,
+                     "begin_shape_index": 0, "end_shape_index": 2, "travel_mode": "drive"},
+                    {"type": 10, "instruction": "Turn right.", "time": 20.0, "length": 0.0,
+                     "begin_shape_index": 2, "end_shape_index": 3, "travel_mode": "drive"},
+                    {"type": 4, "instruction": "You have arrived.", "time": 0.0, "length": 0.0,
+                     "begin_shape_index": 3, "end_shape_index": 3, "travel_mode": "drive"},
+                ],
+                "shape": {"type": "LineString", "coordinates": [[0.0, 0.0], [0.0, 0.001], [0.0, 0.004], [0.001, 0.004]]},
+            }],
+            "summary": summary,
+        }))
+        .unwrap();
+        let playback = Playback::new(&trip, 2.0);
+        assert_eq!(playback.duration(), Duration::from_secs(30));
+
+        let start = playback.at(Duration::ZERO).unwrap();
+        assert_eq!(start.position, ShapePoint { lon: 0.0, lat: 0.0 });
+        assert_eq!(start.heading, Some(0.0));
+
+        // 20s into the trip, half of the distance of the first maneuver is covered
+        let update = playback.at(Duration::from_secs(10)).unwrap();
+        assert_eq!(update.trip_time, Duration::from_secs(20));
+        assert!((update.position.lat - 0.002).abs() < 1e-9);
+        assert_eq!(update.shape_index, 1);
+
+        let update = playback.at(Duration::from_secs(25)).unwrap();
+        assert!((update.position.lon - 0.0005).abs() < 1e-9);
+        assert!((update.heading.unwrap() - 90.0).abs() < 0.01);
+
+        let updates: Vec<_> = playback.updates(Duration::from_secs(7)).collect();
+        assert_eq!(updates.len(), 6);
+        assert_eq!(
+            updates.last().unwrap().position,
+            ShapePoint {
+                lon: 0.001,
+                lat: 0.004
+            }
+        );
+        assert_eq!(playback.at(Duration::from_secs(31)), None);
+
+        for speed_multiplier in [0.0, -1.0, f64::NAN] {
+            let playback = Playback::new(&trip, speed_multiplier);
+            assert_eq!(playback.duration(), Duration::from_secs(60_000));
+            let update = playback.at(Duration::from_secs(20_000)).unwrap();
+            assert_eq!(update.trip_time, Duration::from_secs(20));
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! A pool of clients for multi-threaded servers
+//!
+//! Each thread checks out its own [`Valhalla`] client, instead of all threads sharing one connection
+//! or constructing a client per request.
+//! Clients are created lazily and returned to the pool once the [`PooledClient`] is dropped.
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//! use valhalla_client::pool::Pool;
+//! use valhalla_client::route::{Location, Manifest};
+//!
+//! let pool = Pool::new(4, || Valhalla::default().app_identifier("my-server"));
+//! std::thread::scope(|scope| {
+//!   for _ in 0..16 {
+//!     scope.spawn(|| {
+//!       let manifest = Manifest::builder()
+//!         .locations([Location::new(4.9041, 52.3676), Location::new(5.1214, 52.0907)]);
+//!       pool.get().route(manifest)
+//!     });
+//!   }
+//! });
+//! ```
+use crate::Valhalla;
+use std::sync::{Condvar, Mutex};
+
+#[derive(Default)]
+struct State {
+    idle: Vec<Valhalla>,
+    /// The number of clients created so far, both idle and checked out
+    size: usize,
+}
+
+/// A pool of at most `max_size` clients, see the [module documentation](self)
+pub struct Pool {
+    factory: Box<dyn Fn() -> Valhalla + Send + Sync>,
+    max_size: usize,
+    state: Mutex<State>,
+    returned: Condvar,
+}
+
+/// The current utilisation of a [`Pool`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    /// The maximum number of clients
+    pub max_size: usize,
+    /// The number of clients created so far
+    pub size: usize,
+    /// The number of clients which are created, but not checked out
+    pub available: usize,
+}
+
+impl Pool {
+    /// Creates a pool which creates up to `max_size` clients via `factory`
+    pub fn new(max_size: usize, factory: impl Fn() -> Valhalla + Send + Sync + 'static) -> Self {
+        Self {
+            factory: Box::new(factory),
+            max_size: max_size.max(1),
+            state: Mutex::default(),
+            returned: Condvar::new(),
+        }
+    }
+
+    /// Checks out a client, blocking until one is available if all are checked out
+    pub fn get(&self) -> PooledClient<'_> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = self
+            .returned
+            .wait_while(state, |state| {
+                state.idle.is_empty() && state.size >= self.max_size
+            })
+            .unwrap_or_else(|e| e.into_inner());
+        self.check_out(state)
+    }
+
+    /// Checks out a client, or returns `None` if all are checked out
+    pub fn try_get(&self) -> Option<PooledClient<'_>> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.idle.is_empty() && state.size >= self.max_size {
+            return None;
+        }
+        Some(self.check_out(state))
+    }
+
+    /// The current utilisation of the pool
+    pub fn status(&self) -> Status {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        Status {
+            max_size: self.max_size,
+            size: state.size,
+            available: state.idle.len(),
+        }
+    }
+
+    fn check_out(&self, mut state: std::sync::MutexGuard<'_, State>) -> PooledClient<'_> {
+        let client = match state.idle.pop() {
+            Some(client) => client,
+            None => {
+                state.size += 1;
+                // clients are created outside the lock, so that slow factories do not block other threads
+                drop(state);
+                let reservation = Reservation { pool: self };
+                let client = (self.factory)();
+                std::mem::forget(reservation);
+                client
+            }
+        };
+        PooledClient {
+            pool: self,
+            client: Some(client),
+        }
+    }
+}
+
+/// Releases the capacity reserved for a client if the factory panics
+struct Reservation<'a> {
+    pool: &'a Pool,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        let mut state = self.pool.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.size -= 1;
+        self.pool.returned.notify_one();
+    }
+}
+
+/// A client checked out of a [`Pool`], which is returned to the pool when dropped
+pub struct PooledClient<'a> {
+    pool: &'a Pool,
+    client: Option<Valhalla>,
+}
+
+impl std::ops::Deref for PooledClient<'_> {
+    type Target = Valhalla;
+    fn deref(&self) -> &Valhalla {
+        self.client
+            .as_ref()
+            .expect("the client is only taken on drop")
+    }
+}
+
+impl Drop for PooledClient<'_> {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            let mut state = self.pool.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.idle.push(client);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    #[test]
+    fn checks_out_clients() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let counter = created.clone();
+        let pool = Pool::new(2, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Valhalla::default()
+        });
+        let first = pool.get();
+        let second = pool.try_get().unwrap();
+        assert!(pool.try_get().is_none());
+        assert_eq!(
+            pool.status(),
+            Status {
+                max_size: 2,
+                size: 2,
+                available: 0
+            }
+        );
+        drop(first);
+        assert_eq!(pool.status().available, 1);
+
+        // a waiting thread is woken up once a client is returned
+        std::thread::scope(|scope| {
+            let waiting = scope.spawn(|| {
+                let _third = pool.get();
+                let _fourth = pool.get();
+            });
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            drop(second);
+            waiting.join().unwrap();
+        });
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.status().available, 2);
+    }
+    #[test]
+    fn panicking_factory() {
+        let fail = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let flag = fail.clone();
+        let pool = Pool::new(1, move || {
+            assert!(!flag.load(Ordering::SeqCst), "the factory failed");
+            Valhalla::default()
+        });
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| pool.get()));
+        assert!(panicked.is_err());
+        assert_eq!(pool.status().size, 0);
+
+        // the capacity is not lost
+        fail.store(false, Ordering::SeqCst);
+        assert!(pool.try_get().is_some());
+    }
+}
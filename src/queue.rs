@@ -0,0 +1,128 @@
+//! Prioritisation of requests sharing one client
+//!
+//! See [`crate::Valhalla::request_queue`] for how to enable the queue.
+use std::sync::{Condvar, Mutex};
+
+/// How urgent a request is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Priority {
+    /// A user is waiting for the response, e.g. a single route
+    Interactive,
+    /// A large request which may be delayed, e.g. a matrix
+    Background,
+}
+
+impl Priority {
+    /// The priority requests to an action are queued with
+    pub(crate) fn of(action: &str) -> Self {
+        match action {
+            "sources_to_targets" => Self::Background,
+            _ => Self::Interactive,
+        }
+    }
+    fn index(self) -> usize {
+        match self {
+            Self::Interactive => 0,
+            Self::Background => 1,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: [usize; 2],
+    waiting_interactive: usize,
+}
+
+/// Limits the number of requests in flight per [`Priority`]
+///
+/// Queued interactive requests are started before queued background requests.
+/// Requests which are already in flight are not interrupted.
+#[derive(Debug)]
+pub(crate) struct Queue {
+    limits: [usize; 2],
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Queue {
+    pub(crate) fn new(interactive: usize, background: usize) -> Self {
+        Self {
+            limits: [interactive.max(1), background.max(1)],
+            state: Mutex::default(),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a request of the given priority may be sent
+    ///
+    /// The request counts as in flight until the returned guard is dropped.
+    pub(crate) fn acquire(&self, priority: Priority) -> Slot<'_> {
+        let i = priority.index();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if priority == Priority::Interactive {
+            state.waiting_interactive += 1;
+        }
+        let mut state = self
+            .changed
+            .wait_while(state, |state| {
+                state.in_flight[i] >= self.limits[i]
+                    || (priority == Priority::Background && state.waiting_interactive > 0)
+            })
+            .unwrap_or_else(|e| e.into_inner());
+        if priority == Priority::Interactive {
+            state.waiting_interactive -= 1;
+        }
+        state.in_flight[i] += 1;
+        Slot {
+            queue: self,
+            priority,
+        }
+    }
+}
+
+/// A request in flight, see [`Queue::acquire`]
+pub(crate) struct Slot<'a> {
+    queue: &'a Queue,
+    priority: Priority,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.in_flight[self.priority.index()] -= 1;
+        self.queue.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[test]
+    fn limits_concurrency() {
+        let queue = Queue::new(2, 1);
+        let in_flight = [AtomicUsize::new(0), AtomicUsize::new(0)];
+        let max_in_flight = [AtomicUsize::new(0), AtomicUsize::new(0)];
+        std::thread::scope(|scope| {
+            for n in 0..12 {
+                let priority = if n % 3 == 0 {
+                    Priority::Background
+                } else {
+                    Priority::Interactive
+                };
+                let (queue, in_flight, max_in_flight) = (&queue, &in_flight, &max_in_flight);
+                scope.spawn(move || {
+                    let i = priority.index();
+                    let _slot = queue.acquire(priority);
+                    let now = in_flight[i].fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight[i].fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                    in_flight[i].fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert!(max_in_flight[0].load(Ordering::SeqCst) <= 2);
+        assert_eq!(max_in_flight[1].load(Ordering::SeqCst), 1);
+    }
+}
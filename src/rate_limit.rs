@@ -0,0 +1,101 @@
+//! Rate limits announced by the valhalla instance (or a proxy in front of it)
+//!
+//! See [`crate::Valhalla::rate_limit_observer`] for how to observe the remaining quota.
+use reqwest::header::HeaderMap;
+use std::time::Duration;
+
+/// Unix timestamps are far larger than any plausible number of seconds until a reset
+const MIN_UNIX_TIMESTAMP: u64 = 1_000_000_000;
+
+/// A hook installed via [`crate::Valhalla::rate_limit_observer`]
+pub(crate) type Observer = std::sync::Arc<dyn Fn(&RateLimit) + Send + Sync>;
+
+/// The rate limit state as reported by the `X-RateLimit-*` and `Retry-After` headers
+///
+/// All fields are `None` if the corresponding header is missing or could not be parsed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RateLimit {
+    /// The number of requests allowed in the current window (`X-RateLimit-Limit`)
+    pub limit: Option<u64>,
+    /// The number of requests remaining in the current window (`X-RateLimit-Remaining`)
+    pub remaining: Option<u64>,
+    /// The time until the current window resets (`X-RateLimit-Reset`)
+    ///
+    /// Both the number of seconds and a unix timestamp are accepted as header value.
+    pub reset: Option<Duration>,
+    /// The time to wait before sending the next request (`Retry-After`)
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimit {
+    /// Parses the rate limit headers of a response
+    ///
+    /// Returns `None` if the response does not carry any of them.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let now = chrono::Utc::now().timestamp();
+        let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+        let number = |name: &str| header(name)?.parse::<u64>().ok();
+        let until = |timestamp: i64| Duration::from_secs((timestamp - now).max(0) as u64);
+
+        let reset = number("x-ratelimit-reset").map(|reset| match reset {
+            timestamp if timestamp >= MIN_UNIX_TIMESTAMP => until(timestamp as i64),
+            seconds => Duration::from_secs(seconds),
+        });
+        let retry_after = header("retry-after").and_then(|value| match value.parse::<u64>() {
+            Ok(seconds) => Some(Duration::from_secs(seconds)),
+            Err(_) => chrono::DateTime::parse_from_rfc2822(value)
+                .ok()
+                .map(|date| until(date.timestamp())),
+        });
+        let rate_limit = Self {
+            limit: number("x-ratelimit-limit"),
+            remaining: number("x-ratelimit-remaining"),
+            reset,
+            retry_after,
+        };
+        (rate_limit != Self::default()).then_some(rate_limit)
+    }
+
+    /// How long to wait before the next request to stay within the limit
+    ///
+    /// This is the [`Self::retry_after`] delay, or the [`Self::reset`] delay if no requests [`Self::remaining`].
+    pub fn backoff(&self) -> Option<Duration> {
+        self.retry_after.or(match self.remaining {
+            Some(0) => self.reset,
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn from_headers() {
+        assert_eq!(RateLimit::from_headers(&HeaderMap::new()), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-RateLimit-Limit", "60".parse().unwrap());
+        headers.insert("X-RateLimit-Remaining", "0".parse().unwrap());
+        headers.insert("X-RateLimit-Reset", "30".parse().unwrap());
+        let rate_limit = RateLimit::from_headers(&headers).unwrap();
+        assert_eq!(rate_limit.limit, Some(60));
+        assert_eq!(rate_limit.remaining, Some(0));
+        assert_eq!(rate_limit.reset, Some(Duration::from_secs(30)));
+        assert_eq!(rate_limit.backoff(), Some(Duration::from_secs(30)));
+
+        headers.insert("Retry-After", "120".parse().unwrap());
+        let rate_limit = RateLimit::from_headers(&headers).unwrap();
+        assert_eq!(rate_limit.backoff(), Some(Duration::from_secs(120)));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Retry-After",
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        let rate_limit = RateLimit::from_headers(&headers).unwrap();
+        assert_eq!(rate_limit.retry_after, Some(Duration::ZERO), "in the past");
+        assert_eq!(rate_limit.remaining, None);
+    }
+}
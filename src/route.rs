@@ -1,13 +1,47 @@
 use crate::costing;
+use crate::shapes::{Shape, ShapeFormat, ShapePoint, ShapeRepresentation};
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug, Clone)]
-pub(crate) struct Response {
-    pub(crate) trip: Trip,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(
+    serialize = "S: ShapeRepresentation",
+    deserialize = "S: ShapeRepresentation + Default"
+))]
+pub(crate) struct Response<S = Vec<ShapePoint>> {
+    trip: Trip<S>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct Trip {
+impl<S> From<Trip<S>> for Response<S> {
+    fn from(trip: Trip<S>) -> Self {
+        Self { trip }
+    }
+}
+
+impl<S> Response<S> {
+    /// Extracts the trip, copying its units into all summaries
+    pub(crate) fn into_trip(self) -> Trip<S> {
+        let mut trip = self.trip;
+        trip.summary.units = trip.units;
+        for leg in &mut trip.legs {
+            leg.summary.units = trip.units;
+        }
+        trip
+    }
+}
+
+/// A trip as returned by valhalla
+///
+/// `S` is the representation of the leg shapes, see [`Leg::shape`].
+///
+/// Serializing and deserializing a trip restores it, e.g. to persist computed trips.
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(
+    serialize = "S: ShapeRepresentation",
+    deserialize = "S: ShapeRepresentation + Default"
+))]
+pub struct Trip<S = Vec<ShapePoint>> {
+    #[serde_as(as = "crate::lenient::LenientInteger")]
     pub status: i32,
     pub status_message: String,
     pub units: super::Units,
@@ -15,9 +49,437 @@ pub struct Trip {
     pub locations: Vec<Location>,
     pub warnings: Option<Vec<String>>,
     pub id: Option<String>,
-    pub legs: Vec<Leg>,
+    pub legs: Vec<Leg<S>>,
     pub summary: Summary,
 }
+
+impl Trip<Shape> {
+    /// Decodes the shapes of all legs, given the [`ShapeFormat`] they were requested in
+    ///
+    /// Useful for trips returned by [`crate::Valhalla::route_encoded`], see [`Shape::decode_as`].
+    pub fn decode(self, format: ShapeFormat) -> Trip {
+        let legs = self
+            .legs
+            .into_iter()
+            .map(|leg| Leg {
+                shape: leg.shape.decode_as(format),
+                summary: leg.summary,
+                maneuvers: leg.maneuvers,
+                level_changes: leg.level_changes,
+            })
+            .collect();
+        Trip {
+            status: self.status,
+            status_message: self.status_message,
+            units: self.units,
+            language: self.language,
+            locations: self.locations,
+            warnings: self.warnings,
+            id: self.id,
+            legs,
+            summary: self.summary,
+        }
+    }
+}
+
+impl<S> Trip<S> {
+    /// Total length of the trip in meters, regardless of the requested [`super::Units`]
+    pub fn total_distance(&self) -> f64 {
+        self.summary.distance()
+    }
+    /// Estimated total travel time of the trip
+    ///
+    /// Does not include time spent waiting at locations, see [`Self::total_time_with_waiting`].
+    pub fn total_time(&self) -> std::time::Duration {
+        self.summary.duration()
+    }
+    /// Estimated total time of the trip, including the time spent waiting at locations
+    ///
+    /// The waiting time at each location can be set via [`Location::waiting`].
+    pub fn total_time_with_waiting(&self) -> std::time::Duration {
+        let waiting: u32 = self.locations.iter().filter_map(|l| l.waiting).sum();
+        self.total_time() + std::time::Duration::from_secs(u64::from(waiting))
+    }
+    /// Length of each leg in meters, regardless of the requested [`super::Units`]
+    pub fn leg_distances(&self) -> impl Iterator<Item = f64> + '_ {
+        self.legs.iter().map(|leg| leg.summary.distance())
+    }
+    /// Estimated travel time of each leg
+    pub fn leg_times(&self) -> impl Iterator<Item = std::time::Duration> + '_ {
+        self.legs.iter().map(|leg| leg.summary.duration())
+    }
+    /// The maneuvers affected by time-dependent restrictions, together with the index of their leg
+    ///
+    /// Relevant for e.g. [`crate::costing::Costing::Truck`], where trucks may be banned at certain times.
+    /// See [`Summary::has_time_restrictions`] to check whether there are any.
+    pub fn restricted_maneuvers(&self) -> impl Iterator<Item = (usize, &Maneuver)> + '_ {
+        self.legs.iter().enumerate().flat_map(|(i, leg)| {
+            leg.maneuvers
+                .iter()
+                .filter(|m| m.has_time_restrictions == Some(true))
+                .map(move |m| (i, m))
+        })
+    }
+    /// The [`Self::status`] and the legs of the trip interpreted as outcome of the request
+    pub fn outcome(&self) -> TripStatus {
+        if self.status != 0 {
+            return TripStatus::Other {
+                code: self.status,
+                message: self.status_message.clone(),
+            };
+        }
+        let trivial_legs: Vec<usize> = self
+            .legs
+            .iter()
+            .enumerate()
+            .filter(|(_, leg)| leg.summary.length == 0.0)
+            .map(|(i, _)| i)
+            .collect();
+        if trivial_legs.is_empty() {
+            TripStatus::Found
+        } else {
+            TripStatus::Partial { trivial_legs }
+        }
+    }
+}
+
+/// The outcome of a routing request, see [`Trip::outcome`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TripStatus {
+    /// A route was found between all locations
+    Found,
+    /// A route was found, but some legs have zero length
+    ///
+    /// This happens if consecutive locations snap to the same point, e.g. duplicated stops.
+    Partial {
+        /// The indices of the legs with zero length
+        trivial_legs: Vec<usize>,
+    },
+    /// Any status other than `0` (success), together with its [`Trip::status_message`]
+    Other { code: i32, message: String },
+}
+
+impl Trip {
+    /// The trip as a public transport journey, i.e. alternating walks and rides with transfers in between
+    ///
+    /// Intended for [`costing::Costing::Multimodal`] trips, hiding the individual maneuvers.
+    /// Consecutive walking maneuvers are merged into one [`JourneySegment::Walk`], even across legs.
+    pub fn journey(&self) -> Vec<JourneySegment<'_>> {
+        let mut journey = Vec::new();
+        let mut last_arrival: Option<chrono::NaiveDateTime> = None;
+        let maneuvers = self.legs.iter().flat_map(|leg| &leg.maneuvers);
+        for maneuver in maneuvers {
+            let ride = maneuver.transit_info.as_ref().and_then(|info| {
+                Some(TransitRide {
+                    info,
+                    board: info.transit_stops.first()?,
+                    alight: info.transit_stops.last()?,
+                })
+            });
+            let Some(ride) = ride else {
+                let distance = self.units.to_meters(maneuver.length);
+                let time = std::time::Duration::from_secs_f64(maneuver.time.max(0.0));
+                match journey.last_mut() {
+                    Some(JourneySegment::Walk {
+                        distance: d,
+                        time: t,
+                    }) => {
+                        *d += distance;
+                        *t += time;
+                    }
+                    _ if distance > 0.0 || !time.is_zero() => {
+                        journey.push(JourneySegment::Walk { distance, time })
+                    }
+                    _ => {}
+                }
+                continue;
+            };
+            if let Some(arrival) = last_arrival {
+                journey.push(JourneySegment::Transfer {
+                    duration: ride.board.departure_date_time - arrival,
+                });
+            }
+            last_arrival = Some(ride.alight.arrival_date_time);
+            journey.push(JourneySegment::Ride(ride));
+        }
+        journey
+    }
+    /// A corridor of `width` meters around the shapes of all legs, see [`crate::shapes::corridor`]
+    pub fn corridor(&self, width: f64) -> geo_types::MultiPolygon<f64> {
+        self.legs
+            .iter()
+            .flat_map(|leg| crate::shapes::corridor(&leg.shape, width))
+            .collect()
+    }
+    /// The progress of a traveler at a shape point of a leg, see [`Leg::shape`]
+    pub fn progress_at(&self, leg: usize, shape_index: usize) -> TripProgress<'_> {
+        TripProgress {
+            trip: self,
+            leg,
+            shape_index,
+        }
+    }
+    /// The progress of a traveler at the shape point closest to the `position`
+    ///
+    /// Returns `None` if the trip has no shape.
+    pub fn progress_near(&self, position: super::Coordinate) -> Option<TripProgress<'_>> {
+        let (leg, shape_index) = self.closest_shape_point(position)?;
+        Some(self.progress_at(leg, shape_index))
+    }
+    /// The break locations of the trip together with where they were snapped to, in order
+    ///
+    /// Valhalla returns the requested coordinates in [`Self::locations`].
+    /// The snapped coordinates are the first and last points of the leg shapes.
+    /// Empty if the trip has no shape, or if the break locations don't match the legs.
+    pub fn snapped_locations(&self) -> Vec<crate::shapes::SnappedLocation> {
+        let breaks: Vec<_> = self.locations.iter().filter(|l| l.is_break()).collect();
+        let mut endpoints: Vec<_> = self.legs.iter().filter_map(|l| l.shape.first()).collect();
+        endpoints.extend(self.legs.last().and_then(|l| l.shape.last()));
+        if endpoints.len() != breaks.len() || endpoints.len() != self.legs.len() + 1 {
+            return Vec::new();
+        }
+        breaks
+            .into_iter()
+            .zip(endpoints)
+            .map(|(location, snapped)| crate::shapes::SnappedLocation {
+                requested: ShapePoint {
+                    lon: f64::from(location.longitude),
+                    lat: f64::from(location.latitude),
+                },
+                snapped: snapped.clone(),
+            })
+            .collect()
+    }
+    /// The heading of the shape segment at the shape point closest to `location`
+    fn heading_near(&self, location: &Location) -> Option<f64> {
+        let (leg, i) = self.closest_shape_point((location.longitude, location.latitude))?;
+        crate::shapes::bearing_at(&self.legs[leg].shape, i)
+    }
+    /// The leg and shape index of the shape point closest to `(lon, lat)`
+    fn closest_shape_point(&self, (lon, lat): super::Coordinate) -> Option<(usize, usize)> {
+        let (lon, lat) = (f64::from(lon), f64::from(lat));
+        // an equirectangular approximation suffices to compare the nearby points
+        let distance = |p: &ShapePoint| {
+            let x = crate::shapes::wrap_longitude(p.lon - lon) * lat.to_radians().cos();
+            let y = p.lat - lat;
+            x * x + y * y
+        };
+        self.legs
+            .iter()
+            .enumerate()
+            .flat_map(|(l, leg)| leg.shape.iter().enumerate().map(move |(i, p)| (l, i, p)))
+            .min_by(|(_, _, a), (_, _, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(leg, i, _)| (leg, i))
+    }
+}
+
+/// The position of a traveler along a [`Trip`], for building turn-by-turn navigation
+///
+/// Created via [`Trip::progress_at`] or [`Trip::progress_near`].
+#[derive(Debug, Clone, Copy)]
+pub struct TripProgress<'a> {
+    trip: &'a Trip,
+    leg: usize,
+    shape_index: usize,
+}
+
+impl<'a> TripProgress<'a> {
+    /// Index of the leg the traveler is on
+    pub fn leg(&self) -> usize {
+        self.leg
+    }
+    /// Index into the [`Leg::shape`] of the last shape point the traveler passed
+    pub fn shape_index(&self) -> usize {
+        self.shape_index
+    }
+    /// The maneuvers which are still ahead, in order, together with the index of their leg
+    pub fn upcoming_maneuvers(&self) -> impl Iterator<Item = (usize, &'a Maneuver)> + 'a {
+        let (leg, shape_index) = (self.leg, self.shape_index);
+        self.trip
+            .legs
+            .iter()
+            .enumerate()
+            .skip(leg)
+            .flat_map(|(l, leg)| leg.maneuvers.iter().map(move |m| (l, m)))
+            .filter(move |(l, m)| *l > leg || m.begin_shape_index > shape_index)
+    }
+    /// The next maneuver the traveler has to make
+    pub fn next_maneuver(&self) -> Option<&'a Maneuver> {
+        self.upcoming_maneuvers().next().map(|(_, m)| m)
+    }
+    /// The instruction of the [`Self::next_maneuver`]
+    ///
+    /// Example: "Turn right onto North Prince Street"
+    pub fn next_instruction(&self) -> Option<&'a str> {
+        self.next_maneuver().map(|m| m.instruction.as_str())
+    }
+    /// The maneuver following the [`Self::next_maneuver`], for "then turn left" style hints
+    pub fn then_maneuver(&self) -> Option<&'a Maneuver> {
+        self.upcoming_maneuvers().nth(1).map(|(_, m)| m)
+    }
+    /// Distance in meters along the shape until the [`Self::next_maneuver`]
+    pub fn distance_to_next_maneuver(&self) -> Option<f64> {
+        let (leg, maneuver) = self.upcoming_maneuvers().next()?;
+        let segment_lengths = |shape: &[ShapePoint]| {
+            shape
+                .windows(2)
+                .map(|s| s[0].distance_to(&s[1]))
+                .sum::<f64>()
+        };
+        let legs = &self.trip.legs;
+        let current = legs.get(self.leg)?;
+        if leg == self.leg {
+            let shape = current
+                .shape
+                .get(self.shape_index..=maneuver.begin_shape_index)?;
+            return Some(segment_lengths(shape));
+        }
+        let rest_of_current_leg = segment_lengths(current.shape.get(self.shape_index..)?);
+        let legs_between: f64 = legs[self.leg + 1..leg]
+            .iter()
+            .map(|l| segment_lengths(&l.shape))
+            .sum();
+        let start_of_leg = segment_lengths(legs[leg].shape.get(..=maneuver.begin_shape_index)?);
+        Some(rest_of_current_leg + legs_between + start_of_leg)
+    }
+}
+
+/// A part of a public transport journey, see [`Trip::journey`]
+#[derive(Debug, Clone, Copy)]
+pub enum JourneySegment<'a> {
+    /// Walking (or cycling), e.g. to the first stop or between stops
+    Walk {
+        /// Length in meters
+        distance: f64,
+        time: std::time::Duration,
+    },
+    /// Riding one public transport line
+    Ride(TransitRide<'a>),
+    /// Changing between two rides
+    Transfer {
+        /// From arriving with one ride to departing with the next, including walking in between
+        duration: chrono::TimeDelta,
+    },
+}
+
+/// Riding one public transport line from boarding to alighting
+#[derive(Debug, Clone, Copy)]
+pub struct TransitRide<'a> {
+    pub info: &'a TransitInfo,
+    /// The stop the ride begins at
+    pub board: &'a TransitStop,
+    /// The stop the ride ends at
+    pub alight: &'a TransitStop,
+}
+
+impl<'a> TransitRide<'a> {
+    /// The name of the line, i.e. [`TransitInfo::short_name`] or else [`TransitInfo::long_name`]
+    pub fn line(&self) -> &'a str {
+        if self.info.short_name.is_empty() {
+            &self.info.long_name
+        } else {
+            &self.info.short_name
+        }
+    }
+    /// The destination shown on the vehicle
+    pub fn headsign(&self) -> &'a str {
+        &self.info.headsign
+    }
+    /// The number of stops travelled, i.e. excluding the boarding stop
+    pub fn stop_count(&self) -> usize {
+        self.info.transit_stops.len().saturating_sub(1)
+    }
+}
+
+/// A [`Trip`] together with its departure and arrival time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(
+    serialize = "S: ShapeRepresentation",
+    deserialize = "S: ShapeRepresentation + Default"
+))]
+pub struct ScheduledTrip<S = Vec<ShapePoint>> {
+    /// When the trip departs from the first location
+    pub departure: chrono::NaiveDateTime,
+    /// When the trip arrives at the last location
+    pub arrival: chrono::NaiveDateTime,
+    pub trip: Trip<S>,
+}
+
+impl<S> ScheduledTrip<S> {
+    /// Schedules a trip departing at a given time, including waiting at locations
+    pub fn departing_at(trip: Trip<S>, departure: chrono::NaiveDateTime) -> Self {
+        Self {
+            departure,
+            arrival: departure + Self::duration(&trip),
+            trip,
+        }
+    }
+    /// Schedules a trip arriving at a given time, including waiting at locations
+    pub fn arriving_at(trip: Trip<S>, arrival: chrono::NaiveDateTime) -> Self {
+        Self {
+            departure: arrival - Self::duration(&trip),
+            arrival,
+            trip,
+        }
+    }
+    fn duration(trip: &Trip<S>) -> chrono::TimeDelta {
+        chrono::TimeDelta::from_std(trip.total_time_with_waiting())
+            .unwrap_or(chrono::TimeDelta::MAX)
+    }
+}
+
+/// A trip routed with one of several costing models, see [`crate::Valhalla::compare_costings`]
+#[derive(Debug)]
+pub struct CostingComparison {
+    pub costing: costing::Costing,
+    /// The trip, or why no trip could be found with this costing model
+    pub trip: Result<Trip, crate::Error>,
+}
+
+impl CostingComparison {
+    /// Estimated total travel time, see [`Trip::total_time`]
+    pub fn total_time(&self) -> Option<std::time::Duration> {
+        self.trip.as_ref().ok().map(Trip::total_time)
+    }
+    /// Total length in meters, see [`Trip::total_distance`]
+    pub fn total_distance(&self) -> Option<f64> {
+        self.trip.as_ref().ok().map(Trip::total_distance)
+    }
+}
+
+/// A leg of a route which could not be found, see [`PartialRoute`]
+#[derive(Debug)]
+pub struct FailedLeg {
+    /// The index of the location the leg starts at, in the locations of the [`Manifest`]
+    pub from: usize,
+    /// The index of the location the leg ends at, in the locations of the [`Manifest`]
+    pub to: usize,
+    /// Why no route was found for this leg
+    pub error: crate::Error,
+}
+
+/// The result of [`crate::Valhalla::route_partial`]
+#[derive(Debug)]
+pub enum PartialRoute {
+    /// A route was found between all locations
+    Complete(Trip),
+    /// No route could be found between all locations, so each leg was routed on its own
+    ///
+    /// The legs are in the order of the locations of the [`Manifest`].
+    Partial(Vec<Result<Trip, FailedLeg>>),
+}
+
+impl PartialRoute {
+    /// The legs which could not be found
+    pub fn failed_legs(&self) -> impl Iterator<Item = &FailedLeg> {
+        let legs = match self {
+            Self::Complete(_) => &[][..],
+            Self::Partial(legs) => legs,
+        };
+        legs.iter().filter_map(|leg| leg.as_ref().err())
+    }
+}
+
 #[cfg(feature = "gpx")]
 impl From<Trip> for gpx::Gpx {
     fn from(trip: Trip) -> Self {
@@ -53,20 +515,42 @@ impl From<Trip> for gpx::Gpx {
         gpx
     }
 }
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct Summary {
+    /// Distance units of [`Self::length`]
+    ///
+    /// Not part of the summary in valhalla's response, but copied from [`Trip::units`].
+    #[serde(default)]
+    pub units: super::Units,
+    /// Estimated time in seconds
     pub time: f64,
+    /// Length in the [`Self::units`]
     pub length: f64,
     pub has_toll: bool,
     pub has_highway: bool,
     pub has_ferry: bool,
+    /// `true` if the route passes roads with time-dependent restrictions, such as truck bans at night
+    #[serde(default)]
+    pub has_time_restrictions: bool,
     pub min_lat: f64,
     pub min_lon: f64,
     pub max_lat: f64,
     pub max_lon: f64,
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+impl Summary {
+    /// Length in meters, regardless of the [`Self::units`]
+    pub fn distance(&self) -> f64 {
+        self.units.to_meters(self.length)
+    }
+    /// Estimated time
+    pub fn duration(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(self.time)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TravelMode {
     #[serde(rename = "drive")]
     Drive,
@@ -78,25 +562,25 @@ pub enum TravelMode {
     Transit,
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum CarTravelType {
     #[serde(rename = "car")]
     Car,
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum PedestrianTravelType {
     #[serde(rename = "foot")]
     Foot,
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum BicycleTravelType {
     #[serde(rename = "road")]
     Road,
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum TransitTravelType {
     #[serde(rename = "tram")]
     Tram,
@@ -116,7 +600,7 @@ pub enum TransitTravelType {
     Funicular,
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum BssManeuverType {
     #[serde(rename = "NoneAction")]
     NoneAction,
@@ -126,14 +610,88 @@ pub enum BssManeuverType {
     ReturnBikeAtBikeShare,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct Leg {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(
+    serialize = "S: ShapeRepresentation",
+    deserialize = "S: ShapeRepresentation + Default"
+))]
+pub struct Leg<S = Vec<ShapePoint>> {
     pub summary: Summary,
 
     pub maneuvers: Vec<Maneuver>,
 
-    #[serde(deserialize_with = "crate::shapes::deserialize_shape")]
-    pub shape: Vec<crate::shapes::ShapePoint>,
+    /// The shape of the leg
+    ///
+    /// Decoded into its points by default, or kept as-is if requested via
+    /// [`crate::Valhalla::route_encoded`].
+    /// Empty if [`crate::shapes::ShapeFormat::NoShape`] was requested via [`Manifest::shape_format`].
+    #[serde(
+        default,
+        serialize_with = "S::serialize_shape",
+        deserialize_with = "S::deserialize_shape"
+    )]
+    pub shape: S,
+
+    /// The floor levels along the leg, e.g. to show "go to floor 2" in indoor wayfinding, see [`Self::level_at`]
+    ///
+    /// Only returned on graphs with indoor data, empty otherwise.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub level_changes: Vec<LevelChange>,
+}
+
+/// The floor level a [`Leg`] is on from a point of its shape onwards
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(from = "(usize, f32)", into = "(usize, f32)")]
+pub struct LevelChange {
+    /// The index of the point of [`Leg::shape`] at which the level is entered
+    pub shape_index: usize,
+    /// The floor level, e.g. `0` for the ground floor or `-1` for the basement
+    pub level: f32,
+}
+
+impl From<(usize, f32)> for LevelChange {
+    fn from((shape_index, level): (usize, f32)) -> Self {
+        Self { shape_index, level }
+    }
+}
+impl From<LevelChange> for (usize, f32) {
+    fn from(change: LevelChange) -> Self {
+        (change.shape_index, change.level)
+    }
+}
+
+impl<S> Leg<S> {
+    /// The floor level at a point of the shape, or `None` if no level is known there
+    pub fn level_at(&self, shape_index: usize) -> Option<f32> {
+        self.level_changes
+            .iter()
+            .take_while(|change| change.shape_index <= shape_index)
+            .last()
+            .map(|change| change.level)
+    }
+}
+
+impl Leg {
+    /// Removes invalid and consecutive duplicate points from the shape, see [`crate::shapes::normalize`]
+    ///
+    /// The shape indices of the maneuvers are updated accordingly.
+    pub fn normalize_shape(&mut self) {
+        let new_indices = crate::shapes::normalize(&mut self.shape);
+        let new_index = |i: usize| {
+            new_indices
+                .get(i)
+                .or(new_indices.last())
+                .copied()
+                .unwrap_or(0)
+        };
+        for maneuver in &mut self.maneuvers {
+            maneuver.begin_shape_index = new_index(maneuver.begin_shape_index);
+            maneuver.end_shape_index = new_index(maneuver.end_shape_index);
+        }
+        for change in &mut self.level_changes {
+            change.shape_index = new_index(change.shape_index);
+        }
+    }
 }
 
 #[cfg(feature = "gpx")]
@@ -149,7 +707,7 @@ impl From<&Leg> for gpx::TrackSegment {
     }
 }
 
-#[derive(serde_repr::Deserialize_repr, Debug, Clone, Copy)]
+#[derive(serde_repr::Serialize_repr, serde_repr::Deserialize_repr, Debug, Clone, Copy)]
 #[repr(i8)]
 pub enum ManeuverType {
     None = 0,
@@ -198,10 +756,40 @@ pub enum ManeuverType {
     BuildingExit,
 }
 
-#[derive(Deserialize, Clone, Debug)]
-pub struct Sign {}
+/// The interchange guide information at a road junction
+///
+/// Each list contains the elements in the order they are signed.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[non_exhaustive]
+pub struct Sign {
+    /// Exit numbers, e.g. `"22A"`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exit_number_elements: Vec<SignElement>,
+    /// Roads the exit branches onto, e.g. `"I 95 North"`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exit_branch_elements: Vec<SignElement>,
+    /// Locations the exit leads toward, e.g. `"New York"`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exit_toward_elements: Vec<SignElement>,
+    /// Names of the exit, e.g. `"Gettysburg Pike"`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exit_name_elements: Vec<SignElement>,
+}
 
-#[derive(Deserialize, Clone, Debug)]
+/// One element of a [`Sign`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SignElement {
+    /// The text of the element
+    pub text: String,
+    /// `true` if the element is a route number, e.g. `"I 95"`
+    pub is_route_number: Option<bool>,
+    /// The number of preceding maneuvers this element was signed on as well
+    pub consecutive_count: Option<u32>,
+}
+
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Maneuver {
     #[serde(rename = "type")]
     pub type_: ManeuverType,
@@ -236,8 +824,10 @@ pub struct Maneuver {
     /// Maneuver length in the [`super::Units`] specified via [`Manifest::units`]
     pub length: f64,
     /// Index into the list of shape points for the start of the maneuver.
+    #[serde_as(as = "crate::lenient::LenientInteger")]
     pub begin_shape_index: usize,
     /// Index into the list of shape points for the end of the maneuver.
+    #[serde_as(as = "crate::lenient::LenientInteger")]
     pub end_shape_index: usize,
     /// `true` if a toll booth is encountered on this maneuver.
     pub toll: Option<bool>,
@@ -250,12 +840,15 @@ pub struct Maneuver {
     pub gate: Option<bool>,
     /// `true` if a ferry is encountered on this maneuver.
     pub ferry: Option<bool>,
+    /// `true` if the maneuver passes roads with time-dependent restrictions.
+    pub has_time_restrictions: Option<bool>,
     /// Contains the interchange guide information at a road junction associated with this
     /// maneuver.
     ///
     /// See [`Sign`] for details.
     pub sign: Option<Sign>,
     /// The spoke to exit roundabout after entering.
+    #[serde_as(as = "Option<crate::lenient::LenientInteger>")]
     pub roundabout_exit_count: Option<i64>,
     /// Written depart time instruction.
     ///
@@ -294,7 +887,31 @@ pub struct Maneuver {
     pub bss_maneuver_type: Option<BssManeuverType>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+impl Maneuver {
+    /// Transforms all written and verbal instructions, e.g. to expand abbreviations
+    ///
+    /// Street names ([`Self::street_names`], [`Self::begin_street_names`]) are not changed.
+    pub fn map_instructions(&mut self, f: impl Fn(&str) -> String) {
+        self.instruction = f(&self.instruction);
+        for instruction in [
+            &mut self.verbal_transition_alert_instruction,
+            &mut self.verbal_pre_transition_instruction,
+            &mut self.verbal_post_transition_instruction,
+            &mut self.depart_instruction,
+            &mut self.verbal_depart_instruction,
+            &mut self.arrive_instruction,
+            &mut self.verbal_arrive_instruction,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *instruction = f(instruction);
+        }
+    }
+}
+
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TransitInfo {
     /// Global transit route identifier.
     pub onestop_id: String,
@@ -313,6 +930,7 @@ pub struct TransitInfo {
     /// The numeric color value associated with a transit route.
     ///
     /// The value for yellow would be "16567306".
+    #[serde_as(as = "crate::lenient::LenientInteger")]
     pub color: i32,
     /// The numeric text color value associated with a transit route.
     ///
@@ -345,7 +963,7 @@ pub struct TransitInfo {
     pub transit_stops: Vec<TransitStop>,
 }
 
-#[derive(serde_repr::Deserialize_repr, Debug, Clone, Copy)]
+#[derive(serde_repr::Serialize_repr, serde_repr::Deserialize_repr, Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum TransitStopType {
     /// Simple stop.
@@ -354,7 +972,7 @@ pub enum TransitStopType {
     Station,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TransitStop {
     #[serde(rename = "type")]
     pub type_: TransitStopType,
@@ -394,8 +1012,60 @@ pub enum DirectionsType {
     Instructions,
 }
 
+/// The local date and time at the location
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct DateTime {
+    r#type: RouteDateTimeType,
+    #[serde(serialize_with = "super::serialize_naive_date_time")]
+    value: chrono::NaiveDateTime,
+}
+impl DateTime {
+    /// Current departure time
+    pub fn from_current_departure_time() -> Self {
+        Self {
+            r#type: RouteDateTimeType::CurrentDeparture,
+            value: chrono::Local::now().naive_local(),
+        }
+    }
+    /// Specified departure time
+    pub fn from_departure_time(depart_after: chrono::NaiveDateTime) -> Self {
+        Self {
+            r#type: RouteDateTimeType::SpecifiedDeparture,
+            value: depart_after,
+        }
+    }
+    /// Specified arrival time
+    ///
+    /// **Note:** arrival times are not supported for [`costing::Costing::Multimodal`].
+    /// See [`crate::Valhalla::route_latest_departure`] for an alternative.
+    pub fn from_arrival_time(arrive_by: chrono::NaiveDateTime) -> Self {
+        Self {
+            r#type: RouteDateTimeType::SpecifiedArrival,
+            value: arrive_by,
+        }
+    }
+    /// Invariant specified time
+    ///
+    /// Time does not vary over the course of the path. Not implemented for multimodal or bike share routing
+    pub fn from_invariant_time(value: chrono::NaiveDateTime) -> Self {
+        Self {
+            r#type: RouteDateTimeType::Invariant,
+            value,
+        }
+    }
+}
+
+#[derive(serde_repr::Serialize_repr, Debug, Clone, Copy)]
+#[repr(u8)]
+enum RouteDateTimeType {
+    CurrentDeparture = 0,
+    SpecifiedDeparture,
+    SpecifiedArrival,
+    Invariant,
+}
+
 #[serde_with::skip_serializing_none]
-#[derive(Serialize, Default, Debug)]
+#[derive(Serialize, Default, Debug, Clone)]
 pub struct Manifest {
     #[serde(flatten)]
     costing: Option<costing::Costing>,
@@ -410,6 +1080,8 @@ pub struct Manifest {
     linear_references: Option<bool>,
     prioritize_bidirectional: Option<bool>,
     roundabout_exits: Option<bool>,
+    shape_format: Option<crate::shapes::ShapeFormat>,
+    date_time: Option<DateTime>,
 }
 
 impl Manifest {
@@ -450,6 +1122,71 @@ impl Manifest {
         self
     }
 
+    /// Routes roughly along a sketched line, e.g. drawn freehand on a map
+    ///
+    /// The sketch is [densified](crate::shapes::densify) with a [`LocationType::Through`] location
+    /// every `spacing` meters, between a [`LocationType::Break`] location at each end.
+    /// Choose a `spacing` well above the snapping inaccuracy of the sketch, or the route zigzags
+    /// between the roads next to it.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use valhalla_client::route::Manifest;
+    /// use valhalla_client::shapes::ShapePoint;
+    ///
+    /// let sketch = [
+    ///   ShapePoint { lon: 4.9041, lat: 52.3676 },
+    ///   ShapePoint { lon: 5.1214, lat: 52.0907 },
+    /// ];
+    /// let manifest = Manifest::builder().locations_along(&sketch, 5_000.0);
+    /// ```
+    pub fn locations_along(self, sketch: &[crate::shapes::ShapePoint], spacing: f64) -> Self {
+        let points = crate::shapes::densify(sketch, spacing);
+        let last = points.len().saturating_sub(1);
+        let locations = points.iter().enumerate().map(|(i, point)| {
+            let location = Location::new(point.lon as f32, point.lat as f32);
+            if i == 0 || i == last {
+                location.r#type(LocationType::Break)
+            } else {
+                location.r#type(LocationType::Through)
+            }
+        });
+        self.locations(locations)
+    }
+
+    /// Splits the route into one manifest per leg, i.e. between consecutive break locations
+    ///
+    /// Returns the indices of the first and last location of each leg together with its manifest.
+    pub(crate) fn legs(&self) -> Vec<(usize, usize, Self)> {
+        let breaks: Vec<usize> = self
+            .locations
+            .iter()
+            .enumerate()
+            .filter(|(i, l)| *i == 0 || *i == self.locations.len() - 1 || l.is_break())
+            .map(|(i, _)| i)
+            .collect();
+        breaks
+            .windows(2)
+            .map(|w| {
+                let locations = self.locations[w[0]..=w[1]].iter().cloned();
+                (w[0], w[1], self.clone().locations(locations))
+            })
+            .collect()
+    }
+
+    /// Predicts how expensive the route is, see [`crate::estimate`]
+    pub fn estimate(&self) -> crate::estimate::Estimate {
+        let points: Vec<_> = self
+            .locations
+            .iter()
+            .map(|l| crate::shapes::ShapePoint {
+                lon: f64::from(l.longitude),
+                lat: f64::from(l.latitude),
+            })
+            .collect();
+        crate::estimate::Estimate::route(&points)
+    }
+
     /// Sets the distance units for output.
     ///
     /// Possible unit types are
@@ -482,6 +1219,16 @@ impl Manifest {
         self.language = Some(language.to_string());
         self
     }
+    /// Shortcut for setting both the [`Self::language`] and the [`Self::units`] from a
+    /// [IETF BCP 47](https://en.wikipedia.org/wiki/IETF_language_tag) locale.
+    ///
+    /// The units are derived via [`super::Units::from_locale`].
+    ///
+    /// Example: `de-DE` results in German narration and kilometers, `en-US` in English narration and miles.
+    pub fn locale(self, locale: &str) -> Self {
+        self.units(super::Units::from_locale(locale))
+            .language(locale.replace('_', "-"))
+    }
     /// Sets the directions type
     ///
     /// [`DirectionsType`] is an enum with 3 values:
@@ -630,9 +1377,72 @@ impl Manifest {
         self.roundabout_exits = Some(false);
         self
     }
+
+    /// Sets the date and time of departure or arrival, enabling time dependent routing
+    ///
+    /// See [`DateTime`] for the supported variants.
+    ///
+    /// Default: time independent routing
+    pub fn date_time(mut self, date_time: DateTime) -> Self {
+        self.date_time = Some(date_time);
+        self
+    }
+
+    /// Specifies the [`crate::shapes::ShapeFormat`] for the path shape of each leg.
+    ///
+    /// Default: [`crate::shapes::ShapeFormat::Polyline6`]
+    pub fn shape_format(mut self, shape_format: crate::shapes::ShapeFormat) -> Self {
+        self.shape_format = Some(shape_format);
+        self
+    }
+
+    /// Builds the request recalculating a route from the traveler's current `position`
+    ///
+    /// The costing and all other options of this request are carried over, while the locations
+    /// are replaced by the `position` followed by the `remaining` (not yet visited) waypoints.
+    /// Unless the `position` has a [`Location::heading`] already, its heading is set to the
+    /// direction of the `trip` at the closest shape point, to avoid u-turns or routes starting on the
+    /// opposite carriageway.
+    /// A departure or arrival time is replaced by departing now.
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use valhalla_client::Valhalla;
+    /// use valhalla_client::route::{Location, Manifest};
+    ///
+    /// let amsterdam = Location::new(4.9041, 52.3676);
+    /// let utrecht = Location::new(5.1214, 52.0907);
+    /// let manifest = Manifest::builder().locations([amsterdam, utrecht.clone()]);
+    ///
+    /// let valhalla = Valhalla::default();
+    /// let trip = valhalla.route(manifest.clone()).unwrap();
+    /// // the traveler deviated from the route
+    /// let rerouted = manifest.reroute(&trip, Location::new(4.95, 52.35), [utrecht]);
+    /// let trip = valhalla.route(rerouted).unwrap();
+    /// ```
+    pub fn reroute(
+        &self,
+        trip: &Trip,
+        position: Location,
+        remaining: impl IntoIterator<Item = Location>,
+    ) -> Self {
+        let mut position = position.r#type(LocationType::Break);
+        if position.heading.is_none() {
+            if let Some(heading) = trip.heading_near(&position) {
+                position = position.heading(heading.round() as u32 % 360);
+            }
+        }
+        let mut manifest = self
+            .clone()
+            .locations(std::iter::once(position).chain(remaining));
+        if manifest.date_time.is_some() {
+            manifest.date_time = Some(DateTime::from_current_departure_time());
+        }
+        manifest
+    }
 }
 
-#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LocationType {
     #[default]
     #[serde(rename = "break")]
@@ -661,6 +1471,19 @@ pub enum Side {
     Either,
 }
 
+/// The side of the street a location is on
+///
+/// Relative to the direction of travel for the [`Trip::locations`], and to the direction of the
+/// edge for the [`crate::locate::Edge`]s a location is correlated to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SideOfStreet {
+    Left,
+    Right,
+    /// Directly on the street or at its end
+    Neither,
+}
+
 #[cfg(feature = "gpx")]
 impl From<&Location> for gpx::Waypoint {
     fn from(location: &Location) -> Self {
@@ -671,8 +1494,9 @@ impl From<&Location> for gpx::Waypoint {
         p
     }
 }
+/// Reads the coordinate as (longitude, latitude), like everywhere else in this crate
 impl From<super::Coordinate> for Location {
-    fn from((latitude, longitude): super::Coordinate) -> Self {
+    fn from((longitude, latitude): super::Coordinate) -> Self {
         Self {
             latitude,
             longitude,
@@ -681,7 +1505,41 @@ impl From<super::Coordinate> for Location {
     }
 }
 
+#[cfg(feature = "geojson")]
 impl Location {
+    /// Creates locations from the `Point` features of a GeoJSON `FeatureCollection`
+    ///
+    /// If `name_property` is given, its value is used as the [`Self::name`] of the location.
+    /// Fails if a feature is not a `Point`, so that the locations stay aligned with the features.
+    pub fn from_geojson(
+        collection: &geojson::FeatureCollection,
+        name_property: Option<&str>,
+    ) -> Result<Vec<Self>, geojson::Error> {
+        collection
+            .features
+            .iter()
+            .map(|feature| {
+                let (longitude, latitude) = super::coordinate_of(feature)?;
+                let location = Self::new(longitude, latitude);
+                let name = name_property.and_then(|p| feature.property(p));
+                Ok(match name {
+                    Some(serde_json::Value::String(name)) => location.name(name),
+                    Some(name) => location.name(name),
+                    None => location,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Location {
+    /// Whether a leg of the route starts or ends at this location, see [`LocationType`]
+    pub(crate) fn is_break(&self) -> bool {
+        matches!(
+            self.r#type,
+            None | Some(LocationType::Break | LocationType::BreakThrough)
+        )
+    }
     /// Create a Location from latitude/longitude of the location in degrees.
     ///
     /// This is assumed to be both routing location and display location is equal.
@@ -839,6 +1697,8 @@ impl Location {
     /// Cutoff at which we will assume the input is too far away from civilisation to be worth
     /// correlating to the nearest graph elements.
     ///
+    /// The cutoff is given in meters.
+    ///
     /// Default: `35 km`
     pub fn search_cutoff(mut self, search_cutoff: f32) -> Self {
         self.search_cutoff = Some(search_cutoff);
@@ -896,8 +1756,46 @@ impl Location {
         self.street_side_cutoff = Some(street_side_cutoff);
         self
     }
+
+    /// The waiting time in seconds at this location.
+    ///
+    /// Delays the departure from this location, for example to model the service time of a
+    /// delivery. Only applies to [`LocationType::Break`] and [`LocationType::BreakThrough`] locations.
+    ///
+    /// See [`Trip::total_time_with_waiting`] for the total time including the waiting time.
+    ///
+    /// Default: `0` seconds
+    pub fn waiting(mut self, waiting: u32) -> Self {
+        self.waiting = Some(waiting);
+        self
+    }
+
+    /// Restricts which edges the location may be correlated to, e.g. to exclude tunnels or ferries
+    ///
+    /// See [`crate::locate::SearchFilter`] for the available filters.
+    ///
+    /// Default: no restrictions
+    pub fn search_filter(mut self, search_filter: crate::locate::SearchFilter) -> Self {
+        self.search_filter = Some(search_filter);
+        self
+    }
+
+    /// The side of the street the location is on, e.g. to place a destination pin
+    ///
+    /// Only set on the [`Trip::locations`] returned by valhalla.
+    pub fn side_of_street(&self) -> Option<SideOfStreet> {
+        self.side_of_street
+    }
+
+    /// The index of the location in the request, if the locations were reordered
+    ///
+    /// Only set on the [`Trip::locations`] of an optimized route, see [`crate::optimized_route`].
+    pub fn original_index(&self) -> Option<usize> {
+        self.original_index
+    }
 }
 
+#[serde_with::skip_serializing_none]
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct Location {
     #[serde(rename = "lat")]
@@ -922,6 +1820,13 @@ pub struct Location {
     street_side_tolerance: Option<f32>,
     street_side_max_distance: Option<f32>,
     street_side_cutoff: Option<f32>,
+    waiting: Option<u32>,
+    search_filter: Option<crate::locate::SearchFilter>,
+    // only returned by valhalla, so not sent back when a location of a trip is reused in a request
+    #[serde(skip_serializing)]
+    side_of_street: Option<SideOfStreet>,
+    #[serde(skip_serializing)]
+    original_index: Option<usize>,
 }
 
 #[cfg(test)]
@@ -933,5 +1838,484 @@ mod test {
             serde_json::to_value(Manifest::default()).unwrap(),
             serde_json::json!({"locations": []})
         );
+        assert_eq!(
+            serde_json::to_value(Manifest::builder().locale("en_US")).unwrap(),
+            serde_json::json!({"locations": [], "units": "miles", "language": "en-US"})
+        );
+        let location = Location::new(4.9041, 52.3676)
+            .search_cutoff(500.0)
+            .street_side_tolerance(2.0)
+            .street_side_max_distance(50.0);
+        assert_eq!(
+            serde_json::to_value(location).unwrap(),
+            serde_json::json!({
+                "lat": 52.3676_f32, "lon": 4.9041_f32,
+                "search_cutoff": 500.0, "street_side_tolerance": 2.0, "street_side_max_distance": 50.0,
+            })
+        );
+    }
+
+    fn trip() -> Trip {
+        let summary = serde_json::json!({
+            "time": 90.0, "length": 1.5,
+            "has_toll": false, "has_highway": false, "has_ferry": false,
+            "min_lat": 52.0, "min_lon": 4.0, "max_lat": 53.0, "max_lon": 5.0,
+        });
+        let maneuver = serde_json::json!({
+            "type": 1, "instruction": "Drive north.", "time": 45.0, "length": 0.75,
+            "begin_shape_index": 0, "end_shape_index": 1, "travel_mode": "drive",
+        });
+        let leg = serde_json::json!({
+            "summary": summary, "maneuvers": [maneuver], "shape": "",
+        });
+        let mut trip_summary = summary.clone();
+        trip_summary["time"] = 180.0.into();
+        trip_summary["length"] = 3.0.into();
+        let response: Response = serde_json::from_value(serde_json::json!({ "trip": {
+            "status": 0, "status_message": "Found route between points",
+            "units": "kilometers", "language": "en-US",
+            "locations": [
+                {"lat": 52.0, "lon": 4.0},
+                {"lat": 52.5, "lon": 4.5, "waiting": 300, "side_of_street": "neither"},
+                {"lat": 53.0, "lon": 5.0, "side_of_street": "right", "original_index": 2},
+            ],
+            "legs": [leg.clone(), leg],
+            "summary": trip_summary,
+        }}))
+        .unwrap();
+        response.into_trip()
+    }
+
+    #[test]
+    fn level_changes() {
+        let trip = trip();
+        assert!(trip.legs[0].level_changes.is_empty());
+        assert_eq!(trip.legs[0].level_at(0), None);
+
+        let leg = serde_json::json!({
+            "summary": serde_json::to_value(&trip.legs[0].summary).unwrap(),
+            "maneuvers": [], "shape": "", "level_changes": [[0, 0.0], [4, 2.0]],
+        });
+        let leg: Leg = serde_json::from_value(leg).unwrap();
+        assert_eq!(leg.level_at(3), Some(0.0));
+        assert_eq!(leg.level_at(4), Some(2.0));
+        assert_eq!(
+            serde_json::to_value(&leg).unwrap()["level_changes"],
+            serde_json::json!([[0, 0.0], [4, 2.0]])
+        );
+    }
+
+    #[test]
+    fn reroute() {
+        let mut trip = trip();
+        trip.legs[0].shape = vec![
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.0,
+            },
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.5,
+            },
+            ShapePoint {
+                lon: 4.5,
+                lat: 52.5,
+            },
+        ];
+        let manifest = Manifest::builder()
+            .costing(costing::Costing::Bicycle(Default::default()))
+            .locations([Location::new(4.0, 52.0), Location::new(4.5, 52.5)])
+            .date_time(DateTime::from_arrival_time(chrono::NaiveDateTime::default()));
+        let rerouted = manifest.reroute(
+            &trip,
+            Location::new(4.01, 52.49),
+            [Location::new(4.5, 52.5)],
+        );
+        let rerouted = serde_json::to_value(rerouted).unwrap();
+        assert_eq!(rerouted["costing"], "bicycle");
+        assert_eq!(rerouted["date_time"]["type"], 0);
+        assert_eq!(rerouted["locations"].as_array().unwrap().len(), 2);
+        assert_eq!(rerouted["locations"][0]["heading"], 90, "heading east");
+        assert_eq!(rerouted["locations"][0]["type"], "break");
+    }
+
+    #[test]
+    fn progress() {
+        let mut trip = trip();
+        let maneuver = |begin_shape_index: usize, instruction: &str| {
+            let mut maneuver = trip.legs[0].maneuvers[0].clone();
+            maneuver.begin_shape_index = begin_shape_index;
+            maneuver.instruction = instruction.to_string();
+            maneuver
+        };
+        let first_leg = vec![
+            maneuver(0, "Start"),
+            maneuver(1, "Turn"),
+            maneuver(2, "Stop"),
+        ];
+        let second_leg = vec![maneuver(0, "Restart"), maneuver(1, "Arrive")];
+        trip.legs[0].maneuvers = first_leg;
+        trip.legs[1].maneuvers = second_leg;
+        trip.legs[0].shape = vec![
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.0,
+            },
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.01,
+            },
+            ShapePoint {
+                lon: 4.01,
+                lat: 52.01,
+            },
+        ];
+        trip.legs[1].shape = vec![
+            ShapePoint {
+                lon: 4.01,
+                lat: 52.01,
+            },
+            ShapePoint {
+                lon: 4.02,
+                lat: 52.01,
+            },
+        ];
+
+        let progress = trip.progress_near((4.0, 52.0001)).unwrap();
+        assert_eq!((progress.leg(), progress.shape_index()), (0, 0));
+        assert_eq!(progress.next_instruction(), Some("Turn"));
+        assert_eq!(progress.then_maneuver().unwrap().instruction, "Stop");
+        let distance = progress.distance_to_next_maneuver().unwrap();
+        assert!((distance - 1_112.0).abs() < 1.0, "0.01° of latitude");
+
+        let progress = trip.progress_at(0, 2);
+        assert_eq!(progress.next_instruction(), Some("Restart"));
+        assert_eq!(progress.distance_to_next_maneuver(), Some(0.0));
+        assert!(trip.progress_at(1, 1).next_maneuver().is_none());
+
+        trip.legs[0].shape = vec![
+            ShapePoint {
+                lon: 179.99,
+                lat: 0.0,
+            },
+            ShapePoint {
+                lon: 170.0,
+                lat: 0.0,
+            },
+        ];
+        assert_eq!(
+            trip.progress_near((-179.99, 0.0)).unwrap().shape_index(),
+            0,
+            "closer via the anti-meridian"
+        );
+    }
+
+    #[test]
+    fn normalize_shape() {
+        let mut leg = trip().legs.remove(0);
+        leg.shape = vec![
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.0,
+            },
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.0,
+            },
+            ShapePoint {
+                lon: 4.5,
+                lat: 52.5,
+            },
+        ];
+        leg.maneuvers[0].end_shape_index = 2;
+        leg.normalize_shape();
+        assert_eq!(leg.shape.len(), 2);
+        assert_eq!(leg.maneuvers[0].begin_shape_index, 0);
+        assert_eq!(leg.maneuvers[0].end_shape_index, 1);
+    }
+
+    #[test]
+    fn snapped_locations() {
+        let mut trip = trip();
+        assert!(trip.snapped_locations().is_empty(), "no shape");
+        trip.legs[0].shape = vec![
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.001,
+            },
+            ShapePoint {
+                lon: 4.5,
+                lat: 52.5,
+            },
+        ];
+        trip.legs[1].shape = vec![
+            ShapePoint {
+                lon: 4.5,
+                lat: 52.5,
+            },
+            ShapePoint {
+                lon: 5.0,
+                lat: 53.0,
+            },
+        ];
+        let snapped = trip.snapped_locations();
+        assert_eq!(snapped.len(), 3);
+        assert!((snapped[0].snap_distance() - 111.2).abs() < 0.1);
+        assert_eq!(snapped[2].snap_distance(), 0.0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut trip = trip();
+        trip.units = crate::Units::Imperial;
+        trip.summary.units = crate::Units::Imperial;
+        trip.legs[0].shape = vec![
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.0,
+            },
+            ShapePoint {
+                lon: 4.5,
+                lat: 52.5,
+            },
+        ];
+        let serialized = serde_json::to_value(&trip).unwrap();
+        let restored: Trip = serde_json::from_value(serialized.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), serialized);
+        assert_eq!(restored.total_distance(), trip.total_distance());
+        assert_eq!(restored.legs[0].shape.len(), 2);
+        assert!((restored.legs[0].shape[1].lat - 52.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn split_into_legs() {
+        let manifest = Manifest::builder().locations([
+            Location::new(0.0, 0.0),
+            Location::new(1.0, 0.0).r#type(LocationType::Through),
+            Location::new(2.0, 0.0).r#type(LocationType::BreakThrough),
+            Location::new(3.0, 0.0),
+        ]);
+        let legs = manifest.legs();
+        let bounds: Vec<_> = legs.iter().map(|(from, to, _)| (*from, *to)).collect();
+        assert_eq!(bounds, [(0, 2), (2, 3)]);
+        assert_eq!(legs[0].2.locations.len(), 3);
+
+        let sketch = [
+            crate::shapes::ShapePoint { lon: 0.0, lat: 0.0 },
+            crate::shapes::ShapePoint {
+                lon: 0.0,
+                lat: 0.01,
+            },
+        ];
+        let legs = Manifest::builder().locations_along(&sketch, 300.0).legs();
+        assert_eq!(legs.len(), 1, "through locations do not split the route");
+        assert_eq!(legs[0].2.locations.len(), 5);
+
+        let route = PartialRoute::Partial(vec![Err(FailedLeg {
+            from: 0,
+            to: 2,
+            error: crate::Error::Url(url::ParseError::EmptyHost),
+        })]);
+        let failed: Vec<_> = route.failed_legs().map(|leg| (leg.from, leg.to)).collect();
+        assert_eq!(failed, [(0, 2)]);
+    }
+    #[test]
+    fn map_instructions() {
+        let mut maneuver = trip().legs.remove(0).maneuvers.remove(0);
+        maneuver.verbal_pre_transition_instruction = Some("Drive north on N Main St.".into());
+        maneuver.street_names = Some(vec!["N Main St".into()]);
+        maneuver.map_instructions(|i| i.replace("N ", "North "));
+        assert_eq!(maneuver.instruction, "Drive north.");
+        assert_eq!(
+            maneuver.verbal_pre_transition_instruction.as_deref(),
+            Some("Drive north on North Main St.")
+        );
+        assert_eq!(maneuver.street_names, Some(vec!["N Main St".into()]));
+        assert_eq!(maneuver.depart_instruction, None);
+    }
+
+    #[test]
+    fn outcome() {
+        let mut trip = trip();
+        assert_eq!(trip.outcome(), TripStatus::Found);
+        trip.legs[1].summary.length = 0.0;
+        assert_eq!(
+            trip.outcome(),
+            TripStatus::Partial {
+                trivial_legs: vec![1]
+            }
+        );
+        trip.status = 442;
+        trip.status_message = "No path could be found for input".into();
+        assert!(matches!(
+            trip.outcome(),
+            TripStatus::Other { code: 442, .. }
+        ));
+    }
+
+    #[test]
+    fn restricted_maneuvers() {
+        let mut trip = trip();
+        assert!(!trip.summary.has_time_restrictions);
+        assert_eq!(trip.restricted_maneuvers().count(), 0);
+        trip.legs[1].maneuvers[0].has_time_restrictions = Some(true);
+        let restricted: Vec<_> = trip.restricted_maneuvers().collect();
+        assert_eq!(restricted.len(), 1);
+        assert_eq!(restricted[0].0, 1);
+    }
+
+    #[test]
+    fn journey() {
+        let stop = |name: &str, time: &str| {
+            serde_json::json!({
+                "type": 0, "name": name, "is_parent_stop": false, "assumed_schedule": false,
+                "arrival_date_time": time, "departure_date_time": time, "lat": 52.0, "lon": 4.0,
+            })
+        };
+        let ride = |line: &str, stops: serde_json::Value| {
+            serde_json::json!({
+                "type": 30, "instruction": "Take the train.", "time": 600.0, "length": 10.0,
+                "begin_shape_index": 0, "end_shape_index": 0, "travel_mode": "transit",
+                "transit_info": {
+                    "onestop_id": "", "short_name": line, "long_name": "Long Line", "headsign": "Utrecht",
+                    "color": 0, "text_color": "0", "description": "", "operator_onestop_id": "",
+                    "operator_name": "", "operator_url": "", "transit_stops": stops,
+                },
+            })
+        };
+        let walk = |time: f64, length: f64| {
+            serde_json::json!({
+                "type": 1, "instruction": "Walk north.", "time": time, "length": length,
+                "begin_shape_index": 0, "end_shape_index": 0, "travel_mode": "pedestrian",
+            })
+        };
+        let mut trip = trip();
+        let maneuvers = [
+            walk(120.0, 0.1),
+            walk(60.0, 0.05),
+            ride(
+                "IC",
+                serde_json::json!([
+                    stop("Amsterdam", "2024-11-07T15:00:00"),
+                    stop("Amstel", "2024-11-07T15:05:00"),
+                    stop("Utrecht", "2024-11-07T15:25:00"),
+                ]),
+            ),
+            walk(180.0, 0.2),
+            ride(
+                "",
+                serde_json::json!([
+                    stop("Utrecht", "2024-11-07T15:31:00"),
+                    stop("Houten", "2024-11-07T15:40:00"),
+                ]),
+            ),
+            walk(0.0, 0.0),
+        ];
+        trip.legs[0].maneuvers = maneuvers
+            .into_iter()
+            .map(|m| serde_json::from_value(m).unwrap())
+            .collect();
+        trip.legs[1].maneuvers.clear();
+
+        let journey = trip.journey();
+        assert_eq!(journey.len(), 5);
+        assert!(matches!(
+            journey[0],
+            JourneySegment::Walk { distance, time } if distance == 150.0 && time.as_secs() == 180
+        ));
+        let JourneySegment::Ride(first) = journey[1] else {
+            panic!("expected a ride");
+        };
+        assert_eq!(first.line(), "IC");
+        assert_eq!(first.headsign(), "Utrecht");
+        assert_eq!(first.stop_count(), 2);
+        assert_eq!(first.alight.name, "Utrecht");
+        assert!(matches!(journey[2], JourneySegment::Walk { .. }));
+        assert!(matches!(
+            journey[3],
+            JourneySegment::Transfer { duration } if duration == chrono::TimeDelta::minutes(6)
+        ));
+        let JourneySegment::Ride(second) = journey[4] else {
+            panic!("expected a ride");
+        };
+        assert_eq!(second.line(), "Long Line");
+    }
+
+    #[test]
+    fn totals() {
+        let trip = trip();
+        assert_eq!(trip.total_distance(), 3_000.0);
+        assert_eq!(trip.locations[0].side_of_street(), None);
+        assert_eq!(
+            trip.locations[1].side_of_street(),
+            Some(SideOfStreet::Neither)
+        );
+        assert_eq!(
+            serde_json::to_value(&trip.locations[2]).unwrap(),
+            serde_json::json!({"lat": 53.0, "lon": 5.0})
+        );
+        assert_eq!(
+            trip.locations[2].side_of_street(),
+            Some(SideOfStreet::Right)
+        );
+        assert!(trip
+            .legs
+            .iter()
+            .all(|l| l.summary.units == crate::Units::Metric));
+        assert_eq!(trip.total_time(), std::time::Duration::from_secs(180));
+        assert_eq!(
+            trip.total_time_with_waiting(),
+            std::time::Duration::from_secs(480)
+        );
+        assert_eq!(trip.leg_distances().collect::<Vec<_>>(), [1_500.0; 2]);
+        assert_eq!(
+            trip.leg_times().collect::<Vec<_>>(),
+            [std::time::Duration::from_secs(90); 2]
+        );
+    }
+
+    #[cfg(feature = "geojson")]
+    #[test]
+    fn from_geojson() {
+        let collection: geojson::FeatureCollection = serde_json::from_value(serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "properties": {"name": "Amsterdam"}, "geometry": {"type": "Point", "coordinates": [4.9041, 52.3676]}},
+                {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [5.1214, 52.0907]}},
+            ]
+        }))
+        .unwrap();
+        let locations = Location::from_geojson(&collection, Some("name")).unwrap();
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0].longitude, 4.9041);
+        assert_eq!(locations[0].latitude, 52.3676);
+        assert_eq!(locations[0].name.as_deref(), Some("Amsterdam"));
+        assert_eq!(locations[1].name, None);
+    }
+
+    #[test]
+    fn location_from_coordinate() {
+        // a `Coordinate` is (longitude, latitude)
+        let location = Location::from((4.9041, 52.3676));
+        assert_eq!(location.longitude, 4.9041);
+        assert_eq!(location.latitude, 52.3676);
+    }
+
+    #[test]
+    fn schedule() {
+        let noon = chrono::NaiveDate::from_ymd_opt(2024, 11, 7)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        let arriving = ScheduledTrip::arriving_at(trip(), noon);
+        assert_eq!(arriving.departure.to_string(), "2024-11-07 11:52:00");
+        let departing = ScheduledTrip::departing_at(trip(), noon);
+        assert_eq!(departing.arrival.to_string(), "2024-11-07 12:08:00");
+
+        assert_eq!(
+            serde_json::to_value(Manifest::builder().date_time(DateTime::from_arrival_time(noon)))
+                .unwrap(),
+            serde_json::json!({"locations": [], "date_time": {"type": 2, "value": "2024-11-07T12:00"}})
+        );
     }
 }
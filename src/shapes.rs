@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Specifies the optional format for the path shape of each connection
-#[derive(Serialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShapeFormat {
     #[serde(rename = "polyline6")]
     Polyline6,
@@ -13,7 +13,27 @@ pub enum ShapeFormat {
     NoShape,
 }
 
-#[derive(Debug, Clone)]
+impl ShapeFormat {
+    /// The number of decimal places of polylines in this format
+    fn precision(self) -> i32 {
+        match self {
+            Self::Polyline5 => 5,
+            _ => 6,
+        }
+    }
+    /// The format the shapes of the response to a `request` body are returned in
+    pub(crate) fn of(request: &serde_json::Value) -> Self {
+        request
+            .get("shape_format")
+            .and_then(|format| Self::deserialize(format).ok())
+            .unwrap_or(Self::Polyline6)
+    }
+}
+
+/// The mean radius of the earth in meters
+pub(crate) const EARTH_RADIUS: f64 = 6_371_008.8;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ShapePoint {
     pub lon: f64,
     pub lat: f64,
@@ -31,42 +51,845 @@ impl From<ShapePoint> for super::Coordinate {
     }
 }
 
-fn decode_shape(encoded: &str) -> Vec<ShapePoint> {
-    let inv = 1.0 / 1e6;
-    let mut decoded = Vec::new();
-    let mut previous = [0, 0];
-    let mut i = 0;
+impl ShapePoint {
+    /// Whether the point is a finite, in-range coordinate
+    pub fn is_valid(&self) -> bool {
+        (-180.0..=180.0).contains(&self.lon) && (-90.0..=90.0).contains(&self.lat)
+    }
+    /// The initial bearing in degrees from this point towards `other`, clockwise from north
+    pub fn bearing_to(&self, other: &ShapePoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let delta_lon = (other.lon - self.lon).to_radians();
+        let y = delta_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_lon.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+    /// Whether both coordinates differ by at most `epsilon` degrees
+    ///
+    /// An `epsilon` of `1e-6` matches the precision of [`ShapeFormat::Polyline6`].
+    pub fn approx_eq(&self, other: &ShapePoint, epsilon: f64) -> bool {
+        wrap_longitude(self.lon - other.lon).abs() <= epsilon
+            && (self.lat - other.lat).abs() <= epsilon
+    }
+    /// The great-circle distance in meters between this point and `other`
+    pub fn distance_to(&self, other: &ShapePoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let half_delta_lat = (lat2 - lat1) / 2.0;
+        let half_delta_lon = (other.lon - self.lon).to_radians() / 2.0;
+        let a =
+            half_delta_lat.sin().powi(2) + lat1.cos() * lat2.cos() * half_delta_lon.sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().asin()
+    }
+}
 
-    while i < encoded.len() {
-        let mut ll = [0, 0];
+/// A requested location together with the point on the road network it was snapped to
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnappedLocation {
+    /// The coordinate as requested
+    pub requested: ShapePoint,
+    /// The coordinate on the road network, i.e. where the shape starts or ends
+    pub snapped: ShapePoint,
+}
 
-        for j in 0..2 {
-            let mut shift = 0;
-            let mut byte = 0x20;
+impl SnappedLocation {
+    /// Distance in meters between the requested and the snapped coordinate
+    ///
+    /// Large distances hint at waypoints which are far off the road network or are snapped to the wrong road.
+    pub fn snap_distance(&self) -> f64 {
+        self.requested.distance_to(&self.snapped)
+    }
+}
+
+/// Whether two shapes have the same number of points and all points are [approximately equal](ShapePoint::approx_eq)
+pub fn approx_eq(a: &[ShapePoint], b: &[ShapePoint], epsilon: f64) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, epsilon))
+}
 
-            while byte >= 0x20 {
-                byte = i32::from(encoded.as_bytes()[i]) - 63;
+/// The (discrete) Hausdorff distance in meters between the points of two shapes
+///
+/// This is the largest distance from a point of one shape to the closest point of the other,
+/// e.g. to detect routes which are essentially the same despite differently sampled shapes.
+/// Returns `None` if either shape is empty.
+pub fn hausdorff_distance(a: &[ShapePoint], b: &[ShapePoint]) -> Option<f64> {
+    let directed = |from: &[ShapePoint], to: &[ShapePoint]| {
+        from.iter()
+            .map(|p| {
+                to.iter()
+                    .map(|q| p.distance_to(q))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .fold(0.0, f64::max)
+    };
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    Some(directed(a, b).max(directed(b, a)))
+}
+
+/// Wraps a difference of longitudes into `-180..=180` degrees, e.g. `350` becomes `-10`
+pub(crate) fn wrap_longitude(delta_lon: f64) -> f64 {
+    (delta_lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// A corridor of `width` meters around a shape, e.g. as geofence to detect leaving a planned route
+///
+/// The corridor consists of one rectangle per segment and one octagon per shape point, which
+/// covers the joins between segments. The polygons overlap, so a point is inside the corridor if
+/// it is inside any of them.
+/// Offsets are computed in a local equirectangular projection, which is accurate for corridors
+/// much narrower than the distance to the poles.
+pub fn corridor(shape: &[ShapePoint], width: f64) -> geo_types::MultiPolygon<f64> {
+    let half_width = width / 2.0;
+    let meters_per_degree = EARTH_RADIUS.to_radians();
+    // offsets a point by (east, north) meters
+    let offset = |p: &ShapePoint, east: f64, north: f64| {
+        let lon_scale = meters_per_degree * p.lat.to_radians().cos().max(1e-9);
+        geo_types::coord! { x: p.lon + east / lon_scale, y: p.lat + north / meters_per_degree }
+    };
+    let mut polygons = Vec::with_capacity(shape.len() * 2);
+    for segment in shape.windows(2) {
+        let (a, b) = (&segment[0], &segment[1]);
+        let lat = ((a.lat + b.lat) / 2.0).to_radians();
+        let east = wrap_longitude(b.lon - a.lon) * meters_per_degree * lat.cos();
+        let north = (b.lat - a.lat) * meters_per_degree;
+        let length = east.hypot(north);
+        if length == 0.0 {
+            continue;
+        }
+        // the normal to the left of the segment
+        let (normal_east, normal_north) =
+            (-north / length * half_width, east / length * half_width);
+        let exterior = vec![
+            offset(a, normal_east, normal_north),
+            offset(b, normal_east, normal_north),
+            offset(b, -normal_east, -normal_north),
+            offset(a, -normal_east, -normal_north),
+        ];
+        polygons.push(geo_types::Polygon::new(exterior.into(), Vec::new()));
+    }
+    // circumscribed, so the octagon covers the circle of the corridor's width
+    let radius = half_width / std::f64::consts::FRAC_PI_8.cos();
+    for point in shape {
+        let exterior: Vec<_> = (0..8)
+            .map(|i| {
+                let angle = f64::from(i) * std::f64::consts::FRAC_PI_4;
+                offset(point, radius * angle.sin(), radius * angle.cos())
+            })
+            .collect();
+        polygons.push(geo_types::Polygon::new(exterior.into(), Vec::new()));
+    }
+    geo_types::MultiPolygon::new(polygons)
+}
+
+/// Splits a shape where it crosses the anti-meridian (`±180°` longitude)
+///
+/// Valhalla returns longitudes in `-180..=180`, so shapes crossing the anti-meridian jump from
+/// `180` to `-180`. Rendered as one line, such a shape spans the whole globe.
+/// Each part ends (or starts) with a point interpolated on the anti-meridian.
+pub fn split_at_antimeridian(shape: &[ShapePoint]) -> Vec<Vec<ShapePoint>> {
+    let mut parts = Vec::new();
+    let mut part: Vec<ShapePoint> = Vec::new();
+    for point in shape {
+        if let Some(previous) = part.last() {
+            let delta_lon = point.lon - previous.lon;
+            if delta_lon.abs() > 180.0 {
+                let edge = 180_f64.copysign(previous.lon);
+                let unwrapped_lon = previous.lon + wrap_longitude(delta_lon);
+                let t = (edge - previous.lon) / (unwrapped_lon - previous.lon);
+                let lat = previous.lat + t * (point.lat - previous.lat);
+                part.push(ShapePoint { lon: edge, lat });
+                parts.push(std::mem::take(&mut part));
+                part.push(ShapePoint { lon: -edge, lat });
+            }
+        }
+        part.push(point.clone());
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+/// Resamples a shape with a point every `spacing` meters along it, keeping the first and last point
+///
+/// Points are interpolated linearly between the points of the shape, e.g. to turn a sparse,
+/// freehand sketch into a dense sequence of waypoints. Corners of the shape in between are not kept.
+/// Returns the shape unchanged if it has less than two points or `spacing` is not positive.
+pub fn densify(shape: &[ShapePoint], spacing: f64) -> Vec<ShapePoint> {
+    let (Some(first), Some(last)) = (shape.first(), shape.last()) else {
+        return Vec::new();
+    };
+    if shape.len() < 2 || spacing <= 0.0 || !spacing.is_finite() {
+        return shape.to_vec();
+    }
+    let mut points = vec![first.clone()];
+    // the distance along the shape to the next point to insert
+    let mut next = spacing;
+    let mut start = 0.0;
+    for segment in shape.windows(2) {
+        let length = segment[0].distance_to(&segment[1]);
+        while next < start + length {
+            let t = (next - start) / length;
+            let lon = segment[0].lon + t * wrap_longitude(segment[1].lon - segment[0].lon);
+            points.push(ShapePoint {
+                lon: wrap_longitude(lon),
+                lat: segment[0].lat + t * (segment[1].lat - segment[0].lat),
+            });
+            next += spacing;
+        }
+        start += length;
+    }
+    // avoids a point right before the last one, which would be a detour for the router
+    if points.len() > 1
+        && points
+            .last()
+            .is_some_and(|p| p.distance_to(last) < spacing / 2.0)
+    {
+        points.pop();
+    }
+    points.push(last.clone());
+    points
+}
+
+/// Removes [invalid](ShapePoint::is_valid) points and consecutive duplicate points
+///
+/// Returns the new index of each original point, so indices into the shape can be updated.
+/// A removed point maps to the point kept before it (or to `0` if there is none).
+/// See [`crate::route::Leg::normalize_shape`] for a leg's shape and maneuvers.
+pub fn normalize(shape: &mut Vec<ShapePoint>) -> Vec<usize> {
+    let mut new_indices = Vec::with_capacity(shape.len());
+    let mut kept: Vec<ShapePoint> = Vec::with_capacity(shape.len());
+    for point in shape.drain(..) {
+        if point.is_valid() && kept.last() != Some(&point) {
+            kept.push(point);
+        }
+        new_indices.push(kept.len().saturating_sub(1));
+    }
+    *shape = kept;
+    new_indices
+}
+
+/// The bearing in degrees of the shape at the point `index`, clockwise from north
+///
+/// This is the bearing towards the next distinct point, or for the last point the bearing from the
+/// previous distinct point, e.g. to orient a vehicle marker.
+/// Returns `None` if `index` is out of range or all points are identical.
+pub fn bearing_at(shape: &[ShapePoint], index: usize) -> Option<f64> {
+    let point = shape.get(index)?;
+    if let Some(next) = shape[index + 1..].iter().find(|p| *p != point) {
+        return Some(point.bearing_to(next));
+    }
+    let previous = shape[..index].iter().rev().find(|p| *p != point)?;
+    Some(previous.bearing_to(point))
+}
+
+/// The bearing in degrees at the start of the shape, see [`bearing_at`]
+pub fn initial_bearing(shape: &[ShapePoint]) -> Option<f64> {
+    bearing_at(shape, 0)
+}
+
+/// The bearing in degrees at the end of the shape, see [`bearing_at`]
+pub fn final_bearing(shape: &[ShapePoint]) -> Option<f64> {
+    bearing_at(shape, shape.len().checked_sub(1)?)
+}
+
+/// Decodes a polyline with `precision` decimal places, e.g. `5` for [`ShapeFormat::Polyline5`]
+///
+/// # Example:
+/// ```rust
+/// use valhalla_client::shapes::{decode_polyline, to_polyline5};
+///
+/// let shape = decode_polyline("_p~iF~ps|U_ulLnnqC", 5).unwrap();
+/// assert_eq!((shape[1].lon, shape[1].lat), (-120.95, 40.7));
+/// assert_eq!(to_polyline5(&shape), "_p~iF~ps|U_ulLnnqC");
+/// ```
+///
+/// Returns `None` if the polyline is truncated or contains characters which are not part of the encoding.
+pub fn decode_polyline(encoded: &str, precision: i32) -> Option<Vec<ShapePoint>> {
+    let inv = 1.0 / 10_f64.powi(precision);
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::new();
+    let mut previous = [0_i64; 2];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        for value in &mut previous {
+            let mut delta = 0_i64;
+            let mut shift = 0_u32;
+            loop {
+                // each character carries 5 bits of the delta, offset by 63
+                let chunk = bytes.get(i)?.checked_sub(63).filter(|&c| c < 0x40)?;
                 i += 1;
-                ll[j] |= (byte & 0x1f) << shift;
+                delta |= i64::from(chunk & 0x1f).checked_shl(shift)?;
+                if chunk < 0x20 {
+                    break;
+                }
                 shift += 5;
             }
-
-            ll[j] = previous[j] + ((!ll[j] >> 1) ^ -(ll[j] & 1));
-            previous[j] = ll[j];
+            *value = value.checked_add((delta >> 1) ^ -(delta & 1))?;
         }
 
         decoded.push(ShapePoint {
-            lon: -1.0 * f64::from(ll[1]) * inv,
-            lat: -1.0 * f64::from(ll[0]) * inv,
+            lon: previous[1] as f64 * inv,
+            lat: previous[0] as f64 * inv,
         });
     }
 
-    decoded
+    Some(decoded)
+}
+/// Encodes a shape compactly for storage, the inverse of [`from_bytes`]
+///
+/// Like a polyline, coordinates are rounded to 6 decimal places and delta-encoded, but each delta
+/// is stored as a binary varint instead of printable characters. Each byte carries 7 instead of
+/// 5 bits of the delta, so the encoding is never larger than the equivalent [`to_polyline6`].
+/// How much smaller it is depends on the magnitude of the deltas, and thus on the shape.
+pub fn to_bytes(shape: &[ShapePoint]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(shape.len() * 4);
+    let mut previous = [0_i64; 2];
+    for point in shape {
+        for (j, degrees) in [point.lat, point.lon].into_iter().enumerate() {
+            let value = (degrees * 1e6).round() as i64;
+            let delta = value - previous[j];
+            previous[j] = value;
+
+            let mut zigzag = ((delta << 1) ^ (delta >> 63)) as u64;
+            while zigzag >= 0x80 {
+                bytes.push((zigzag & 0x7f) as u8 | 0x80);
+                zigzag >>= 7;
+            }
+            bytes.push(zigzag as u8);
+        }
+    }
+    bytes
+}
+
+/// Decodes a shape encoded via [`to_bytes`]
+///
+/// Returns `None` if the bytes are truncated or corrupted.
+pub fn from_bytes(bytes: &[u8]) -> Option<Vec<ShapePoint>> {
+    let mut shape = Vec::with_capacity(bytes.len() / 4);
+    let mut previous = [0_i64; 2];
+    // 0 while decoding the latitude, 1 while decoding the longitude of a point
+    let mut coordinate = 0;
+    let (mut value, mut shift) = (0_u64, 0);
+    for &byte in bytes {
+        value |= u64::from(byte & 0x7f).checked_shl(shift)?;
+        if byte & 0x80 != 0 {
+            shift += 7;
+            continue;
+        }
+        let delta = (value >> 1) as i64 ^ -((value & 1) as i64);
+        previous[coordinate] = previous[coordinate].checked_add(delta)?;
+        (value, shift) = (0, 0);
+        if coordinate == 1 {
+            shape.push(ShapePoint {
+                lon: previous[1] as f64 / 1e6,
+                lat: previous[0] as f64 / 1e6,
+            });
+        }
+        coordinate ^= 1;
+    }
+    if shift != 0 || coordinate != 0 {
+        return None;
+    }
+    Some(shape)
+}
+
+/// Encodes a shape as [`ShapeFormat::Polyline5`], as expected by Google's Encoded Polyline Algorithm
+///
+/// Useful for web map SDKs which only support a precision of 5 decimal places (roughly `1 m`).
+///
+/// # Example:
+/// ```rust
+/// use valhalla_client::shapes::{to_polyline5, ShapePoint};
+///
+/// let shape = [ShapePoint { lon: -120.2, lat: 38.5 }, ShapePoint { lon: -120.95, lat: 40.7 }];
+/// assert_eq!(to_polyline5(&shape), "_p~iF~ps|U_ulLnnqC");
+/// ```
+pub fn to_polyline5(shape: &[ShapePoint]) -> String {
+    encode_shape(shape, 5)
 }
+
+/// Encodes a shape as [`ShapeFormat::Polyline6`], the format valhalla returns by default
+pub fn to_polyline6(shape: &[ShapePoint]) -> String {
+    encode_shape(shape, 6)
+}
+
+/// Encodes a shape as polyline with `precision` decimal places, the inverse of [`decode_polyline`]
+fn encode_shape(shape: &[ShapePoint], precision: i32) -> String {
+    let factor = 10_f64.powi(precision);
+    let mut encoded = String::new();
+    let mut previous = [0, 0];
+    for point in shape {
+        for (j, degrees) in [point.lat, point.lon].into_iter().enumerate() {
+            let value = (degrees * factor).round() as i64;
+            let delta = value - previous[j];
+            previous[j] = value;
+
+            let mut zigzag = (delta << 1) ^ (delta >> 63);
+            while zigzag >= 0x20 {
+                encoded.push(char::from((0x20 | (zigzag & 0x1f)) as u8 + 63));
+                zigzag >>= 5;
+            }
+            encoded.push(char::from(zigzag as u8 + 63));
+        }
+    }
+    encoded
+}
+
+/// A shape as returned by valhalla, depending on the requested [`ShapeFormat`]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EncodedShape {
+    /// [`ShapeFormat::Polyline6`]
+    Polyline(String),
+    /// [`ShapeFormat::GeoJSON`] `LineString`
+    GeoJSON { coordinates: Vec<(f64, f64)> },
+}
+
+/// A shape kept exactly as valhalla returned it, without decoding
+///
+/// Useful for proxies which forward the geometry to web clients as-is.
+/// See [`crate::Valhalla::route_encoded`] for how to request it.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Shape {
+    /// An encoded polyline, as returned for [`ShapeFormat::Polyline6`] and [`ShapeFormat::Polyline5`]
+    Encoded(String),
+    /// A GeoJSON `LineString`, as returned for [`ShapeFormat::GeoJSON`]
+    GeoJSON(serde_json::Value),
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        Self::Encoded(String::new())
+    }
+}
+
+impl Shape {
+    /// Decodes the shape into its points
+    ///
+    /// Returns an empty shape if a [`Self::GeoJSON`] is not a valid `LineString` or an
+    /// [`Self::Encoded`] shape is not a valid polyline.
+    /// Polylines are assumed to be [`ShapeFormat::Polyline6`], see [`Self::decode_as`] for other formats.
+    pub fn decode(&self) -> Vec<ShapePoint> {
+        self.decode_as(ShapeFormat::Polyline6)
+    }
+
+    /// Decodes the shape into its points, given the [`ShapeFormat`] it was requested in
+    pub fn decode_as(&self, format: ShapeFormat) -> Vec<ShapePoint> {
+        match self {
+            Self::Encoded(s) => decode_polyline(s.as_str(), format.precision()).unwrap_or_default(),
+            Self::GeoJSON(v) => EncodedShape::deserialize(v)
+                .ok()
+                .and_then(EncodedShape::decode)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// How a shape is represented after deserializing a response
+///
+/// Implemented for decoded shapes (`Vec<ShapePoint>`) and for shapes passed through as-is ([`Shape`]).
+pub trait ShapeRepresentation: Sized {
+    /// Deserializes the shape from its representation in valhalla's response
+    fn deserialize_shape<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>;
+    /// Serializes the shape such that [`Self::deserialize_shape`] restores it
+    fn serialize_shape<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer;
+}
+
+impl ShapeRepresentation for Vec<ShapePoint> {
+    fn deserialize_shape<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserialize_shape(deserializer)
+    }
+    fn serialize_shape<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialize_shape(self, serializer)
+    }
+}
+
+impl ShapeRepresentation for Shape {
+    fn deserialize_shape<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Self::deserialize(deserializer)
+    }
+    fn serialize_shape<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.serialize(serializer)
+    }
+}
+
+impl EncodedShape {
+    /// Decodes polylines as [`ShapeFormat::Polyline6`], returning `None` if they are invalid
+    fn decode(self) -> Option<Vec<ShapePoint>> {
+        match self {
+            Self::Polyline(s) => decode_polyline(s.as_str(), 6),
+            Self::GeoJSON { coordinates } => Some(
+                coordinates
+                    .into_iter()
+                    .map(|(lon, lat)| ShapePoint { lon, lat })
+                    .collect(),
+            ),
+        }
+    }
+}
+
 pub(crate) fn deserialize_shape<'de, D>(deserializer: D) -> Result<Vec<ShapePoint>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let s = String::deserialize(deserializer)?;
-    Ok(decode_shape(s.as_str()))
+    EncodedShape::deserialize(deserializer)?
+        .decode()
+        .ok_or_else(|| serde::de::Error::custom("invalid polyline"))
+}
+
+/// Deserializes an optional shape as [`ShapeRepresentation`] `R`
+pub(crate) fn deserialize_shape_opt<'de, D, R>(deserializer: D) -> Result<Option<R>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    R: ShapeRepresentation,
+{
+    struct Representation<R>(R);
+    impl<'de, R: ShapeRepresentation> Deserialize<'de> for Representation<R> {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            R::deserialize_shape(deserializer).map(Self)
+        }
+    }
+    Ok(Option::<Representation<R>>::deserialize(deserializer)?.map(|shape| shape.0))
+}
+
+pub(crate) fn serialize_shape<S>(shape: &[ShapePoint], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&encode_shape(shape, 6))
+}
+
+/// Serializes an optional shape such that [`deserialize_shape_opt`] restores it
+pub(crate) fn serialize_shape_opt<S, R>(shape: &Option<R>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    R: ShapeRepresentation,
+{
+    match shape {
+        None => serializer.serialize_none(),
+        Some(shape) => shape.serialize_shape(serializer),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[derive(Deserialize)]
+    struct Shaped {
+        #[serde(deserialize_with = "deserialize_shape")]
+        shape: Vec<ShapePoint>,
+    }
+    #[test]
+    fn deserialisation() {
+        let polyline: Shaped = serde_json::from_str(r#"{"shape": "_izlhA~rlgdF"}"#).unwrap();
+        let geojson: Shaped = serde_json::from_str(
+            r#"{"shape": {"type": "LineString", "coordinates": [[-120.2, 38.5]]}}"#,
+        )
+        .unwrap();
+        let passthrough: Shape = serde_json::from_str(r#""_izlhA~rlgdF""#).unwrap();
+        assert_eq!(passthrough, Shape::Encoded("_izlhA~rlgdF".to_string()));
+        for shape in [polyline.shape, geojson.shape, passthrough.decode()] {
+            assert_eq!(shape.len(), 1);
+            assert!((shape[0].lon - -120.2).abs() < 1e-6);
+            assert!((shape[0].lat - 38.5).abs() < 1e-6);
+        }
+    }
+    #[test]
+    fn invalid_polylines() {
+        assert_eq!(decode_polyline("", 6), Some(Vec::new()));
+        // truncated within a coordinate and after the latitude
+        assert_eq!(decode_polyline("_izlh", 6), None);
+        assert_eq!(decode_polyline("_izlhA", 6), None);
+        // non-ASCII and characters outside of the encoding
+        assert_eq!(decode_polyline("_izlhA~rlgdFä", 6), None);
+        assert_eq!(decode_polyline("_izlhA ~rlgdF", 6), None);
+        // a never ending delta
+        assert_eq!(decode_polyline(&"~".repeat(64), 6), None);
+        assert!(serde_json::from_str::<Shaped>(r#"{"shape": "_izlh"}"#).is_err());
+        assert!(Shape::Encoded("_izlh".to_string()).decode().is_empty());
+    }
+    #[test]
+    fn densification() {
+        let sketch = [
+            ShapePoint { lon: 0.0, lat: 0.0 },
+            ShapePoint {
+                lon: 0.0,
+                lat: 0.01,
+            },
+            ShapePoint {
+                lon: 0.01,
+                lat: 0.01,
+            },
+        ];
+        // each segment is about 1112m long
+        let points = densify(&sketch, 500.0);
+        assert_eq!(points.len(), 5, "no point 224m before the end");
+        assert_eq!(points.first(), sketch.first());
+        assert_eq!(points.last(), sketch.last());
+        assert!((points[1].distance_to(&sketch[0]) - 500.0).abs() < 0.1);
+        assert!((points[3].lat - 0.01).abs() < 1e-9, "turns the corner");
+        assert_eq!(densify(&sketch, 0.0), sketch);
+        assert_eq!(
+            densify(&sketch, 5_000.0),
+            [sketch[0].clone(), sketch[2].clone()]
+        );
+        assert!(densify(&[], 10.0).is_empty());
+    }
+    #[test]
+    fn normalization() {
+        let point = |lon, lat| ShapePoint { lon, lat };
+        let mut shape = vec![
+            point(f64::NAN, 0.0),
+            point(4.0, 52.0),
+            point(4.0, 52.0),
+            point(4.0, 91.0),
+            point(4.1, 52.0),
+            point(4.0, 52.0),
+        ];
+        assert_eq!(normalize(&mut shape), [0, 0, 0, 0, 1, 2]);
+        assert_eq!(
+            shape,
+            [point(4.0, 52.0), point(4.1, 52.0), point(4.0, 52.0)]
+        );
+    }
+    #[test]
+    fn antimeridian() {
+        let point = |lon, lat| ShapePoint { lon, lat };
+        let shape = [point(179.0, 10.0), point(-179.0, 12.0), point(-178.0, 12.0)];
+        assert_eq!(
+            split_at_antimeridian(&shape),
+            [
+                vec![point(179.0, 10.0), point(180.0, 11.0)],
+                vec![
+                    point(-180.0, 11.0),
+                    point(-179.0, 12.0),
+                    point(-178.0, 12.0)
+                ],
+            ]
+        );
+        assert_eq!(split_at_antimeridian(&shape[1..]), [shape[1..].to_vec()]);
+        assert!(split_at_antimeridian(&[]).is_empty());
+        assert_eq!(wrap_longitude(350.0), -10.0);
+        assert_eq!(wrap_longitude(-358.0), 2.0);
+    }
+    #[test]
+    fn corridor_around_shape() {
+        let shape = [
+            ShapePoint {
+                lon: 4.9,
+                lat: 52.0,
+            },
+            ShapePoint {
+                lon: 4.9,
+                lat: 52.01,
+            },
+            ShapePoint {
+                lon: 4.91,
+                lat: 52.01,
+            },
+        ];
+        let polygons = corridor(&shape, 100.0);
+        // two segments and three joins
+        assert_eq!(polygons.0.len(), 5);
+        let first = polygons.0[0].exterior();
+        let corner = |i: usize| ShapePoint {
+            lon: first[i].x,
+            lat: first[i].y,
+        };
+        assert!((corner(0).distance_to(&shape[0]) - 50.0).abs() < 0.1);
+        assert!((corner(0).distance_to(&corner(3)) - 100.0).abs() < 0.1);
+        assert!(corner(0).lon < shape[0].lon, "left of a northbound segment");
+        assert_eq!(corridor(&shape[..1], 100.0).0.len(), 1);
+        assert!(corridor(&[], 100.0).0.is_empty());
+    }
+    #[test]
+    fn binary_encoding() {
+        let shape: Vec<_> = (0..100)
+            .map(|i| ShapePoint {
+                lon: 4.9041 + f64::from(i) * 0.000_13,
+                lat: 52.3676 - f64::from(i) * 0.000_07,
+            })
+            .chain([ShapePoint {
+                lon: -179.999_999,
+                lat: -89.0,
+            }])
+            .collect();
+        let bytes = to_bytes(&shape);
+        assert!(bytes.len() < encode_shape(&shape, 6).len());
+        let decoded = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.len(), shape.len());
+        for (a, b) in decoded.iter().zip(&shape) {
+            assert!((a.lon - b.lon).abs() < 1e-6 && (a.lat - b.lat).abs() < 1e-6);
+        }
+        assert_eq!(from_bytes(&[]), Some(Vec::new()));
+        assert_eq!(from_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(from_bytes(&[0x80]), None);
+
+        // deltas of i64::MAX and 1 overflow the latitude
+        let mut overflowing = to_bytes(&[]);
+        for delta in [i64::MAX, 0, 1, 0] {
+            let mut zigzag = ((delta << 1) ^ (delta >> 63)) as u64;
+            while zigzag >= 0x80 {
+                overflowing.push((zigzag & 0x7f) as u8 | 0x80);
+                zigzag >>= 7;
+            }
+            overflowing.push(zigzag as u8);
+        }
+        assert_eq!(from_bytes(&overflowing), None);
+    }
+    #[test]
+    fn binary_encoding_size() {
+        // a road in 1 m steps, a trip in 100 m steps and points spread across the globe
+        for step in [1e-5, 1e-3, 10.0] {
+            let shape: Vec<_> = (0..500)
+                .map(|i| ShapePoint {
+                    lon: (f64::from(i) * step * 1.3) % 360.0 - 180.0,
+                    lat: (f64::from(i) * step * 0.7) % 180.0 - 90.0,
+                })
+                .collect();
+            let bytes = to_bytes(&shape).len();
+            assert!(bytes < to_polyline6(&shape).len(), "step {step}");
+        }
+    }
+    #[test]
+    fn approximate_equality() {
+        let a = [
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.0,
+            },
+            ShapePoint {
+                lon: 180.0,
+                lat: 52.001,
+            },
+        ];
+        let b = [
+            ShapePoint {
+                lon: 4.000_000_4,
+                lat: 51.999_999_6,
+            },
+            ShapePoint {
+                lon: -180.0,
+                lat: 52.001,
+            },
+        ];
+        assert!(a[0] != b[0]);
+        assert!(approx_eq(&a, &b, 1e-6));
+        assert!(!approx_eq(&a, &b, 1e-7));
+        assert!(!approx_eq(&a, &b[..1], 1e-6));
+
+        let sampled = [
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.0,
+            },
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.000_5,
+            },
+            ShapePoint {
+                lon: 4.0,
+                lat: 52.001,
+            },
+        ];
+        let distance = hausdorff_distance(&sampled, &[sampled[0].clone(), sampled[2].clone()]);
+        assert!((distance.unwrap() - 55.6).abs() < 0.1);
+        assert_eq!(hausdorff_distance(&sampled, &sampled), Some(0.0));
+        assert_eq!(hausdorff_distance(&sampled, &[]), None);
+    }
+    #[test]
+    fn bearings() {
+        let point = |lon, lat| ShapePoint { lon, lat };
+        let shape = [
+            point(0.0, 0.0),
+            point(0.0, 0.0),
+            point(0.0, 1.0),
+            point(1.0, 1.0),
+        ];
+        assert_eq!(initial_bearing(&shape), Some(0.0));
+        assert_eq!(bearing_at(&shape, 1), Some(0.0));
+        assert!((bearing_at(&shape, 2).unwrap() - 90.0).abs() < 0.01);
+        assert!((final_bearing(&shape).unwrap() - 90.0).abs() < 0.01);
+        assert_eq!(bearing_at(&shape, 4), None);
+        assert_eq!(final_bearing(&[]), None);
+        assert_eq!(initial_bearing(&shape[..2]), None);
+    }
+    #[test]
+    fn encoding() {
+        assert_eq!(
+            encode_shape(
+                &[ShapePoint {
+                    lon: -120.2,
+                    lat: 38.5
+                }],
+                6
+            ),
+            "_izlhA~rlgdF"
+        );
+        let shape = [
+            ShapePoint {
+                lon: 4.9041,
+                lat: 52.3676,
+            },
+            ShapePoint {
+                lon: 4.9042,
+                lat: 52.3675,
+            },
+            ShapePoint {
+                lon: -179.999_999,
+                lat: -89.0,
+            },
+        ];
+        let decoded = decode_polyline(&encode_shape(&shape, 6), 6).unwrap();
+        assert_eq!(decoded.len(), shape.len());
+        for (a, b) in decoded.iter().zip(&shape) {
+            assert!((a.lon - b.lon).abs() < 1e-6 && (a.lat - b.lat).abs() < 1e-6);
+        }
+        let google = [
+            ShapePoint {
+                lon: -120.2,
+                lat: 38.5,
+            },
+            ShapePoint {
+                lon: -120.95,
+                lat: 40.7,
+            },
+            ShapePoint {
+                lon: -126.453,
+                lat: 43.252,
+            },
+        ];
+        assert_eq!(to_polyline5(&google), "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+        let polyline5 = Shape::Encoded(to_polyline5(&google));
+        for (a, b) in polyline5
+            .decode_as(ShapeFormat::Polyline5)
+            .iter()
+            .zip(&google)
+        {
+            assert!((a.lon - b.lon).abs() < 1e-5 && (a.lat - b.lat).abs() < 1e-5);
+        }
+        assert!(
+            (polyline5.decode()[0].lat - 3.85).abs() < 1e-6,
+            "misread as polyline6"
+        );
+    }
 }
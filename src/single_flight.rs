@@ -0,0 +1,121 @@
+//! Coalescing of identical requests which are in flight at the same time
+//!
+//! See [`crate::Valhalla::coalesce_requests`] for how to enable coalescing.
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// The result of the request in flight, `None` while it is still pending
+struct Flight<V> {
+    /// `Some(None)` if the request failed, in which case waiting callers send their own request
+    result: Mutex<Option<Option<V>>>,
+    done: Condvar,
+}
+
+/// Runs at most one request per key at the same time, sharing successful results with all callers
+pub(crate) struct SingleFlight<V> {
+    flights: Mutex<HashMap<String, Arc<Flight<V>>>>,
+}
+
+impl<V> Default for SingleFlight<V> {
+    fn default() -> Self {
+        Self {
+            flights: Mutex::default(),
+        }
+    }
+}
+
+impl<V: Clone> SingleFlight<V> {
+    /// Runs `f`, unless a call with the same `key` is already running, in which case its result is shared
+    ///
+    /// Errors are not shared: if the running call fails, the waiting callers run `f` themselves.
+    pub(crate) fn run<E>(&self, key: String, f: impl FnOnce() -> Result<V, E>) -> Result<V, E> {
+        let (flight, is_leader) = {
+            let mut flights = self.flights.lock().unwrap_or_else(|e| e.into_inner());
+            match flights.get(&key) {
+                Some(flight) => (flight.clone(), false),
+                None => {
+                    let flight = Arc::new(Flight {
+                        result: Mutex::new(None),
+                        done: Condvar::new(),
+                    });
+                    flights.insert(key.clone(), flight.clone());
+                    (flight, true)
+                }
+            }
+        };
+        if !is_leader {
+            let result = flight.result.lock().unwrap_or_else(|e| e.into_inner());
+            let shared = flight
+                .done
+                .wait_while(result, |result| result.is_none())
+                .unwrap_or_else(|e| e.into_inner())
+                .clone()
+                .flatten();
+            return match shared {
+                Some(value) => Ok(value),
+                None => f(),
+            };
+        }
+        // publishes the result, or the failure if `f` errors or panics
+        let mut landing = Landing {
+            flights: &self.flights,
+            key,
+            flight: &flight,
+            value: None,
+        };
+        let result = f();
+        landing.value = result.as_ref().ok().cloned();
+        result
+    }
+}
+
+struct Landing<'a, V> {
+    flights: &'a Mutex<HashMap<String, Arc<Flight<V>>>>,
+    key: String,
+    flight: &'a Flight<V>,
+    value: Option<V>,
+}
+
+impl<V> Drop for Landing<'_, V> {
+    fn drop(&mut self) {
+        let mut flights = self.flights.lock().unwrap_or_else(|e| e.into_inner());
+        flights.remove(&self.key);
+        let mut result = self.flight.result.lock().unwrap_or_else(|e| e.into_inner());
+        *result = Some(self.value.take());
+        self.flight.done.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[test]
+    fn coalesces() {
+        let flights = SingleFlight::default();
+        let calls = AtomicUsize::new(0);
+        let results: Vec<Result<u32, ()>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        flights.run("key".to_string(), || {
+                            calls.fetch_add(1, Ordering::SeqCst);
+                            std::thread::sleep(std::time::Duration::from_millis(50));
+                            Ok(42)
+                        })
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(results.iter().all(|r| *r == Ok(42)));
+        assert!(calls.load(Ordering::SeqCst) < 8, "some calls are coalesced");
+
+        // failures are not shared, and the key is free again afterwards
+        assert_eq!(
+            flights.run("key".to_string(), || Err::<u32, _>(())),
+            Err(())
+        );
+        assert_eq!(flights.run("key".to_string(), || Ok::<_, ()>(1)), Ok(1));
+    }
+}
@@ -0,0 +1,157 @@
+//! The version and loaded data of an instance, e.g. for health checks of a deployment
+//!
+//! See <https://valhalla.github.io/valhalla/api/status/api-reference/> for details
+//!
+//! # Example:
+//! ```rust,no_run
+//! use valhalla_client::Valhalla;
+//!
+//! let status = Valhalla::default().status_verbose().unwrap();
+//! println!("valhalla {} with tiles from {:?}", status.version, status.last_modified());
+//! assert!(status.supports("route"));
+//! assert_eq!(status.has_tiles, Some(true));
+//! ```
+use serde::{Deserialize, Serialize};
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Manifest {
+    pub(crate) verbose: Option<bool>,
+}
+
+/// The status of an instance, see [`crate::Valhalla::status`]
+///
+/// Except for the version, the attributes are only known if requested via [`crate::Valhalla::status_verbose`].
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Status {
+    /// The version of valhalla, e.g. `3.5.1`
+    pub version: String,
+    /// When the routing tiles were last modified, as seconds since the unix epoch, see [`Self::last_modified`]
+    pub tileset_last_modified: Option<i64>,
+    /// The actions the instance serves, e.g. `route` or `isochrone`
+    pub available_actions: Option<Vec<String>>,
+    pub has_tiles: Option<bool>,
+    /// Whether administrative areas are loaded, e.g. for country crossings and driving sides
+    pub has_admins: Option<bool>,
+    /// Whether timezones are loaded, needed for time dependent routes
+    pub has_timezones: Option<bool>,
+    /// Whether live traffic is loaded
+    pub has_live_traffic: Option<bool>,
+    /// Whether transit tiles are loaded, needed for [`crate::costing::Costing::Multimodal`]
+    pub has_transit_tiles: Option<bool>,
+    /// The extent of the tiles as GeoJSON `FeatureCollection`, see [`Self::covers`]
+    pub bbox: Option<serde_json::Value>,
+    /// The latest OpenStreetMap changeset in the tiles
+    pub osm_changeset: Option<u64>,
+    /// Further attributes, e.g. `warnings`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Status {
+    /// When the routing tiles were last modified
+    pub fn last_modified(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.tileset_last_modified?, 0)
+    }
+    /// Whether the instance serves the `action`, e.g. `route`
+    ///
+    /// `false` if the actions are not known, see [`Self::available_actions`].
+    pub fn supports(&self, action: &str) -> bool {
+        self.available_actions
+            .iter()
+            .flatten()
+            .any(|available| available == action)
+    }
+    /// Whether the point lies within the extent of the tiles, i.e. the polygons of [`Self::bbox`]
+    ///
+    /// `None` if the extent is not known, see [`crate::Valhalla::status_verbose`].
+    /// A point within the extent may still be far from any road, see [`crate::Valhalla::is_covered`].
+    pub fn covers(&self, point: crate::Coordinate) -> Option<bool> {
+        let mut polygons = Vec::new();
+        collect_polygons(self.bbox.as_ref()?, &mut polygons);
+        let point = geo_types::Point::new(f64::from(point.0), f64::from(point.1));
+        Some(
+            polygons
+                .iter()
+                .any(|polygon| crate::geometry::contains(polygon, point)),
+        )
+    }
+}
+
+/// The polygons of a GeoJSON `FeatureCollection`, `Feature` or geometry
+fn collect_polygons(geojson: &serde_json::Value, polygons: &mut Vec<geo_types::Polygon<f64>>) {
+    match geojson["type"].as_str() {
+        Some("FeatureCollection") => geojson["features"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .for_each(|feature| collect_polygons(feature, polygons)),
+        Some("Feature") => collect_polygons(&geojson["geometry"], polygons),
+        _ => match crate::isochrone::geometry::deserialize(geojson) {
+            Ok(geo_types::Geometry::Polygon(polygon)) => polygons.push(polygon),
+            Ok(geo_types::Geometry::MultiPolygon(multi)) => polygons.extend(multi),
+            _ => {}
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn covers() {
+        let square = |min: f64, max: f64| {
+            serde_json::json!([[min, min], [max, min], [max, max], [min, max], [min, min]])
+        };
+        let status: Status = serde_json::from_value(serde_json::json!({
+            "version": "3.5.1",
+            "bbox": {"type": "FeatureCollection", "features": [
+                {"type": "Feature", "properties": {}, "geometry": {
+                    "type": "Polygon", "coordinates": [square(0.0, 10.0), square(4.0, 6.0)],
+                }},
+                {"type": "Feature", "properties": {}, "geometry": {
+                    "type": "MultiPolygon", "coordinates": [[square(20.0, 30.0)]],
+                }},
+            ]},
+        }))
+        .unwrap();
+        assert_eq!(status.covers((1.0, 2.0)), Some(true));
+        assert_eq!(status.covers((5.0, 5.0)), Some(false), "in the hole");
+        assert_eq!(status.covers((25.0, 21.0)), Some(true));
+        assert_eq!(status.covers((15.0, 15.0)), Some(false));
+    }
+    #[test]
+    fn deserialisation() {
+        let status: Status = serde_json::from_value(serde_json::json!({
+            "version": "3.5.1",
+            "tileset_last_modified": 1_730_000_000,
+            "available_actions": ["status", "route", "locate"],
+            "has_tiles": true,
+            "has_transit_tiles": false,
+            "bbox": {"type": "FeatureCollection", "features": []},
+        }))
+        .unwrap();
+        assert!(status.supports("route"));
+        assert!(!status.supports("isochrone"));
+        assert_eq!(
+            status.last_modified().unwrap().to_rfc3339(),
+            "2024-10-27T03:33:20+00:00"
+        );
+
+        assert_eq!(status.covers((4.9, 52.4)), Some(false));
+
+        let status: Status =
+            serde_json::from_value(serde_json::json!({"version": "3.5.1"})).unwrap();
+        assert_eq!(status.covers((4.9, 52.4)), None);
+        assert_eq!(status.last_modified(), None);
+        assert!(!status.supports("route"));
+        assert_eq!(
+            serde_json::to_value(Manifest {
+                verbose: Some(true)
+            })
+            .unwrap(),
+            serde_json::json!({"verbose": true})
+        );
+    }
+}
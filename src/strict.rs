@@ -0,0 +1,63 @@
+//! Detection of schema drift between valhalla and this crate
+//!
+//! See [`crate::Valhalla::strict_parsing`] for how to enable these checks.
+use serde_json::Value;
+
+/// Checks that a response was parsed without losing information
+///
+/// `received` is the response as sent by valhalla, `parsed` the serialization of the parsed response.
+/// Fails with the path of the first `null` value in `received`, or the first key of `received` which
+/// is missing in `parsed`, i.e. which is unknown to this crate.
+pub(crate) fn check(received: &Value, parsed: &Value) -> Result<(), String> {
+    check_at("", received, parsed)
+}
+
+fn check_at(path: &str, received: &Value, parsed: &Value) -> Result<(), String> {
+    match (received, parsed) {
+        (Value::Null, _) => Err(format!("unexpected null at `{path}`")),
+        (Value::Object(received), Value::Object(parsed)) => {
+            for (key, value) in received {
+                let path = format!("{path}/{key}");
+                match parsed.get(key) {
+                    Some(parsed) => check_at(&path, value, parsed)?,
+                    None => return Err(format!("unknown field `{path}`")),
+                }
+            }
+            Ok(())
+        }
+        (Value::Array(received), Value::Array(parsed)) if received.len() == parsed.len() => {
+            received
+                .iter()
+                .zip(parsed)
+                .enumerate()
+                .try_for_each(|(i, (received, parsed))| {
+                    check_at(&format!("{path}/{i}"), received, parsed)
+                })
+        }
+        // values with a different representation after parsing, e.g. decoded shapes
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn detects_drift() {
+        let received =
+            serde_json::json!({"a": 1, "b": [{"c": 2.0}], "shape": {"type": "LineString"}});
+        let parsed = serde_json::json!({"a": 1, "b": [{"c": 2}], "shape": "", "units": "miles"});
+        assert_eq!(check(&received, &parsed), Ok(()));
+
+        let received = serde_json::json!({"a": 1, "b": [{"c": 2, "d": 3}]});
+        assert_eq!(
+            check(&received, &parsed),
+            Err("unknown field `/b/0/d`".to_string())
+        );
+        let received = serde_json::json!({"a": null});
+        assert_eq!(
+            check(&received, &parsed),
+            Err("unexpected null at `/a`".to_string())
+        );
+    }
+}
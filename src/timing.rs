@@ -0,0 +1,110 @@
+//! Latency of requests, split into the time spent on the network and on the server
+//!
+//! See [`crate::Valhalla::timing_observer`] for how to observe the timing of every request.
+use reqwest::header::HeaderMap;
+use std::time::Duration;
+
+/// A hook installed via [`crate::Valhalla::timing_observer`]
+pub(crate) type Observer = std::sync::Arc<dyn Fn(&Timing) + Send + Sync>;
+
+/// How long a request took
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Timing {
+    /// The action (endpoint) the request was sent to, e.g. `route`
+    pub action: &'static str,
+    /// The wall-clock time from sending the request until the response body was received
+    ///
+    /// Does not include time spent waiting in the [`crate::Valhalla::request_queue`].
+    pub latency: Duration,
+    /// The time the server reports to have spent on the request
+    ///
+    /// Taken from the `total` (or first) metric of the `Server-Timing` header, or from the
+    /// `X-Response-Time` header. `None` if neither is sent, which is the case for valhalla
+    /// without a proxy in front of it.
+    pub server_time: Option<Duration>,
+    /// The address of the server which answered, e.g. to check which endpoint behind a hostname was used
+    ///
+    /// See [`crate::Valhalla::resolve`] for pinning the addresses. `None` if a middleware answered
+    /// the request without sending it.
+    pub remote_addr: Option<std::net::SocketAddr>,
+}
+
+impl Timing {
+    /// The time spent outside the server, e.g. on the network, if the server reports its time
+    pub fn network_time(&self) -> Option<Duration> {
+        Some(self.latency.saturating_sub(self.server_time?))
+    }
+}
+
+/// Parses the time the server reports to have spent on a request
+pub(crate) fn server_time(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok();
+    header("server-timing")
+        .and_then(parse_server_timing)
+        .or_else(|| header("x-response-time").and_then(parse_response_time))
+}
+
+/// Parses e.g. `db;dur=53, total;dur=123.4` into the `total` (or first) duration
+fn parse_server_timing(value: &str) -> Option<Duration> {
+    let metrics: Vec<(&str, f64)> = value
+        .split(',')
+        .filter_map(|metric| {
+            let mut params = metric.split(';').map(str::trim);
+            let name = params.next()?;
+            let millis = params.find_map(|p| p.strip_prefix("dur="))?.parse().ok()?;
+            Some((name, millis))
+        })
+        .collect();
+    let (_, millis) = metrics
+        .iter()
+        .find(|(name, _)| *name == "total")
+        .or(metrics.first())?;
+    Duration::try_from_secs_f64(millis / 1_000.0).ok()
+}
+
+/// Parses e.g. `123.4ms`, `0.12s` or `123` (milliseconds)
+fn parse_response_time(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (number, factor) = if let Some(millis) = value.strip_suffix("ms") {
+        (millis, 1e-3)
+    } else if let Some(seconds) = value.strip_suffix('s') {
+        (seconds, 1.0)
+    } else {
+        (value, 1e-3)
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    Duration::try_from_secs_f64(number * factor).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn server_time_from_headers() {
+        assert_eq!(server_time(&HeaderMap::new()), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Response-Time", "12.5ms".parse().unwrap());
+        assert_eq!(server_time(&headers), Some(Duration::from_micros(12_500)));
+        headers.insert("Server-Timing", "db;dur=53, total;dur=120".parse().unwrap());
+        assert_eq!(server_time(&headers), Some(Duration::from_millis(120)));
+        headers.insert("Server-Timing", "db;desc=\"x\";dur=53".parse().unwrap());
+        assert_eq!(server_time(&headers), Some(Duration::from_millis(53)));
+
+        assert_eq!(
+            parse_response_time("0.5s"),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(parse_response_time("7"), Some(Duration::from_millis(7)));
+        assert_eq!(parse_response_time("-1"), None);
+
+        let timing = Timing {
+            action: "route",
+            latency: Duration::from_millis(200),
+            server_time: Some(Duration::from_millis(120)),
+            remote_addr: None,
+        };
+        assert_eq!(timing.network_time(), Some(Duration::from_millis(80)));
+    }
+}
@@ -0,0 +1,1098 @@
+//! Timestamped GPS traces, e.g. recorded by a vehicle, and their matching to the road network
+//!
+//! See [`crate::Valhalla::trace_route`] for map matching a trace and [`crate::Valhalla::trace_attributes`]
+//! for the attributes of the matched roads.
+//!
+//! # Example:
+//! ```rust
+//! use chrono::{NaiveDate, TimeDelta};
+//! use valhalla_client::shapes::ShapePoint;
+//! use valhalla_client::trace::{detect_stops, TracePoint};
+//!
+//! let start = NaiveDate::from_ymd_opt(2024, 11, 7).unwrap().and_hms_opt(8, 0, 0).unwrap();
+//! let trace: Vec<_> = (0..10)
+//!   .map(|i| TracePoint {
+//!     point: ShapePoint { lon: 4.9041, lat: 52.3676 },
+//!     time: start + TimeDelta::minutes(i),
+//!   })
+//!   .collect();
+//! let stops = detect_stops(&trace, 50.0, TimeDelta::minutes(5));
+//! assert_eq!(stops[0].duration(), TimeDelta::minutes(9));
+//! ```
+use crate::costing;
+use crate::route::{DirectionsType, LocationType};
+use crate::shapes::{Shape, ShapeFormat, ShapePoint, ShapeRepresentation};
+use serde::{Deserialize, Serialize};
+
+/// A recorded position
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracePoint {
+    pub point: ShapePoint,
+    /// When the position was recorded
+    pub time: chrono::NaiveDateTime,
+}
+
+/// A place where a trace dwells, see [`detect_stops`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stop {
+    /// The index of the first point of the trace at the stop
+    pub first_index: usize,
+    /// The index of the last point of the trace at the stop
+    pub last_index: usize,
+    /// The mean position of the points at the stop
+    pub location: ShapePoint,
+    /// When the trace arrived at the stop
+    pub arrival: chrono::NaiveDateTime,
+    /// When the trace departed from the stop
+    pub departure: chrono::NaiveDateTime,
+}
+
+impl Stop {
+    /// How long the trace dwelled at the stop
+    pub fn duration(&self) -> chrono::TimeDelta {
+        self.departure - self.arrival
+    }
+}
+
+/// Detects stops, i.e. clusters of consecutive points staying within `radius` meters for at least `min_duration`
+///
+/// Useful for verifying visits, e.g. of delivery addresses. The trace must be ordered by time.
+/// GPS noise while standing still is tolerated as long as it stays within the `radius`.
+pub fn detect_stops(
+    trace: &[TracePoint],
+    radius: f64,
+    min_duration: chrono::TimeDelta,
+) -> Vec<Stop> {
+    let mut stops = Vec::new();
+    let mut first = 0;
+    while first < trace.len() {
+        let mut center = trace[first].point.clone();
+        let mut last = first;
+        while let Some(next) = trace.get(last + 1) {
+            if center.distance_to(&next.point) > radius {
+                break;
+            }
+            last += 1;
+            // the running mean of the cluster's points
+            let n = (last - first + 1) as f64;
+            center.lon += (next.point.lon - center.lon) / n;
+            center.lat += (next.point.lat - center.lat) / n;
+        }
+        let (arrival, departure) = (trace[first].time, trace[last].time);
+        if departure - arrival >= min_duration {
+            stops.push(Stop {
+                first_index: first,
+                last_index: last,
+                location: center,
+                arrival,
+                departure,
+            });
+        }
+        first = last + 1;
+    }
+    stops
+}
+
+/// A part of a matched trace driven faster than the speed limit, see [`Response::speeding`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Speeding {
+    /// The index of the trace point at the start of the part
+    pub first_index: usize,
+    /// The index of the trace point at the end of the part
+    pub last_index: usize,
+    /// The matched position at the start of the part
+    pub from: ShapePoint,
+    /// The matched position at the end of the part
+    pub to: ShapePoint,
+    /// The index of the [`Edge`] at the start of the part
+    pub edge_index: usize,
+    /// The average speed driven in km/h resp. mph
+    pub speed: f64,
+    /// The posted speed limit in km/h resp. mph
+    pub speed_limit: f64,
+}
+
+/// A point of a trace to be matched, see [`Manifest::shape`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Location {
+    lat: f64,
+    lon: f64,
+    #[serde(rename = "type")]
+    r#type: Option<LocationType>,
+    /// Seconds since the unix epoch
+    time: Option<i64>,
+    radius: Option<u32>,
+    heading: Option<u32>,
+    heading_tolerance: Option<u32>,
+}
+
+impl Location {
+    /// Creates a trace point from a longitude/latitude in degrees
+    pub fn new(longitude: f64, latitude: f64) -> Self {
+        Self {
+            lat: latitude,
+            lon: longitude,
+            ..Default::default()
+        }
+    }
+    /// When the point was recorded, used when [`Manifest::use_timestamps`] is set
+    ///
+    /// Only the differences between the times matter, so the time zone is irrelevant as long as it is the same for all points.
+    pub fn time(mut self, time: chrono::NaiveDateTime) -> Self {
+        self.time = Some(time.and_utc().timestamp());
+        self
+    }
+    /// The accuracy of the point in meters, i.e. how far from it candidate roads are searched
+    ///
+    /// Default: the [`Manifest::search_radius`]
+    pub fn radius(mut self, meters: u32) -> Self {
+        self.radius = Some(meters);
+        self
+    }
+    /// The direction of travel in degrees, clockwise from north, e.g. the course over ground of a GPS fix
+    ///
+    /// Helps to match the point to the right carriageway of divided highways.
+    /// See [`crate::route::Location::heading`].
+    pub fn heading(mut self, heading: u32) -> Self {
+        self.heading = Some(heading % 360);
+        self
+    }
+    /// How close in degrees the heading of a road must be to the [`Self::heading`] to match the point to it
+    ///
+    /// Default: `60` degrees
+    pub fn heading_tolerance(mut self, heading_tolerance: u32) -> Self {
+        self.heading_tolerance = Some(heading_tolerance);
+        self
+    }
+    /// Where the matched trip is split into legs, see [`LocationType`]
+    ///
+    /// Default: [`LocationType::Break`] for the first and last point, [`LocationType::Via`] otherwise
+    pub fn r#type(mut self, r#type: LocationType) -> Self {
+        self.r#type = Some(r#type);
+        self
+    }
+}
+
+impl From<&TracePoint> for Location {
+    fn from(point: &TracePoint) -> Self {
+        Self::new(point.point.lon, point.point.lat).time(point.time)
+    }
+}
+
+#[cfg(feature = "gpx")]
+impl Location {
+    /// Creates trace points from the points of all tracks of a GPX document, keeping their times
+    pub fn from_gpx(gpx: &gpx::Gpx) -> Vec<Self> {
+        gpx.tracks
+            .iter()
+            .flat_map(|track| &track.segments)
+            .flat_map(|segment| &segment.points)
+            .map(|waypoint| {
+                let point = waypoint.point();
+                let location = Self::new(point.x(), point.y());
+                let time = waypoint
+                    .time
+                    .and_then(|time| time.format().ok())
+                    .and_then(|time| chrono::DateTime::parse_from_rfc3339(&time).ok());
+                match time {
+                    Some(time) => location.time(time.naive_utc()),
+                    None => location,
+                }
+            })
+            .collect()
+    }
+}
+
+/// How the trace is matched to the road network
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapeMatch {
+    /// Walks along the edges of the graph, for shapes which are known to follow the roads exactly,
+    /// e.g. shapes previously returned by valhalla
+    EdgeWalk,
+    /// Snaps the points to the roads using a hidden markov model, for noisy GPS traces
+    MapSnap,
+    /// Tries [`Self::EdgeWalk`] first, falling back to [`Self::MapSnap`]
+    #[default]
+    WalkOrSnap,
+}
+
+/// Whether the [`Manifest::include_attributes`] or [`Manifest::exclude_attributes`] are returned
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum FilterAction {
+    Include,
+    Exclude,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+struct Filters {
+    attributes: Vec<String>,
+    action: FilterAction,
+}
+
+/// Options for matching noisy traces
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+struct TraceOptions {
+    search_radius: Option<f64>,
+    gps_accuracy: Option<f64>,
+    breakage_distance: Option<f64>,
+    interpolation_distance: Option<f64>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Default, Debug, Clone)]
+pub struct Manifest {
+    #[serde(flatten)]
+    costing: Option<costing::Costing>,
+    shape: Vec<Location>,
+    shape_match: Option<ShapeMatch>,
+    trace_options: Option<TraceOptions>,
+    use_timestamps: Option<bool>,
+    units: Option<crate::Units>,
+    id: Option<String>,
+    language: Option<String>,
+    directions_type: Option<DirectionsType>,
+    shape_format: Option<ShapeFormat>,
+    filters: Option<Filters>,
+}
+
+impl Manifest {
+    #[must_use]
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Sets the points of the trace, in the order they were recorded
+    pub fn shape(mut self, shape: impl IntoIterator<Item = Location>) -> Self {
+        self.shape = shape.into_iter().collect();
+        self
+    }
+    /// Sets the trace to a sketched line, e.g. drawn freehand on a map, so the matched trip follows the roads along it
+    ///
+    /// The sketch is [densified](crate::shapes::densify) with a point every `spacing` meters and
+    /// matched via [`ShapeMatch::MapSnap`], searching for roads within a few spacings of the sketch.
+    /// See [`crate::Valhalla::route_along`] for a shortcut.
+    pub fn sketch(self, sketch: &[ShapePoint], spacing: f64) -> Self {
+        let points = crate::shapes::densify(sketch, spacing);
+        self.shape(points.iter().map(|p| Location::new(p.lon, p.lat)))
+            .shape_match(ShapeMatch::MapSnap)
+            .search_radius(2.0 * spacing)
+            .breakage_distance(2_000_f64.max(4.0 * spacing))
+    }
+    /// Configures the costing model
+    ///
+    /// Default: [`costing::Costing::Auto`]
+    pub fn costing(mut self, costing: costing::Costing) -> Self {
+        self.costing = Some(costing);
+        self
+    }
+    /// Sets how the trace is matched to the road network
+    ///
+    /// Default: [`ShapeMatch::WalkOrSnap`]
+    pub fn shape_match(mut self, shape_match: ShapeMatch) -> Self {
+        self.shape_match = Some(shape_match);
+        self
+    }
+    /// Uses the [`Location::time`]s of the points instead of the estimated travel times
+    ///
+    /// Default: `false`
+    pub fn use_timestamps(mut self, use_timestamps: bool) -> Self {
+        self.use_timestamps = Some(use_timestamps);
+        self
+    }
+    /// How far in meters from each point candidate roads are searched, see [`Location::radius`]
+    ///
+    /// Default: `50`, limited by the instance
+    pub fn search_radius(mut self, meters: f64) -> Self {
+        self.trace_options
+            .get_or_insert_with(Default::default)
+            .search_radius = Some(meters);
+        self
+    }
+    /// The accuracy of the GPS points in meters
+    ///
+    /// Default: `5`
+    pub fn gps_accuracy(mut self, meters: f64) -> Self {
+        self.trace_options
+            .get_or_insert_with(Default::default)
+            .gps_accuracy = Some(meters);
+        self
+    }
+    /// The distance in meters between two consecutive points above which the trace is split into legs
+    ///
+    /// Default: `2000`
+    pub fn breakage_distance(mut self, meters: f64) -> Self {
+        self.trace_options
+            .get_or_insert_with(Default::default)
+            .breakage_distance = Some(meters);
+        self
+    }
+    /// The distance in meters within which consecutive points are merged, e.g. while standing still
+    ///
+    /// Default: `10`
+    pub fn interpolation_distance(mut self, meters: f64) -> Self {
+        self.trace_options
+            .get_or_insert_with(Default::default)
+            .interpolation_distance = Some(meters);
+        self
+    }
+    /// Returns only the given attributes from [`crate::Valhalla::trace_attributes`]
+    ///
+    /// Attributes are named after their key in the response, e.g. `edge.way_id`, `edge.names`,
+    /// `matched.type` or `shape`.
+    ///
+    /// Default: all attributes
+    pub fn include_attributes(
+        mut self,
+        attributes: impl IntoIterator<Item = impl ToString>,
+    ) -> Self {
+        self.filters = Some(Filters {
+            attributes: attributes.into_iter().map(|a| a.to_string()).collect(),
+            action: FilterAction::Include,
+        });
+        self
+    }
+    /// Returns all but the given attributes from [`crate::Valhalla::trace_attributes`]
+    ///
+    /// See [`Self::include_attributes`] for the names of the attributes.
+    pub fn exclude_attributes(
+        mut self,
+        attributes: impl IntoIterator<Item = impl ToString>,
+    ) -> Self {
+        self.filters = Some(Filters {
+            attributes: attributes.into_iter().map(|a| a.to_string()).collect(),
+            action: FilterAction::Exclude,
+        });
+        self
+    }
+    /// Sets the distance units for output, see [`crate::route::Manifest::units`]
+    ///
+    /// Default: [`crate::Units::Metric`]
+    pub fn units(mut self, units: crate::Units) -> Self {
+        self.units = Some(units);
+        self
+    }
+    /// Name your trace request.
+    ///
+    /// If id is specified, the naming will be sent through to the response.
+    pub fn id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+    /// The language of the narration instructions, see [`crate::route::Manifest::language`]
+    ///
+    /// Default: `en-US`
+    pub fn language(mut self, language: impl ToString) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+    /// Sets the directions type, see [`DirectionsType`]
+    ///
+    /// Default: [`DirectionsType::Instructions`]
+    pub fn directions_type(mut self, directions_type: DirectionsType) -> Self {
+        self.directions_type = Some(directions_type);
+        self
+    }
+    /// Specifies the [`ShapeFormat`] of the matched path
+    ///
+    /// The shapes are decoded accordingly by [`crate::Valhalla::trace_route`] and [`crate::Valhalla::trace_attributes`].
+    ///
+    /// Default: [`ShapeFormat::Polyline6`]
+    pub fn shape_format(mut self, shape_format: ShapeFormat) -> Self {
+        self.shape_format = Some(shape_format);
+        self
+    }
+    /// A route between the first and last point of the trace, with the same costing and output options
+    ///
+    /// Returns [`None`] for traces of fewer than two points.
+    pub(crate) fn route_between_endpoints(&self) -> Option<crate::route::Manifest> {
+        let (first, last) = match self.shape.as_slice() {
+            [first, .., last] => (first, last),
+            _ => return None,
+        };
+        let endpoint = |l: &Location| crate::route::Location::new(l.lon as f32, l.lat as f32);
+        let mut manifest =
+            crate::route::Manifest::builder().locations([endpoint(first), endpoint(last)]);
+        if let Some(costing) = self.costing.clone() {
+            manifest = manifest.costing(costing);
+        }
+        if let Some(units) = self.units {
+            manifest = manifest.units(units);
+        }
+        if let Some(id) = &self.id {
+            manifest = manifest.id(id);
+        }
+        if let Some(language) = &self.language {
+            manifest = manifest.language(language);
+        }
+        if let Some(directions_type) = self.directions_type {
+            manifest = manifest.directions_type(directions_type);
+        }
+        if let Some(shape_format) = self.shape_format {
+            manifest = manifest.shape_format(shape_format);
+        }
+        Some(manifest)
+    }
+}
+
+/// The importance of a road, from most to least important
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum RoadClass {
+    Motorway,
+    Trunk,
+    Primary,
+    Secondary,
+    Tertiary,
+    Unclassified,
+    Residential,
+    ServiceOther,
+}
+
+/// The surface of a road, from smoothest to roughest
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Surface {
+    PavedSmooth,
+    Paved,
+    PavedRough,
+    Compacted,
+    Dirt,
+    Gravel,
+    Path,
+    Impassable,
+}
+
+/// The directions in which an edge can be travelled
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Traversability {
+    Forward,
+    Backward,
+    Both,
+}
+
+/// A road segment of the matched path
+///
+/// Attributes are `None` if they are not known or filtered out, see [`Manifest::include_attributes`].
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Edge {
+    /// The names of the road, e.g. street names and route numbers
+    pub names: Option<Vec<String>>,
+    /// The length in [`Response::units`]
+    pub length: Option<f64>,
+    /// The estimated speed in km/h resp. mph
+    pub speed: Option<f64>,
+    /// The posted speed limit in km/h resp. mph
+    pub speed_limit: Option<f64>,
+    /// Where the [`Self::speed`] originates from
+    pub speed_type: Option<crate::graph::SpeedType>,
+    /// The relative road density around the edge, from `0` (rural) to `15` (dense urban)
+    pub density: Option<u32>,
+    /// Whether the edge is in an urban area
+    pub is_urban: Option<bool>,
+    pub road_class: Option<RoadClass>,
+    pub surface: Option<Surface>,
+    pub traversability: Option<Traversability>,
+    /// How the road is used, e.g. a ramp or cycleway
+    #[serde(rename = "use")]
+    pub use_: Option<crate::graph::EdgeUse>,
+    /// The OpenStreetMap way the edge is part of
+    pub way_id: Option<u64>,
+    /// The id of the edge in valhalla's graph
+    pub id: Option<crate::graph::GraphId>,
+    pub lane_count: Option<u32>,
+    /// The bearing in degrees at the start of the edge, clockwise from north
+    pub begin_heading: Option<u32>,
+    /// The bearing in degrees at the end of the edge, clockwise from north
+    pub end_heading: Option<u32>,
+    /// The index of the first point of the edge in [`Response::shape`]
+    pub begin_shape_index: Option<usize>,
+    /// The index of the last point of the edge in [`Response::shape`]
+    pub end_shape_index: Option<usize>,
+    pub toll: Option<bool>,
+    pub tunnel: Option<bool>,
+    pub bridge: Option<bool>,
+    pub roundabout: Option<bool>,
+    /// The kind of cycle lane along the road
+    pub cycle_lane: Option<crate::graph::CycleLane>,
+    /// The cycle networks the road is part of
+    pub bicycle_network: Option<crate::graph::BicycleNetwork>,
+    /// Whether the road has a shoulder, which cyclists may use
+    pub shoulder: Option<bool>,
+    /// The node at the end of the edge, e.g. an intersection
+    pub end_node: Option<EndNode>,
+    /// Further attributes, e.g. `sign`, `sidewalk` or `speeds_faded`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The node at the end of an [`Edge`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EndNode {
+    /// The index of the country and state of the node in [`Response::admins`]
+    pub admin_index: Option<usize>,
+    /// What the node represents, e.g. an intersection or toll booth
+    #[serde(rename = "type")]
+    pub type_: Option<crate::graph::NodeType>,
+    /// Further attributes, e.g. `time_zone` or `intersecting_edges`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// How a trace point was matched
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchType {
+    /// No road was found close enough to the point
+    Unmatched,
+    /// The point was too close to the previous one, so its position was interpolated along the path
+    Interpolated,
+    /// The point was matched to a road
+    Matched,
+}
+
+/// A point of the trace together with where it was matched to
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MatchedPoint {
+    /// The matched latitude, or the one of the trace point if it is [`MatchType::Unmatched`]
+    pub lat: f64,
+    /// The matched longitude, or the one of the trace point if it is [`MatchType::Unmatched`]
+    pub lon: f64,
+    #[serde(rename = "type")]
+    pub type_: MatchType,
+    /// The index of the [`Edge`] the point was matched to
+    pub edge_index: Option<usize>,
+    /// How far along the edge the point is, as fraction of its length
+    pub distance_along_edge: Option<f64>,
+    /// The distance in meters between the trace point and the matched point
+    pub distance_from_trace_point: Option<f64>,
+    /// Whether the path is interrupted before this point, e.g. due to a gap in the trace
+    pub begin_route_discontinuity: Option<bool>,
+    /// Whether the path is interrupted after this point
+    pub end_route_discontinuity: Option<bool>,
+}
+
+/// The attributes of a matched trace, see [`crate::Valhalla::trace_attributes`]
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(
+    serialize = "S: ShapeRepresentation",
+    deserialize = "S: ShapeRepresentation"
+))]
+pub struct Response<S = Vec<ShapePoint>> {
+    /// The id set via [`Manifest::id`]
+    pub id: Option<String>,
+    /// The road segments of the matched path, in order
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+    /// The points of the trace, in the order of [`Manifest::shape`]
+    #[serde(default)]
+    pub matched_points: Vec<MatchedPoint>,
+    /// The matched path
+    #[serde(
+        default,
+        serialize_with = "crate::shapes::serialize_shape_opt",
+        deserialize_with = "crate::shapes::deserialize_shape_opt"
+    )]
+    pub shape: Option<S>,
+    #[serde(default)]
+    pub units: crate::Units,
+    /// How likely the match is correct, between `0` (unlikely) and `1` (likely)
+    pub confidence_score: Option<f64>,
+    /// The countries and states the matched path passes through, see [`EndNode::admin_index`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub admins: Vec<crate::locate::Admin>,
+    /// The access restrictions of the edges, see [`Self::restrictions_of`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub restrictions: Vec<crate::graph::AccessRestriction>,
+    /// Further attributes, e.g. `osm_changeset`
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Response<Shape> {
+    /// Decodes the matched path, given the [`ShapeFormat`] it was requested in
+    pub fn decode(self, format: ShapeFormat) -> Response {
+        Response {
+            id: self.id,
+            edges: self.edges,
+            matched_points: self.matched_points,
+            shape: self.shape.map(|shape| shape.decode_as(format)),
+            units: self.units,
+            confidence_score: self.confidence_score,
+            admins: self.admins,
+            restrictions: self.restrictions,
+            other: self.other,
+        }
+    }
+}
+
+impl<S> Response<S> {
+    /// The number of toll booths and gantries passed on the matched path
+    ///
+    /// Requires the `node.type` attribute, see [`Manifest::include_attributes`].
+    pub fn toll_crossings(&self) -> usize {
+        self.edges
+            .iter()
+            .filter_map(|edge| edge.end_node.as_ref()?.type_)
+            .filter(|type_| type_.is_toll())
+            .count()
+    }
+    /// The parts of the trace driven more than `tolerance` km/h resp. mph faster than the speed limit
+    ///
+    /// The `trace` are the recorded positions sent via [`Manifest::shape`], in the same order.
+    /// The speed between consecutive matched points follows from their times and the distance along the
+    /// matched path between them. It is compared with the highest speed limit of the edges in between,
+    /// so parts without a known speed limit are never flagged.
+    /// Requires the `edge.length`, `edge.speed_limit`, `matched.edge_index` and
+    /// `matched.distance_along_edge` attributes, see [`Manifest::include_attributes`].
+    pub fn speeding(&self, trace: &[TracePoint], tolerance: f64) -> Vec<Speeding> {
+        let mut edge_starts = Vec::with_capacity(self.edges.len());
+        let mut length = 0.0;
+        for edge in &self.edges {
+            edge_starts.push(length);
+            length += edge.length.unwrap_or_default();
+        }
+        // the index of the edge and the distance along the matched path of each matched point
+        let positions: Vec<Option<(usize, f64)>> = self
+            .matched_points
+            .iter()
+            .map(|point| {
+                let index = point
+                    .edge_index
+                    .filter(|_| point.type_ != MatchType::Unmatched)?;
+                let along = point.distance_along_edge? * self.edges.get(index)?.length?;
+                Some((index, edge_starts[index] + along))
+            })
+            .collect();
+        let mut speeding = Vec::new();
+        for i in 1..positions.len().min(trace.len()) {
+            let (Some((first_edge, start)), Some((last_edge, end))) =
+                (positions[i - 1], positions[i])
+            else {
+                continue;
+            };
+            let (first, last) = (&self.matched_points[i - 1], &self.matched_points[i]);
+            if first.end_route_discontinuity == Some(true)
+                || last.begin_route_discontinuity == Some(true)
+            {
+                continue;
+            }
+            let hours = (trace[i].time - trace[i - 1].time).num_milliseconds() as f64 / 3_600_000.0;
+            let Some(speed_limit) = self.edges
+                [first_edge.min(last_edge)..=last_edge.max(first_edge)]
+                .iter()
+                .filter_map(|edge| edge.speed_limit)
+                .reduce(f64::max)
+            else {
+                continue;
+            };
+            let speed = (end - start).abs() / hours;
+            if hours > 0.0 && speed > speed_limit + tolerance {
+                speeding.push(Speeding {
+                    first_index: i - 1,
+                    last_index: i,
+                    from: ShapePoint {
+                        lon: first.lon,
+                        lat: first.lat,
+                    },
+                    to: ShapePoint {
+                        lon: last.lon,
+                        lat: last.lat,
+                    },
+                    edge_index: first_edge,
+                    speed,
+                    speed_limit,
+                });
+            }
+        }
+        speeding
+    }
+    /// The access restrictions of the edge at `edge_index`, e.g. to check that a matched truck trip
+    /// did not cross a weight restricted bridge
+    pub fn restrictions_of(
+        &self,
+        edge_index: usize,
+    ) -> impl Iterator<Item = &crate::graph::AccessRestriction> {
+        self.restrictions
+            .iter()
+            .filter(move |restriction| restriction.edge_index == Some(edge_index))
+    }
+}
+
+impl Response {
+    /// The country and state of the matched path at a `point`, e.g. to assign a tax or toll jurisdiction
+    ///
+    /// Uses the [`Edge`] with the point of the matched [`Self::shape`] nearest to `point`, and the admin at its end.
+    /// Requires the shape, the edges' shape indices and `node.admin_index` as well as `admin.*` attributes,
+    /// see [`Manifest::include_attributes`].
+    pub fn admin_at(&self, point: &ShapePoint) -> Option<&crate::locate::Admin> {
+        let (nearest, _) = self
+            .shape
+            .iter()
+            .flatten()
+            .map(|p| p.distance_to(point))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        let edge = self.edges.iter().find(|edge| {
+            let begin = edge.begin_shape_index.unwrap_or(usize::MAX);
+            let end = edge.end_shape_index.unwrap_or_default();
+            (begin..=end).contains(&nearest)
+        })?;
+        let index = edge.end_node.as_ref()?.admin_index?;
+        self.admins.get(index)
+    }
+}
+
+/// What a [`Heatmap`] counts traversals of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapKey {
+    /// OpenStreetMap ways, see [`Edge::way_id`]
+    WayId,
+    /// Edges of valhalla's graph, by the [`crate::graph::GraphId`] value of [`Edge::id`]
+    EdgeId,
+}
+
+/// How often a way or edge was traversed, see [`Heatmap`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Traversals {
+    pub count: u64,
+    /// The matched shape of the first traversal
+    pub shape: Vec<ShapePoint>,
+}
+
+/// Counts how often ways or edges are traversed by many matched traces, e.g. for heatmaps of fleet flows
+///
+/// Consecutive edges of a trace with the same key, e.g. the edges of one way, count as one traversal.
+/// Requires the `edge.way_id` resp. `edge.id` attributes, and `edge.begin_shape_index` as well as
+/// `edge.end_shape_index` for the shapes, see [`Manifest::include_attributes`].
+///
+/// # Example:
+/// ```rust,no_run
+/// use valhalla_client::Valhalla;
+/// use valhalla_client::trace::{Heatmap, HeatmapKey, Location, Manifest};
+///
+/// let valhalla = Valhalla::default();
+/// let traces = [[(4.9041, 52.3676), (4.9102, 52.3712)], [(4.9102, 52.3712), (4.9166, 52.3731)]];
+/// let mut heatmap = Heatmap::new(HeatmapKey::WayId);
+/// for trace in traces {
+///   let shape = trace.map(|(lon, lat)| Location::new(lon, lat));
+///   heatmap.add(&valhalla.trace_attributes(Manifest::builder().shape(shape)).unwrap());
+/// }
+/// for (way_id, traversals) in heatmap.traversals() {
+///   println!("way {way_id}: {}", traversals.count);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Heatmap {
+    key: HeatmapKey,
+    traversals: std::collections::HashMap<u64, Traversals>,
+}
+
+impl Heatmap {
+    pub fn new(key: HeatmapKey) -> Self {
+        Self {
+            key,
+            traversals: Default::default(),
+        }
+    }
+    /// Counts the traversals of a matched trace
+    pub fn add(&mut self, response: &Response) {
+        let shape = response.shape.as_deref().unwrap_or_default();
+        let mut previous: Option<(u64, Vec<ShapePoint>)> = None;
+        for edge in &response.edges {
+            let key = match self.key {
+                HeatmapKey::WayId => edge.way_id,
+                HeatmapKey::EdgeId => edge.id.map(|id| id.0),
+            };
+            let edge_shape = match (edge.begin_shape_index, edge.end_shape_index) {
+                (Some(begin), Some(end)) => shape.get(begin..=end).unwrap_or_default(),
+                _ => &[],
+            };
+            match (&mut previous, key) {
+                (Some((previous_key, traversal)), Some(key)) if *previous_key == key => {
+                    // the first point is the last one of the previous edge
+                    traversal.extend(edge_shape.iter().skip(1).cloned());
+                }
+                _ => {
+                    if let Some((key, traversal)) = previous.take() {
+                        self.count(key, traversal);
+                    }
+                    previous = key.map(|key| (key, edge_shape.to_vec()));
+                }
+            }
+        }
+        if let Some((key, traversal)) = previous {
+            self.count(key, traversal);
+        }
+    }
+    fn count(&mut self, key: u64, shape: Vec<ShapePoint>) {
+        self.traversals
+            .entry(key)
+            .or_insert(Traversals { count: 0, shape })
+            .count += 1;
+    }
+    /// The traversals per way id resp. edge id, see [`HeatmapKey`]
+    pub fn traversals(&self) -> &std::collections::HashMap<u64, Traversals> {
+        &self.traversals
+    }
+    /// The traversals as GeoJSON `FeatureCollection` of `LineString`s, with the `count` and the
+    /// `way_id` resp. `edge_id` as properties
+    #[cfg(feature = "geojson")]
+    pub fn to_geojson(&self) -> Result<geojson::FeatureCollection, serde_json::Error> {
+        let key = match self.key {
+            HeatmapKey::WayId => "way_id",
+            HeatmapKey::EdgeId => "edge_id",
+        };
+        let features: Vec<_> = self
+            .traversals
+            .iter()
+            .map(|(id, traversals)| {
+                let coordinates: Vec<_> = traversals.shape.iter().map(|p| [p.lon, p.lat]).collect();
+                serde_json::json!({
+                    "type": "Feature",
+                    "properties": {key: id, "count": traversals.count},
+                    "geometry": {"type": "LineString", "coordinates": coordinates},
+                })
+            })
+            .collect();
+        serde_json::from_value(
+            serde_json::json!({"type": "FeatureCollection", "features": features}),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn stops() {
+        let start = chrono::NaiveDate::from_ymd_opt(2024, 11, 7)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap();
+        // driving north at roughly 11 m per 10 seconds, with a 5 minute stop in between
+        let lats = (0..5)
+            .map(|i| 52.0 + f64::from(i) * 0.0001)
+            .chain([52.0005, 52.000_52, 52.000_49, 52.0005])
+            .chain((1..4).map(|i| 52.0005 + f64::from(i) * 0.0001));
+        let times = (0..5)
+            .map(|i| i * 10)
+            .chain([50, 150, 250, 350])
+            .chain([360, 370, 380]);
+        let trace: Vec<_> = lats
+            .zip(times)
+            .map(|(lat, seconds)| TracePoint {
+                point: ShapePoint { lon: 4.0, lat },
+                time: start + chrono::TimeDelta::seconds(seconds),
+            })
+            .collect();
+        let stops = detect_stops(&trace, 10.0, chrono::TimeDelta::minutes(2));
+        assert_eq!(stops.len(), 1);
+        assert_eq!((stops[0].first_index, stops[0].last_index), (5, 8));
+        assert_eq!(stops[0].duration(), chrono::TimeDelta::minutes(5));
+        assert!((stops[0].location.lat - 52.0005).abs() < 1e-5);
+        assert!(detect_stops(&[], 10.0, chrono::TimeDelta::zero()).is_empty());
+    }
+    #[test]
+    fn serialisation() {
+        let time = chrono::DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .naive_utc();
+        let point = TracePoint {
+            point: ShapePoint {
+                lon: 4.9,
+                lat: 52.3,
+            },
+            time,
+        };
+        let manifest = Manifest::builder()
+            .shape([
+                Location::from(&point),
+                Location::new(4.91, 52.31)
+                    .radius(20)
+                    .heading(450)
+                    .heading_tolerance(30),
+            ])
+            .shape_match(ShapeMatch::MapSnap)
+            .gps_accuracy(10.0)
+            .use_timestamps(true)
+            .shape_format(ShapeFormat::Polyline5);
+        assert_eq!(
+            serde_json::to_value(&manifest).unwrap(),
+            serde_json::json!({
+                "shape": [
+                    {"lat": 52.3, "lon": 4.9, "time": 1_700_000_000},
+                    {"lat": 52.31, "lon": 4.91, "radius": 20, "heading": 90, "heading_tolerance": 30},
+                ],
+                "shape_match": "map_snap",
+                "trace_options": {"gps_accuracy": 10.0},
+                "use_timestamps": true,
+                "shape_format": "polyline5",
+            })
+        );
+
+        let sketch = [
+            ShapePoint { lon: 0.0, lat: 0.0 },
+            ShapePoint {
+                lon: 0.0,
+                lat: 0.01,
+            },
+        ];
+        let manifest = serde_json::to_value(Manifest::builder().sketch(&sketch, 100.0)).unwrap();
+        assert_eq!(manifest["shape"].as_array().unwrap().len(), 12);
+        assert_eq!(manifest["shape_match"], "map_snap");
+        assert_eq!(manifest["trace_options"]["search_radius"], 200.0);
+
+        let manifest = Manifest::builder().include_attributes(["edge.way_id", "edge.names"]);
+        assert_eq!(
+            serde_json::to_value(&manifest).unwrap(),
+            serde_json::json!({
+                "shape": [],
+                "filters": {"attributes": ["edge.way_id", "edge.names"], "action": "include"},
+            })
+        );
+    }
+    #[test]
+    fn attributes() {
+        let received = serde_json::json!({
+            "edges": [{
+                "names": ["Damrak"], "length": 0.2, "speed": 30.0, "road_class": "secondary",
+                "surface": "paved_smooth", "traversability": "both", "use": "road", "way_id": 7_249_729,
+                "begin_shape_index": 0, "end_shape_index": 1, "cycle_lane": "dedicated",
+                "bicycle_network": 4, "shoulder": false,
+                "speed_type": "classified", "density": 12, "is_urban": true,
+                "end_node": {"admin_index": 0, "type": "street_intersection"},
+            }],
+            "admins": [{"country_code": "NL", "country_text": "Netherlands", "state_code": "NH", "state_text": "Noord-Holland"}],
+            "restrictions": [{"edge_index": 0, "type": "max_weight", "value": 750, "except_destination": false, "modes": 8}],
+            "matched_points": [
+                {"lat": 52.3, "lon": 4.9, "type": "matched", "edge_index": 0, "distance_along_edge": 0.5, "distance_from_trace_point": 3.2},
+                {"lat": 52.4, "lon": 4.8, "type": "unmatched"},
+            ],
+            "shape": "_izlhA~rlgdF_{geC~ywl@",
+            "units": "kilometers",
+            "confidence_score": 1.0,
+            "osm_changeset": 123,
+        });
+        let response: Response = serde_json::from_value(received.clone()).unwrap();
+        assert_eq!(response.edges[0].road_class, Some(RoadClass::Secondary));
+        assert_eq!(response.edges[0].use_, Some(crate::graph::EdgeUse::Road));
+        assert_eq!(
+            response.edges[0].speed_type,
+            Some(crate::graph::SpeedType::Classified)
+        );
+        assert_eq!(response.edges[0].density, Some(12));
+        assert_eq!(response.edges[0].is_urban, Some(true));
+        assert_eq!(
+            response.edges[0].end_node.as_ref().unwrap().type_,
+            Some(crate::graph::NodeType::StreetIntersection)
+        );
+        assert_eq!(response.toll_crossings(), 0);
+        assert_eq!(
+            response.edges[0].cycle_lane,
+            Some(crate::graph::CycleLane::Dedicated)
+        );
+        assert!(response.edges[0]
+            .bicycle_network
+            .is_some_and(|network| network.contains(crate::graph::BicycleNetwork::LOCAL)));
+        let restrictions: Vec<_> = response.restrictions_of(0).collect();
+        assert_eq!(restrictions.len(), 1);
+        assert_eq!(
+            restrictions[0].type_,
+            crate::graph::AccessRestrictionType::MaxWeight
+        );
+        assert_eq!(response.restrictions_of(1).count(), 0);
+        assert_eq!(response.matched_points[1].type_, MatchType::Unmatched);
+        assert_eq!(response.matched_points[1].edge_index, None);
+        assert_eq!(response.shape.as_ref().map(Vec::len), Some(2));
+        let end = response.shape.as_ref().unwrap()[1].clone();
+        assert_eq!(
+            response
+                .admin_at(&end)
+                .and_then(|a| a.state_code.as_deref()),
+            Some("NH")
+        );
+        assert_eq!(serde_json::to_value(&response).unwrap(), received);
+    }
+    #[test]
+    fn speeding() {
+        let response: Response = serde_json::from_value(serde_json::json!({
+            "edges": [
+                {"length": 0.5, "speed_limit": 50.0},
+                {"length": 1.0, "speed_limit": 100.0},
+                {"length": 1.0},
+            ],
+            "matched_points": [
+                {"lat": 0.0, "lon": 0.0, "type": "matched", "edge_index": 0, "distance_along_edge": 0.0},
+                {"lat": 0.0, "lon": 0.1, "type": "matched", "edge_index": 0, "distance_along_edge": 1.0},
+                {"lat": 0.0, "lon": 0.2, "type": "matched", "edge_index": 1, "distance_along_edge": 0.5},
+                {"lat": 0.0, "lon": 0.3, "type": "unmatched"},
+                {"lat": 0.0, "lon": 0.4, "type": "matched", "edge_index": 2, "distance_along_edge": 0.5},
+                {"lat": 0.0, "lon": 0.5, "type": "matched", "edge_index": 2, "distance_along_edge": 1.0},
+            ],
+        }))
+        .unwrap();
+        let start = chrono::DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .naive_utc();
+        // 500 m per 30 seconds, i.e. 60 km/h
+        let trace: Vec<_> = (0..6)
+            .map(|i| TracePoint {
+                point: ShapePoint { lon: 0.0, lat: 0.0 },
+                time: start + chrono::TimeDelta::seconds(i * 30),
+            })
+            .collect();
+        let speeding = response.speeding(&trace, 5.0);
+        assert_eq!(speeding.len(), 1, "{speeding:?}");
+        assert_eq!((speeding[0].first_index, speeding[0].last_index), (0, 1));
+        assert!((speeding[0].speed - 60.0).abs() < 1e-9);
+        assert_eq!(speeding[0].speed_limit, 50.0);
+        assert_eq!(speeding[0].to, ShapePoint { lon: 0.1, lat: 0.0 });
+        assert!(response.speeding(&trace, 10.0).is_empty());
+    }
+    #[test]
+    fn heatmap() {
+        let point = |lon: f64| ShapePoint { lon, lat: 0.0 };
+        let edge = |way_id: u64, id: u64, begin: usize| {
+            serde_json::json!({
+                "way_id": way_id, "id": id, "begin_shape_index": begin, "end_shape_index": begin + 1,
+            })
+        };
+        let mut response: Response = serde_json::from_value(serde_json::json!({
+            "edges": [edge(1, 10, 0), edge(1, 11, 1), edge(2, 20, 2)],
+        }))
+        .unwrap();
+        response.shape = Some((0..4).map(|i| point(f64::from(i))).collect());
+        let mut ways = Heatmap::new(HeatmapKey::WayId);
+        let mut edges = Heatmap::new(HeatmapKey::EdgeId);
+        for _ in 0..2 {
+            ways.add(&response);
+            edges.add(&response);
+        }
+        let way = &ways.traversals()[&1];
+        assert_eq!(way.count, 2);
+        assert_eq!(way.shape, [point(0.0), point(1.0), point(2.0)]);
+        assert_eq!(ways.traversals()[&2].count, 2);
+        assert_eq!(edges.traversals().len(), 3);
+        assert_eq!(edges.traversals()[&11].shape, [point(1.0), point(2.0)]);
+        #[cfg(feature = "geojson")]
+        {
+            let geojson = ways.to_geojson().unwrap();
+            assert_eq!(geojson.features.len(), 2);
+        }
+    }
+    #[test]
+    fn polyline5_shape() {
+        let response: Response<Shape> =
+            serde_json::from_value(serde_json::json!({"shape": "_p~iF~ps|U_ulLnnqC"})).unwrap();
+        let shape = response.decode(ShapeFormat::Polyline5).shape.unwrap();
+        assert_eq!((shape[1].lon, shape[1].lat), (-120.95, 40.7));
+    }
+}